@@ -1,22 +1,41 @@
 // AI
 // The controller for AI thinking.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::time::Duration;
 
 //use crate::ai_random::AIRandom;
-use crate::ai_minimax::AIMinimax;
 //use crate::ai_monte_carlo::AIMonteCarlo;
 //use crate::ai_monte_carlo_tree::AIMonteCarloTree;
 use crate::ai_sender::{AISender, AIMessage};
 
+use crate::ai_minimax::AIMinimax;
 use crate::controller::Player;
+use crate::evaluation::Evaluator;
 use crate::controller::PlayerKind::*;
 use crate::game::{Game, Move};
+use crate::opening_book::OpeningBook;
+use crate::tablebase::Tablebases;
+use crate::transposition::TranspositionTable;
+
+/// Analysis searches deeper until stopped, but no further than this.
+const ANALYSIS_MAX_DEPTH: usize = 30;
 
 pub trait Think {
     fn think(&mut self) -> AIProgress;
 }
 
+/// One of the best moves found by an analysis, with the line expected to follow it.
+#[derive(Debug, Clone)]
+pub struct PvLine {
+    /// For the player to move at the root.
+    pub score: f64,
+    /// Starts with the root move.
+    pub pv: Vec<Move>,
+}
+
 #[derive(Debug, Clone)]
 pub struct AIProgress {
     pub is_complete: bool,
@@ -26,6 +45,18 @@ pub struct AIProgress {
     pub percent_complete: f64,
     pub score: f64,
     pub best_node: Option<Game>,
+    /// True if best_node came from the opening book rather than a search.
+    pub from_book: bool,
+    /// The deepest completed search. Set by analysis.
+    pub depth: usize,
+    /// The best root moves, best first. Set by analysis.
+    pub lines: Vec<PvLine>,
+}
+
+impl Default for AIProgress {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AIProgress {
@@ -38,22 +69,75 @@ impl AIProgress {
             percent_complete: 0.0,
             score: 0.0,
             best_node: None,
+            from_book: false,
+            depth: 0,
+            lines: Vec::new(),
         }
     }
 }
 
+/// What a search uses besides the game: the evaluator, the shared tables and the time
+/// it may take.
+#[derive(Clone)]
+pub struct AIContext {
+    pub evaluator: Evaluator,
+    pub book: Option<Arc<OpeningBook>>,
+    pub tablebases: Option<Arc<Tablebases>>,
+    pub transposition_table: Option<Arc<TranspositionTable>>,
+    /// From the game clock's time manager. See Clock::move_budget().
+    pub time_budget: Option<Duration>,
+}
+
 pub struct AI {}
 
 impl AI {
-    pub fn think(player: Player, game: Game, mut message_sender: AISender) {
+    pub fn think(player: Player, game: Game, context: AIContext, mut message_sender: AISender) {
+        let progress = AI::search(&player, game, context, &message_sender, None);
+        message_sender.send(AIMessage::SearchCompleted(progress));
+    }
+
+    /// Searches the position expected after the opponent's reply, on the opponent's time.
+    /// If the opponent plays that reply, the controller clears pondering and the search
+    /// goes on as the AI's turn, sending its result as think() does. Otherwise the
+    /// controller sets stop, and nothing is sent.
+    pub fn ponder(player: Player, game: Game, context: AIContext, pondering: Arc<AtomicBool>, stop: Arc<AtomicBool>, mut message_sender: AISender) {
+        let control = (pondering.clone(), stop.clone());
+        let progress = AI::search(&player, game, context, &message_sender, Some(control));
+        // A search that finishes early waits for the opponent's move.
+        while pondering.load(Ordering::Relaxed) && !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        if stop.load(Ordering::Relaxed) { return }
+        message_sender.send(AIMessage::SearchCompleted(progress));
+    }
+
+    /// The search for think() and ponder(). ponder holds the pondering and stop flags.
+    fn search(player: &Player, game: Game, context: AIContext, message_sender: &AISender, ponder: Option<(Arc<AtomicBool>, Arc<AtomicBool>)>) -> AIProgress {
+        // Play from the opening book while the position is in it.
+        if player.use_book {
+            if let Some(progress) = context.book.and_then(|book| AI::book_move(&book, &game)) {
+                return progress;
+            }
+        }
+
         let mut sender_clone = message_sender.clone();
         
-        let progress: AIProgress = match player.kind {
+        match player.kind {
             
             AI => {
                 sender_clone.min_time_between = Some(Duration::from_millis(100));
-                let mut ai = AIMinimax::new(game, player.search_depth, sender_clone);
-                ai.think()
+                let mut ai = player.strength.minimax(game, context.evaluator, sender_clone);
+                ai.tablebases = context.tablebases;
+                ai.transposition_table = context.transposition_table;
+                // A level searching to a fixed depth also stops when its budget is spent.
+                if let Some(budget) = context.time_budget {
+                    ai.time_limit = Some(ai.time_limit.map_or(budget, |limit| limit.min(budget)));
+                }
+                if let Some((pondering, stop)) = ponder {
+                    ai.pondering = Some(pondering);
+                    ai.stop_signal = Some(stop);
+                }
+                player.strength.think(&mut ai, &mut fastrand::Rng::new())
             },
             // AIRandom => {
             //     let mut ai = AIRandom::new(game, sender_clone);
@@ -70,7 +154,41 @@ impl AI {
             //     ai.think()
             // },
             _ => {panic!("AI::think panic!")},
-        };
+        }
+    }
+
+    /// Analyzes the game for the player to move, reporting the best line_count moves as
+    /// the search deepens, until stop is set or the search runs out of depth.
+    pub fn analyze(game: Game, context: AIContext, line_count: usize, stop: Arc<AtomicBool>, mut message_sender: AISender) {
+        let mut sender_clone = message_sender.clone();
+        sender_clone.min_time_between = Some(Duration::from_millis(100));
+        let mut ai = AIMinimax::new(game, ANALYSIS_MAX_DEPTH, context.evaluator, sender_clone);
+        ai.tablebases = context.tablebases;
+        ai.transposition_table = context.transposition_table;
+        ai.stop_signal = Some(stop);
+        let progress = ai.analyze(line_count);
         message_sender.send(AIMessage::SearchCompleted(progress));
     }
-}
\ No newline at end of file
+
+    /// Searches for a move to suggest to a human player, at their hint strength.
+    pub fn hint(player: Player, game: Game, context: AIContext, tx: Sender<AIProgress>) {
+        let mut ai = player.hint_strength.minimax(game, context.evaluator, AISender::muted());
+        ai.tablebases = context.tablebases;
+        ai.transposition_table = context.transposition_table;
+        let progress = player.hint_strength.think(&mut ai, &mut fastrand::Rng::new());
+        // The receiver is gone if the human moved meanwhile.
+        let _ = tx.send(progress);
+    }
+
+    fn book_move(book: &OpeningBook, game: &Game) -> Option<AIProgress> {
+        let mut rng = fastrand::Rng::new();
+        let node = book.choose(game, &mut rng)?;
+        let mut progress = AIProgress::new();
+        progress.is_complete = true;
+        progress.percent_complete = 1.0;
+        progress.pv = node.last_move.into_iter().collect();
+        progress.best_node = Some(node);
+        progress.from_book = true;
+        Some(progress)
+    }
+}
@@ -1,16 +1,37 @@
 // AI Minimax
 
-use std::time::Instant;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::ai::{AIProgress, Think};
+use crate::ai::{AIProgress, PvLine, Think};
+use crate::evaluation::Evaluator;
 use crate::game::{Game, GameState};
 use crate::game::Move;
 use crate::ai_sender::{AIMessage, AISender};
-use crate::piece::PieceKind::*;
+use crate::tablebase::{TablebaseResult, Tablebases};
+use crate::transposition::{Bound, TableEntry, TranspositionTable};
+
+pub const WIN_LOSS_VAL: f64 = 1000.0;
 
 pub struct AIMinimax {
     game: Game, // a clone of the original
     depth: usize,
+    evaluator: Evaluator,
+    /// Endgame tables, probed at the root and at the leaves.
+    pub tablebases: Option<Arc<Tablebases>>,
+    /// If set, search deeper one ply at a time until depth is reached or time runs out,
+    /// and play the best move of the deepest completed search.
+    pub time_limit: Option<Duration>,
+    /// If set, the search stops as soon as it becomes true, as if out of time.
+    pub stop_signal: Option<Arc<AtomicBool>>,
+    /// If set, the search is pondering (searching on the opponent's time) while it is
+    /// true, and ignores time_limit. The limit counts from when it becomes false.
+    pub pondering: Option<Arc<AtomicBool>>,
+    /// Shared with later searches, which start out knowing what this one found.
+    pub transposition_table: Option<Arc<TranspositionTable>>,
+    stopped: bool, // out of time or signaled
+    finish_depth: bool, // ignore the time limit and stop signal until this search ends
     // Due to the recursive nature of Minimax, we need more persistent fields so we're
     // not passing too many arguments to alpha_beta.
     search_player: usize, // need to remember this before things get hairy
@@ -23,10 +44,22 @@ impl Think for AIMinimax {
     fn think(&mut self) -> AIProgress {
         self.now = std::time::Instant::now();
 
-        // Optimization: consider switching to fixed-size array with index tracker.
-        let mut pv = Vec::new();
+        // A position in the tablebase needs no search.
+        if let Some(progress) = self.tablebase_move() {
+            return progress;
+        }
 
-        let score = self.alpha_beta(self.game, self.depth, true, f64::MIN, f64::MAX, &mut pv);
+        self.evaluator.set_root(&self.game);
+        let (score, pv) = match self.time_limit {
+            Some(_) => self.iterative_deepening(),
+            None => {
+                // Optimization: consider switching to fixed-size array with index tracker.
+                let mut pv = Vec::new();
+                let score = self.alpha_beta(self.game, self.depth, true, f64::MIN, f64::MAX, &mut pv);
+                self.progress.depth = self.depth;
+                (score, pv)
+            },
+        };
         self.progress.duration = self.now.elapsed();
 
         // Use the final version of the pv assembled by alpha_beta.
@@ -37,10 +70,17 @@ impl Think for AIMinimax {
 }
 
 impl AIMinimax {
-    pub fn new(game: Game, depth: usize, message_sender: AISender) -> Self {
+    pub fn new(game: Game, depth: usize, evaluator: Evaluator, message_sender: AISender) -> Self {
         let p = game.current_player;
         Self {
-            game, depth,
+            game, depth, evaluator,
+            tablebases: None,
+            time_limit: None,
+            stop_signal: None,
+            pondering: None,
+            transposition_table: None,
+            stopped: false,
+            finish_depth: false,
             search_player: p,
             now: std::time::Instant::now(),
             progress: AIProgress::new(),
@@ -48,19 +88,192 @@ impl AIMinimax {
         }
     }
 
+    /// Searches depth 1, 2, 3... until self.depth or the time limit. An unfinished
+    /// search is thrown away. Depth 1 always finishes, so there is a move to play however
+    /// soon the search is stopped.
+    fn iterative_deepening(&mut self) -> (f64, Vec<Move>) {
+        let max_depth = self.depth;
+        let mut result = (0.0, Vec::new());
+        let mut best_node = None;
+
+        for depth in 1..=max_depth {
+            self.depth = depth;
+            self.finish_depth = depth == 1;
+            let mut pv = Vec::new();
+            let score = self.alpha_beta(self.game, depth, true, f64::MIN, f64::MAX, &mut pv);
+            self.finish_depth = false;
+            if self.stopped { break }
+
+            result = (score, pv);
+            best_node = self.progress.best_node;
+            self.progress.depth = depth;
+            // Stop early once the outcome is certain.
+            if score.abs() >= WIN_LOSS_VAL - depth as f64 { break }
+        }
+        self.depth = max_depth;
+        // Keep the unfinished search's best move only if nothing finished.
+        if best_node.is_some() {
+            self.progress.best_node = best_node;
+        }
+        result
+    }
+
+    /// Returns every root move with its exact score for the player to move. Slower than
+    /// think(), which only needs to prove the best move is best; used by the weaker
+    /// strength levels to pick moves other than the best. If time_limit runs out, only
+    /// the moves scored by then are returned.
+    pub fn root_scores(&mut self) -> Vec<(Game, f64)> {
+        self.now = std::time::Instant::now();
+        self.evaluator.set_root(&self.game);
+
+        let mut node = self.game;
+        if *node.update_state() != GameState::Ongoing { return Vec::new() }
+        let child_nodes = node.child_nodes(node.current_player);
+        let mut scores = Vec::with_capacity(child_nodes.len());
+        let mut child_pv = Vec::new();
+
+        for (index, child) in child_nodes.iter().enumerate() {
+            self.evaluator.make(&node, child);
+            let score = self.alpha_beta(*child, self.depth.saturating_sub(1), false, f64::MIN, f64::MAX, &mut child_pv);
+            self.evaluator.unmake();
+            // Out of time: keep the moves scored so far, but always have one to play.
+            if self.stopped && !scores.is_empty() { break }
+            scores.push((*child, score));
+
+            self.progress.nodes += 1;
+            self.progress.percent_complete = (index + 1) as f64 / child_nodes.len() as f64;
+            self.progress.duration = self.now.elapsed();
+            self.message_sender.send(AIMessage::AIUpdate(self.progress.clone()));
+        }
+        scores
+    }
+
+    /// Multi-PV analysis: searches depth 1, 2, 3... like iterative_deepening(), but
+    /// keeps the best line_count root moves, each with its own score and pv, rather than
+    /// only the best. Sends an update as each root move is searched. Stops at depth, on
+    /// the time limit or stop signal, or once every line's outcome is certain.
+    pub fn analyze(&mut self, line_count: usize) -> AIProgress {
+        self.now = std::time::Instant::now();
+        self.evaluator.set_root(&self.game);
+        self.progress.is_complete = true;
+
+        let mut node = self.game;
+        if *node.update_state() != GameState::Ongoing { return self.progress.clone() }
+        let mut children = node.child_nodes(node.current_player);
+        let line_count = line_count.clamp(1, children.len().max(1));
+        let max_depth = self.depth;
+
+        for depth in 1..=max_depth {
+            self.depth = depth;
+            // Best first. Moves that can't make the list are only known to be worse.
+            let mut lines: Vec<(Game, PvLine)> = Vec::with_capacity(line_count + 1);
+
+            for (index, child) in children.iter().enumerate() {
+                // A move only needs searching exactly if it might beat the last line.
+                let alpha = if lines.len() < line_count { f64::MIN } else { lines[line_count - 1].1.score };
+                let mut child_pv = Vec::new();
+                self.evaluator.make(&node, child);
+                let score = self.alpha_beta(*child, depth - 1, false, alpha, f64::MAX, &mut child_pv);
+                self.evaluator.unmake();
+                if self.stopped { break }
+                self.progress.nodes += 1;
+
+                if score > alpha {
+                    let mut pv = vec![child.last_move.unwrap()];
+                    pv.append(&mut child_pv);
+                    let position = lines.partition_point(|(_, line)| line.score >= score);
+                    lines.insert(position, (*child, PvLine { score, pv }));
+                    lines.truncate(line_count);
+                }
+
+                // Show this depth's lines once there are enough of them.
+                if lines.len() == line_count {
+                    self.set_lines(&lines);
+                }
+                self.progress.percent_complete = (index + 1) as f64 / children.len() as f64;
+                self.progress.duration = self.now.elapsed();
+                self.message_sender.send(AIMessage::AIUpdate(self.progress.clone()));
+            }
+            if self.stopped { break }
+
+            self.set_lines(&lines);
+            self.progress.depth = depth;
+            // Search the best moves first next time, so the others are cut off sooner.
+            let best: Vec<Game> = lines.iter().map(|(child, _)| *child).collect();
+            children.retain(|child| !best.iter().any(|b| b.last_move == child.last_move));
+            children.splice(0..0, best);
+
+            if lines.iter().all(|(_, line)| line.score.abs() >= WIN_LOSS_VAL / 2.0) { break }
+        }
+        self.depth = max_depth;
+        self.progress.duration = self.now.elapsed();
+        self.progress.percent_complete = 1.0;
+        self.progress.clone()
+    }
+
+    /// Copies the lines to progress, with the best also as best_node, pv and score.
+    fn set_lines(&mut self, lines: &[(Game, PvLine)]) {
+        self.progress.lines = lines.iter().map(|(_, line)| line.clone()).collect();
+        if let Some((child, line)) = lines.first() {
+            self.progress.best_node = Some(*child);
+            self.progress.pv = line.pv.clone();
+            self.progress.score = line.score;
+        }
+    }
+
     fn alpha_beta(&mut self, mut node: Game, depth: usize, maximizing: bool, mut alpha: f64, mut beta: f64, pv: &mut Vec<Move>) -> f64 {
+        if !self.finish_depth {
+            if let Some(limit) = self.time_limit {
+                if !self.check_pondering() && self.now.elapsed() >= limit {
+                    self.stopped = true;
+                }
+            }
+            if let Some(signal) = &self.stop_signal {
+                if signal.load(Ordering::Relaxed) {
+                    self.stopped = true;
+                }
+            }
+        }
+        if self.stopped { return 0.0 }
+
         if *node.update_state() != GameState::Ongoing || depth == 0 {
             pv.clear();
             return self.evaluate(&node, self.depth - depth);
         }
         
+        // A search of this position from before may settle it, or at least tell which
+        // move to try first. The root is always searched, since it needs its best_node.
+        let key = self.transposition_table.as_ref().map(|_| node.position_key());
+        let entry = key.and_then(|key| self.transposition_table.as_ref().unwrap().probe(key));
+        let mut child_nodes = node.child_nodes(node.current_player);
+        if let Some(entry) = entry {
+            if depth < self.depth && entry.depth as usize >= depth {
+                let (score, bound) = self.score_from_table(&node, entry.score as f64, entry.bound, depth);
+                let usable = match bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if usable {
+                    pv.clear();
+                    pv.extend(entry.best_move.filter(|m| child_nodes.iter().any(|c| c.last_move == Some(*m))));
+                    return score;
+                }
+            }
+            if let Some(first) = child_nodes.iter().position(|c| c.last_move == entry.best_move) {
+                child_nodes.swap(0, first);
+            }
+        }
+        let (alpha_before, beta_before) = (alpha, beta);
+
         let mut child_pv = Vec::new();
-        let child_nodes = node.child_nodes(node.current_player);
 
         // Maximizing
         if maximizing {
-            for (index, node) in child_nodes.iter().enumerate() {
-                let child_score = self.alpha_beta(*node, depth-1, false, alpha, beta, &mut child_pv);
+            for (index, child) in child_nodes.iter().enumerate() {
+                self.evaluator.make(&node, child);
+                let child_score = self.alpha_beta(*child, depth-1, false, alpha, beta, &mut child_pv);
+                self.evaluator.unmake();
                 
                 // Progress reporting
                 self.progress.nodes += 1;
@@ -71,12 +284,12 @@ impl AIMinimax {
                 if child_score > alpha {
                     alpha = child_score;
                     pv.clear();
-                    pv.push(node.last_move.unwrap());
+                    pv.push(child.last_move.unwrap());
                     pv.append(&mut child_pv);
 
                     self.progress.pv = pv.clone();
                     if depth == self.depth {
-                        self.progress.best_node = Some(*node);
+                        self.progress.best_node = Some(*child);
                     }
                     self.progress.duration = self.now.elapsed();
                     self.message_sender.send(AIMessage::AIUpdate(self.progress.clone()));
@@ -86,18 +299,24 @@ impl AIMinimax {
                     break;
                 }
             }
+            if let Some(key) = key {
+                let bound = if alpha >= beta { Bound::Lower } else if alpha <= alpha_before { Bound::Upper } else { Bound::Exact };
+                self.store_in_table(key, &node, alpha, bound, depth, pv.first().copied());
+            }
             alpha
         }
         // Minimizing
         else {
-            for node in &child_nodes {
-                let child_score = self.alpha_beta(*node, depth-1, true, alpha, beta, &mut child_pv);
+            for child in &child_nodes {
+                self.evaluator.make(&node, child);
+                let child_score = self.alpha_beta(*child, depth-1, true, alpha, beta, &mut child_pv);
+                self.evaluator.unmake();
                 self.progress.nodes += 1;
 
                 if child_score < beta {
                     beta = child_score;
                     pv.clear();
-                    pv.push(node.last_move.unwrap());
+                    pv.push(child.last_move.unwrap());
                     pv.append(&mut child_pv);
                 }
                 
@@ -105,15 +324,80 @@ impl AIMinimax {
                     break;
                 }
             }
+            if let Some(key) = key {
+                let bound = if beta <= alpha { Bound::Upper } else if beta >= beta_before { Bound::Lower } else { Bound::Exact };
+                self.store_in_table(key, &node, beta, bound, depth, pv.first().copied());
+            }
             beta
         }
     }
 
+    /// Returns true while pondering. On a ponder hit, restarts the clock for the time
+    /// limit, which only applies from then on.
+    fn check_pondering(&mut self) -> bool {
+        let Some(pondering) = &self.pondering else { return false };
+        if pondering.load(Ordering::Relaxed) { return true }
+        self.pondering = None;
+        self.now = Instant::now();
+        false
+    }
+
+    /// Saves a search result, unless the search was cut short. The table holds scores
+    /// for the player to move, so it suits either side, and wins and losses by their
+    /// distance from the position rather than from the root.
+    fn store_in_table(&self, key: u64, node: &Game, score: f64, bound: Bound, depth: usize, best_move: Option<Move>) {
+        if self.stopped { return }
+        let Some(table) = &self.transposition_table else { return };
+        let ply = (self.depth - depth) as f64;
+        let (mut score, bound) = if node.current_player == self.search_player { (score, bound) } else { (-score, bound.flipped()) };
+        if score >= WIN_LOSS_VAL / 2.0 {
+            score += ply;
+        } else if score <= -WIN_LOSS_VAL / 2.0 {
+            score -= ply;
+        }
+        table.store(key, TableEntry { depth: depth as u8, bound, score: score as f32, best_move });
+    }
+
+    /// The inverse of store_in_table()'s score conversion.
+    fn score_from_table(&self, node: &Game, score: f64, bound: Bound, depth: usize) -> (f64, Bound) {
+        let ply = (self.depth - depth) as f64;
+        let mut score = score;
+        if score >= WIN_LOSS_VAL / 2.0 {
+            score -= ply;
+        } else if score <= -WIN_LOSS_VAL / 2.0 {
+            score += ply;
+        }
+        if node.current_player == self.search_player { (score, bound) } else { (-score, bound.flipped()) }
+    }
+
+    /// Returns the tablebase move at the root, if the root is a known win or loss.
+    /// Known draws are still searched, since the leaves are probed too.
+    fn tablebase_move(&mut self) -> Option<AIProgress> {
+        let (child, result) = self.tablebases.as_ref()?.best_child(&self.game)?;
+        if result == TablebaseResult::Draw { return None }
+
+        self.progress.best_node = Some(child);
+        self.progress.pv = child.last_move.into_iter().collect();
+        self.progress.score = Self::tablebase_score(result, 0);
+        self.progress.percent_complete = 1.0;
+        self.progress.duration = self.now.elapsed();
+        Some(self.progress.clone())
+    }
+
+    /// Scores a tablebase result for the player to move, found ply plies from the root,
+    /// on the same scale as a king capture.
+    fn tablebase_score(result: TablebaseResult, ply: usize) -> f64 {
+        match result {
+            TablebaseResult::Win(distance) => WIN_LOSS_VAL - (ply + distance as usize) as f64,
+            TablebaseResult::Loss(distance) => -WIN_LOSS_VAL + (ply + distance as usize) as f64,
+            TablebaseResult::Draw => 0.0,
+        }
+    }
+
     /// Scores the game from the point of view of search_player.
     /// Depth is used here to make the eval favor winning sooner (low depth) or
     /// losing later (high depth).
     fn evaluate(&self, node: &Game, depth: usize) -> f64 {
-        const WIN_LOSS_VAL: f64 = 1000.0;
         match node.state {
             GameState::Draw => 0.0,
             GameState::WinPlayer0 => {
@@ -131,39 +415,13 @@ impl AIMinimax {
                 }
             },
             GameState::Ongoing => {
-                self.evaluate_pieces(node)
-            }
-        }
-    }
-
-    /// All this does is return the value of the player's pieces vs those of the opponent.
-    /// Lame, but it works on a tactical level.
-    fn evaluate_pieces(&self, node: &Game) -> f64 {
-        let mut p0 = 0.;
-        let mut p1 = 0.;
-        for piece in &node.pieces {
-            let val = match piece.kind {
-                King => 0.,
-                Gold => 9.,
-                Silver => 6.,
-                SilverPro => 7.,
-                Rook => 6.,
-                RookPro => 8.,
-                Bishop => 4.,
-                BishopPro => 3.,
-                Pawn => 1.,
-                PawnPro => 3.,
-            };
-            if piece.player == 0 {
-                p0 += val;
-            } else {
-                p1 += val;
+                if let Some(result) = self.tablebases.as_ref().and_then(|t| t.probe(node)) {
+                    let score = Self::tablebase_score(result, depth);
+                    return if node.current_player == self.search_player { score } else { -score };
+                }
+                self.evaluator.evaluate(node, self.search_player)
             }
         }
-        if self.search_player == 0 {
-            return p0 - p1;
-        }
-        p1 - p0
     }
 
 }
\ No newline at end of file
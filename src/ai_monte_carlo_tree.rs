@@ -1,216 +1,191 @@
-// MonteCarloTree
-// More advanced than MonteCarloPure.
-// This code is based on: https://github.com/wlongxiang/mcts/blob/main/monte_carlo_tree_search.py
-
-// NON-FUNCTIONAL. See critical bug in ucb_select()
-// I think this whole thing will need to be reworked somehow.
-
-// use std::collections::HashMap;
-// use std::time::{Duration, Instant};
-
-// use crate::Action;
-// use crate::ai::AIProgress;
-// use crate::controller::Message;
-// use crate::game::*;
-// use crate::GameState::Ongoing;
-// use crate::message_sender::MessageSender;
-
-// pub struct AIMonteCarloTree {
-//     root: Game,
-//     search_player: u8,
-//     time_limit: Duration,
-//     message_sender: MessageSender,
-
-//     reward: HashMap<Game, f64>, // Q
-//     visits: HashMap<Game, f64>, // N
-//     prev_action: HashMap<Game, Action>,
-//     children: HashMap<Game, Vec<Game>>,
-// }
-
-// impl AIMonteCarloTree {
-//     pub fn new(game: Game, time_limit: Duration, message_sender: MessageSender) -> Self {
-//         Self {
-//             root: game,
-//             search_player: game.current_player as u8,
-//             time_limit,
-//             message_sender,
-//             reward: HashMap::new(),
-//             visits: HashMap::new(),
-//             prev_action: HashMap::new(),
-//             children: HashMap::new(),
-//         }
-//     }
-
-//     pub fn think(&mut self) -> AIProgress {
-//         let now = Instant::now();
-//         let mut progress= AIProgress::new();
-
-//         let root_clone = self.root.clone();
-//         self.visits.insert(self.root, 0.0);
-
-//         while now.elapsed() < self.time_limit {
-//             self.one_iteration(&root_clone);
-
-//             progress.nodes += 1;
-//             progress.duration = now.elapsed();
-//             // progress.pv = ....
-//             self.message_sender.send(Message::AIUpdate(progress.clone()));
-//         }
-
-//         // Find best action
-//         let mut best_score = f64::MIN;
-//         let node_kids = self.children.get(&root_clone).unwrap();
-//         for kid in node_kids {
-//            let reward = self.reward.get(kid).unwrap();
-//            let visits = self.visits.get(kid).unwrap();
-//            let score = reward / visits;
-//            if score > best_score {
-//                best_score = score;
-//                let action = self.prev_action.get(kid).unwrap();
-//                progress.pv = vec![action.clone()];
-//            }
-//         }
-
-//         progress.duration = now.elapsed();
-//         progress
-//     }
-
-//     pub fn one_iteration(&mut self, node: &Game) {
-//         let path = self.select(node);
-//         let mut leaf = *path.last().unwrap();
-//         self.expand(&mut leaf);
-//         let mut reward = 0.0;
-//         for _ in 0..1 {
-//             reward += self.simulate(leaf.clone());
-//         }
-//         self.backup(path, reward);
-//     }
-
-//     fn select(&mut self, root: &Game) -> Vec<Game> {
-//         let mut node = root;
-//         let mut path = Vec::new();
-//         loop {
-//             path.push(*node);
-//             if !self.children.contains_key(node) || node.state != Ongoing {
-//                 //println!("children does not contain node");
-//                 break;
-//             }
-
-//             let mut unexplored = Vec::new();
-//             let node_kids = self.children.get(node).unwrap();
-//             for kid in node_kids {
-//                 if !self.children.contains_key(kid) {
-//                     unexplored.push(*kid);
-//                 }
-//             }
-//             if !unexplored.is_empty() {
-//                 path.push(*unexplored.last().unwrap());
-//                 //println!("found unexplored node");
-//                 break;
-//             }
-//             //println!("geting ucb node");
-//             node = self.ucb_select(node).unwrap();
-//         }
-//         path
-//     }
-
-//     fn ucb_select(&self, node: &Game) -> Option<&Game> {
-//         let log_n_parent = self.visits.get(node).unwrap();
-//         let mut best_ucb = f64::MIN;
-//         let mut best_node: Option<&Game> = None;
-
-//         let node_kids = self.children.get(node).unwrap();
-//         if node_kids.is_empty() {
-//             panic!("EMPTY!");
-//         }
-
-//         for node in node_kids {
-//             let reward = self.reward.get(node).unwrap();
-//             let visits = self.visits.get(node).unwrap();
-//             let inner = log_n_parent / visits;
-//             let ucb = reward / visits + 1.141 * inner.sqrt();
-
-//             if ucb >= best_ucb {
-//                 best_ucb = ucb;
-//                 best_node = Some(node);
-//             }
-//         }
-//         // debug code
-//         // For some reason, best_node will remain None, even if node_kids is not empty.
-//         // I am at a loss. Also, sometimes, node_kids *will* be empty, when it should
-//         // never be.
-//         if best_node.is_none() {
-//             println!("NONE: best_ucb: {}, node count: {}", best_ucb, node_kids.len());
-//         }
-
-//         best_node
-//     }
-
-//     fn expand(&mut self, node: &mut Game) {
-//         if self.children.contains_key(node) {
-//             return;
-//         }
-//         let mut kids = Vec::new();
-//         let actions = node.actions_available();
-//         for action in actions {
-//             let mut new_node = node.clone();
-//             new_node.perform_action(&action, true);
-//             self.prev_action.insert(new_node, action);
-//             kids.push(new_node);
-
-//             self.reward.insert(new_node, 0.0);
-//             self.visits.insert(new_node, 0.0);
-//         }
-//         self.children.insert(*node, kids);
-//     }
-
-//     fn simulate(&mut self, mut node: Game) -> f64 {
-//         let mut result = 0.0;
-//         while node.update_state() == &GameState::Ongoing {
-//             let mut available = node.actions_available();
-//             let rand_index = fastrand::usize(0..available.len());
-//             let rand_action = available.swap_remove(rand_index);
-//             node.perform_action(&rand_action, true);
-//             //progress.nodes += 1;
-//         }
-        
-//         match node.state {
-//             GameState::Draw => {
-//                 result += 0.5;
-//             },
-//             GameState::WinPlayer0 => {
-//                 if self.search_player == 0 {
-//                     result += 1.0;
-//                 } else {
-//                     result += 0.0;
-//                 }
-//             },
-//             GameState::WinPlayer1 => {
-//                 if self.search_player == 1 {
-//                     result += 1.0;
-//                 } else {
-//                     result += 0.0;
-//                 }
-//             },
-//             GameState::Ongoing => {
-//                 panic!("Game not completed!");
-//             }
-//         }
-        
-//         result
-//     }
-
-//     fn backup(&mut self, mut path: Vec<Game>, reward: f64) {
-//         path.reverse();
-//         for node in path {
-//             self.visits.entry(node).and_modify(|visits| {
-//                 *visits += 1.0;
-//             });
-//             self.reward.entry(node).and_modify(|rewards| {
-//                 *rewards += reward;
-//             });
-//         }
-//     }
-
-    
-// }
\ No newline at end of file
+// AI Monte Carlo Tree
+// Monte Carlo tree search (UCT). More advanced than AIMonteCarlo: instead of spending the
+// same number of random playouts on every move, the tree grows toward the moves that
+// have done well so far, while still trying the others now and then.
+//
+// Nodes store only the move that led to them, and positions are replayed from the root
+// on each iteration, which keeps memory use low.
+
+use std::time::{Duration, Instant};
+
+use crate::ai::{AIProgress, Think};
+use crate::ai_sender::{AIMessage, AISender};
+use crate::game::{Game, GameState, Move};
+
+/// The UCB exploration constant. Higher explores more.
+const EXPLORATION: f64 = 1.41;
+
+struct TreeNode {
+    /// The move from the parent. None for the root.
+    the_move: Option<Move>,
+    parent: Option<usize>,
+    /// The player who made the_move.
+    mover: usize,
+    children: Vec<usize>,
+    /// Moves not yet added as children.
+    untried: Vec<Move>,
+    visits: f64,
+    /// Total playout reward for the player who made the_move.
+    reward: f64,
+}
+
+pub struct AIMonteCarloTree {
+    root: Game,
+    time_limit: Duration,
+    /// Stops after this many playouts, even with time left. Makes results repeatable.
+    pub max_iterations: Option<usize>,
+    /// Playouts longer than this are scored as draws.
+    pub max_playout_plies: usize,
+    message_sender: AISender,
+    nodes: Vec<TreeNode>,
+    rng: fastrand::Rng,
+}
+
+impl Think for AIMonteCarloTree {
+    fn think(&mut self) -> AIProgress {
+        let now = Instant::now();
+        let mut progress = AIProgress::new();
+
+        self.nodes.clear();
+        self.nodes.push(TreeNode {
+            the_move: None,
+            parent: None,
+            mover: 1 - self.root.current_player,
+            children: Vec::new(),
+            untried: Self::moves(&self.root),
+            visits: 0.,
+            reward: 0.,
+        });
+
+        let mut iterations = 0;
+        while now.elapsed() < self.time_limit && self.max_iterations.is_none_or(|max| iterations < max) {
+            self.one_iteration();
+            iterations += 1;
+
+            if iterations % 256 == 0 {
+                progress.nodes = iterations;
+                progress.duration = now.elapsed();
+                progress.percent_complete = (now.elapsed().as_secs_f64() / self.time_limit.as_secs_f64()).min(1.0);
+                self.fill_best(&mut progress);
+                self.message_sender.send(AIMessage::AIUpdate(progress.clone()));
+            }
+        }
+
+        progress.nodes = iterations;
+        progress.duration = now.elapsed();
+        progress.percent_complete = 1.0;
+        progress.is_complete = true;
+        self.fill_best(&mut progress);
+        progress
+    }
+}
+
+impl AIMonteCarloTree {
+    pub fn new(game: Game, time_limit: Duration, message_sender: AISender) -> Self {
+        Self {
+            root: game,
+            time_limit,
+            max_iterations: None,
+            max_playout_plies: 200,
+            message_sender,
+            nodes: Vec::new(),
+            rng: fastrand::Rng::new(),
+        }
+    }
+
+    fn moves(game: &Game) -> Vec<Move> {
+        let mut game = *game;
+        if *game.update_state() != GameState::Ongoing { return Vec::new() }
+        game.child_nodes(game.current_player).iter().filter_map(|child| child.last_move).collect()
+    }
+
+    /// Plays the move, which must be one of the game's moves.
+    fn apply(game: &Game, the_move: Move) -> Game {
+        game.child_for_move(the_move).expect("AIMonteCarloTree: illegal move")
+    }
+
+    fn one_iteration(&mut self) {
+        // Select: follow the best UCB child while every move has been tried.
+        let mut index = 0;
+        let mut game = self.root;
+        while self.nodes[index].untried.is_empty() && !self.nodes[index].children.is_empty() {
+            index = self.ucb_select(index);
+            game = Self::apply(&game, self.nodes[index].the_move.unwrap());
+        }
+
+        // Expand: add one untried move.
+        if !self.nodes[index].untried.is_empty() {
+            let pick = self.rng.usize(..self.nodes[index].untried.len());
+            let the_move = self.nodes[index].untried.swap_remove(pick);
+            let mover = game.current_player;
+            game = Self::apply(&game, the_move);
+            let child = TreeNode {
+                the_move: Some(the_move),
+                parent: Some(index),
+                mover,
+                children: Vec::new(),
+                untried: Self::moves(&game),
+                visits: 0.,
+                reward: 0.,
+            };
+            self.nodes.push(child);
+            let child_index = self.nodes.len() - 1;
+            self.nodes[index].children.push(child_index);
+            index = child_index;
+        }
+
+        // Simulate, then back up the result.
+        let result = self.simulate(game);
+        let mut node = Some(index);
+        while let Some(i) = node {
+            let tree_node = &mut self.nodes[i];
+            tree_node.visits += 1.;
+            tree_node.reward += if tree_node.mover == 0 { result } else { 1. - result };
+            node = tree_node.parent;
+        }
+    }
+
+    fn ucb_select(&self, index: usize) -> usize {
+        let log_parent = self.nodes[index].visits.max(1.).ln();
+        *self.nodes[index].children.iter()
+            .max_by(|a, b| self.ucb(**a, log_parent).total_cmp(&self.ucb(**b, log_parent)))
+            .unwrap()
+    }
+
+    fn ucb(&self, index: usize, log_parent: f64) -> f64 {
+        let node = &self.nodes[index];
+        if node.visits == 0. { return f64::MAX }
+        node.reward / node.visits + EXPLORATION * (log_parent / node.visits).sqrt()
+    }
+
+    /// Plays random moves to the end and returns the result for player 0: 1 for a win,
+    /// 0.5 for a draw, 0 for a loss.
+    fn simulate(&mut self, mut game: Game) -> f64 {
+        for _ in 0..self.max_playout_plies {
+            if *game.update_state() != GameState::Ongoing { break }
+            let mut children = game.child_nodes(game.current_player);
+            if children.is_empty() { break }
+            game = children.swap_remove(self.rng.usize(..children.len()));
+        }
+        match game.update_state() {
+            GameState::WinPlayer0 => 1.,
+            GameState::WinPlayer1 => 0.,
+            _ => 0.5,
+        }
+    }
+
+    /// Sets the most visited root move as the best node.
+    fn fill_best(&self, progress: &mut AIProgress) {
+        let best = self.nodes.first().and_then(|root| {
+            root.children.iter().max_by(|a, b| self.nodes[**a].visits.total_cmp(&self.nodes[**b].visits))
+        });
+        if let Some(best) = best {
+            let node = &self.nodes[*best];
+            let the_move = node.the_move.unwrap();
+            progress.best_node = Some(Self::apply(&self.root, the_move));
+            progress.pv = vec![the_move];
+            // Expected result for the player to move, as a percentage.
+            progress.score = node.reward / node.visits.max(1.) * 100.;
+        }
+    }
+}
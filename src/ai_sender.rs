@@ -16,7 +16,8 @@ pub enum AIMessage {
 
 #[derive(Clone)]
 pub struct AISender {
-    pub tx: Sender<AIMessage>,
+    /// None for a muted sender. See muted().
+    pub tx: Option<Sender<AIMessage>>,
     pub min_time_between: Option<Duration>,
     last_time: Option<Instant>,
 }
@@ -24,12 +25,23 @@ pub struct AISender {
 impl AISender {
     pub fn new(tx: Sender<AIMessage>, min_time_between: Option<Duration>) -> Self {
         Self {
-            tx, min_time_between,
+            tx: Some(tx), min_time_between,
+            last_time: None,
+        }
+    }
+
+    /// A sender that drops every message. For headless tools, like the tuner, that
+    /// only need the AIProgress returned at the end of the search.
+    pub fn muted() -> Self {
+        Self {
+            tx: None,
+            min_time_between: None,
             last_time: None,
         }
     }
     
     pub fn send(&mut self, message: AIMessage) {
+        let Some(tx) = &self.tx else { return };
         let mut send = false;
         if self.min_time_between.is_none() || self.last_time.is_none() {
             send = true;
@@ -38,7 +50,7 @@ impl AISender {
             send = time.elapsed() > self.min_time_between.unwrap();
         }
         if send {
-            let result = self.tx.send(message);
+            let result = tx.send(message);
             if result.is_err() {
                 println!("MessageSender send error.");
             }
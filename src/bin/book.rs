@@ -0,0 +1,102 @@
+// Book
+// Builds the opening book from game records and/or self-play games. Every move played in
+// the first few plies of a game adds one to its weight, so moves that were played more
+// often are chosen more often. With --winners, only the winning side's moves count.
+//
+//   cargo run --release --bin book -- --self-play 200 --depth 5 --out opening_book.txt
+//   cargo run --release --bin book -- --records games.txt
+//
+// Options (defaults in brackets):
+//   --records FILE    game records to read (see game_record.rs)
+//   --self-play N     self-play games to add [0]
+//   --depth N         self-play search depth [5]
+//   --seed N          self-play rng seed [1]
+//   --random-plies N  random moves at the start of each self-play game [2]
+//   --margin X        the random moves score within X pawns of the best move [1.0]
+//   --plies N         book moves are taken from the first N plies of each game [10]
+//   --min-count N     moves played fewer times than this are left out [2]
+//   --winners         only count moves of the side that won
+//   --out FILE        where to write the book [opening_book.txt]
+
+use mini_shogi::evaluation::{EvaluatorKind, Evaluators};
+use mini_shogi::game::{Game, GameState};
+use mini_shogi::game_record::GameRecord;
+use mini_shogi::opening_book::{OpeningBook, OPENING_BOOK_FILE};
+use mini_shogi::self_play::SelfPlay;
+use mini_shogi::tool_args::ToolArgs;
+
+fn add_game(book: &mut OpeningBook, positions: &[Game], result: Option<GameState>, plies: usize, winners: bool) {
+    let winner = match result {
+        Some(GameState::WinPlayer0) => Some(0),
+        Some(GameState::WinPlayer1) => Some(1),
+        _ => None,
+    };
+    if winners && winner.is_none() { return }
+
+    for pair in positions.windows(2).take(plies) {
+        let (parent, child) = (&pair[0], &pair[1]);
+        if winners && winner != Some(parent.current_player) { continue }
+        book.add(parent, &parent.usi_for_child(child), 1);
+    }
+}
+
+fn main() {
+    let args = ToolArgs::from_env();
+    let self_play_games: usize = args.get("self-play", 0);
+    let plies: usize = args.get("plies", 10);
+    let min_count: u32 = args.get("min-count", 2);
+    let winners = args.has("winners");
+    let out_file = args.get_str("out").unwrap_or(OPENING_BOOK_FILE);
+
+    let mut book = OpeningBook::new();
+    let mut games = 0;
+
+    if let Some(records_file) = args.get_str("records") {
+        let records = GameRecord::load_all(records_file).unwrap_or_else(|error| {
+            eprintln!("Could not read records: {}", error);
+            std::process::exit(1);
+        });
+        for (index, record) in records.iter().enumerate() {
+            match record.positions() {
+                Ok(positions) => {
+                    add_game(&mut book, &positions, record.result, plies, winners);
+                    games += 1;
+                },
+                Err(error) => eprintln!("{}: record {}: {}", records_file, index + 1, error),
+            }
+        }
+        println!("{} records read from {}", games, records_file);
+    }
+
+    if self_play_games > 0 {
+        let evaluators = Evaluators::load();
+        let kind = if evaluators.has_network() { EvaluatorKind::Network } else { EvaluatorKind::Handcrafted };
+        let depth: usize = args.get("depth", 5);
+        let mut self_play = SelfPlay::new(args.get("seed", 1), [depth, depth], evaluators.evaluator(kind));
+        self_play.random_plies = args.get("random-plies", 2);
+        // Vary the opening among good moves only, so they belong in the book.
+        self_play.random_margin = Some(args.get("margin", 1.0));
+
+        for index in 0..self_play_games {
+            let game = self_play.play_game();
+            add_game(&mut book, &game.positions, Some(game.result), plies, winners);
+            games += 1;
+            println!("self-play game {}/{}: {:?} in {} plies",
+                index + 1, self_play_games, game.result, game.positions.len() - 1);
+        }
+    }
+
+    if games == 0 {
+        eprintln!("No games. Use --records FILE and/or --self-play N.");
+        std::process::exit(2);
+    }
+
+    book.prune(min_count);
+    match book.save(out_file) {
+        Ok(()) => println!("Wrote {} positions from {} games to {}", book.len(), games, out_file),
+        Err(error) => {
+            eprintln!("Could not write {}: {}", out_file, error);
+            std::process::exit(1);
+        },
+    }
+}
@@ -0,0 +1,51 @@
+// Levels
+// Plays each strength level against the level above it and reports the score, to check
+// the levels are calibrated: each should lose clearly to the next. Colors alternate
+// between games.
+//
+//   cargo run --release --bin levels -- --games 40
+//
+// Options (defaults in brackets):
+//   --games N         games per pair of levels [20]
+//   --seed N          rng seed [1]
+//   --random-plies N  random moves at the start of each game [2]
+//   --max-plies N     longer games are scored as draws [150]
+
+use mini_shogi::evaluation::{EvaluatorKind, Evaluators};
+use mini_shogi::game::GameState;
+use mini_shogi::self_play::SelfPlay;
+use mini_shogi::strength::Strength;
+use mini_shogi::tool_args::ToolArgs;
+
+fn main() {
+    let args = ToolArgs::from_env();
+    let games: usize = args.get("games", 20);
+    let seed: u64 = args.get("seed", 1);
+
+    let evaluators = Evaluators::load();
+    let kind = if evaluators.has_network() { EvaluatorKind::Network } else { EvaluatorKind::Handcrafted };
+
+    for pair in Strength::ALL.windows(2) {
+        let (weaker, stronger) = (pair[0], pair[1]);
+        let mut self_play = SelfPlay::new(seed, [0, 0], evaluators.evaluator(kind));
+        self_play.random_plies = args.get("random-plies", 2);
+        self_play.max_plies = args.get("max-plies", self_play.max_plies);
+
+        // Points for the stronger level: 1 per win, 0.5 per draw.
+        let mut points = 0.0;
+        for index in 0..games {
+            let stronger_player = index % 2;
+            self_play.strengths[stronger_player] = Some(stronger);
+            self_play.strengths[1 - stronger_player] = Some(weaker);
+
+            let game = self_play.play_game();
+            points += match (game.result, stronger_player) {
+                (GameState::WinPlayer0, 0) | (GameState::WinPlayer1, 1) => 1.0,
+                (GameState::Draw, _) => 0.5,
+                _ => 0.0,
+            };
+        }
+        println!("{} vs {}: {:.1}/{} ({:.0}%)",
+            stronger.name(), weaker.name(), points, games, points / games as f64 * 100.0);
+    }
+}
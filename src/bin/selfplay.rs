@@ -0,0 +1,63 @@
+// Selfplay
+// Plays self-play games and writes their quiet positions, labeled with the game result,
+// as training data for the 'train_nnue' tool. One position per line: "<sfen>;<result>",
+// where result is 1, 0.5 or 0 for player 0.
+//
+//   cargo run --release --bin selfplay -- --games 1000 --seed 3 --out selfplay.txt
+//
+// Options (defaults in brackets):
+//   --games N         games to play [500]
+//   --depth N         search depth [3]
+//   --seed N          rng seed; the same seed gives the same games [1]
+//   --random-plies N  random moves at the start of each game [4]
+//   --max-plies N     longer games are scored as draws [150]
+//   --skip-plies N    positions skipped at the start of each game [6]
+//   --network         play with the network evaluator (needs the "nnue" feature)
+//   --out FILE        where to write the positions; appends if it exists [selfplay.txt]
+
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use mini_shogi::evaluation::{EvaluatorKind, Evaluators};
+use mini_shogi::self_play::{is_quiet, training_line, SelfPlay};
+use mini_shogi::tool_args::ToolArgs;
+
+fn main() {
+    let args = ToolArgs::from_env();
+    let games: usize = args.get("games", 500);
+    let depth: usize = args.get("depth", 3);
+    let seed: u64 = args.get("seed", 1);
+    let skip_plies: usize = args.get("skip-plies", 6);
+    let out_file = args.get_str("out").unwrap_or("selfplay.txt");
+
+    let evaluators = Evaluators::load();
+    let kind = if args.has("network") { EvaluatorKind::Network } else { EvaluatorKind::Handcrafted };
+    if kind == EvaluatorKind::Network && !evaluators.has_network() {
+        eprintln!("No network available. Build with --features nnue and provide a network file.");
+        std::process::exit(1);
+    }
+
+    let mut self_play = SelfPlay::new(seed, [depth, depth], evaluators.evaluator(kind));
+    self_play.random_plies = args.get("random-plies", self_play.random_plies);
+    self_play.max_plies = args.get("max-plies", self_play.max_plies);
+
+    let mut file = OpenOptions::new().create(true).append(true).open(out_file).unwrap_or_else(|e| {
+        eprintln!("Could not open {}: {}", out_file, e);
+        std::process::exit(1);
+    });
+
+    let mut total = 0;
+    for index in 0..games {
+        let game = self_play.play_game();
+        let mut lines = String::new();
+        for position in game.positions.iter().skip(skip_plies).filter(|p| is_quiet(p)) {
+            lines.push_str(&training_line(position, game.result));
+            lines.push('\n');
+            total += 1;
+        }
+        file.write_all(lines.as_bytes()).expect("selfplay: write error");
+        println!("game {}/{}: {:?} in {} plies, {} positions",
+            index + 1, games, game.result, game.positions.len() - 1, total);
+    }
+    println!("Wrote {} positions to {}", total, out_file);
+}
@@ -0,0 +1,51 @@
+// Tablebase
+// Builds endgame tables by retrograde analysis and writes them where the app loads them.
+// A material set is named by the pieces besides the two kings, so "G" is K+G vs K and
+// "GP" adds a pawn. Every ownership of the pieces is covered, in hand or on the board.
+//
+//   cargo run --release --bin tablebase -- --material G,S,R,B,P
+//
+// Options (defaults in brackets):
+//   --material LIST   comma-separated material sets to build [G]
+//   --dir DIR         where to write the tables [tablebases]
+
+use std::time::Instant;
+
+use mini_shogi::tablebase::{Tablebase, TABLEBASE_DIR};
+use mini_shogi::tool_args::ToolArgs;
+
+fn main() {
+    let args = ToolArgs::from_env();
+    let materials = args.get_str("material").unwrap_or("G");
+    let dir = args.get_str("dir").unwrap_or(TABLEBASE_DIR);
+
+    if let Err(error) = std::fs::create_dir_all(dir) {
+        eprintln!("Could not create {}: {}", dir, error);
+        std::process::exit(1);
+    }
+
+    for material in materials.split(',').map(str::trim).filter(|m| !m.is_empty()) {
+        let start = Instant::now();
+        let table = Tablebase::generate(material, |distance, count| {
+            if count > 0 {
+                println!("{}: {} positions resolved in {} plies", material, count, distance);
+            }
+        }).unwrap_or_else(|error| {
+            eprintln!("{}: {}", material, error);
+            std::process::exit(2);
+        });
+
+        let (wins, losses, draws, longest) = table.stats();
+        println!("K{}K: {} wins, {} losses, {} draws, longest {} plies, {:.1}s",
+            table.material(), wins, losses, draws, longest, start.elapsed().as_secs_f64());
+
+        let path = Tablebase::path(dir, table.material());
+        match table.save(&path) {
+            Ok(()) => println!("Wrote {}", path),
+            Err(error) => {
+                eprintln!("Could not write {}: {}", path, error);
+                std::process::exit(1);
+            },
+        }
+    }
+}
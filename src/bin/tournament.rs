@@ -0,0 +1,183 @@
+// Tournament
+// Plays matches between engine configurations and reports wins, draws and losses with
+// an Elo estimate. Every opening is played twice, once with each engine moving first.
+// Games run in parallel on all cores.
+//
+//   cargo run --release --bin tournament -- --engines minimax:2,minimax:3,mcts:500 --games 40
+//   cargo run --release --bin tournament -- --engines "minimax:4,usi:fairy-stockfish" \
+//       --mode gauntlet --usi-option UCI_Variant=minishogi --usi-go "movetime 200"
+//
+// Engines are comma-separated: minimax:DEPTH, minimax:DEPTH:network, level:NAME
+// (Beginner to Expert), mcts:MILLIS, montecarlo:ROUNDS, random or usi:COMMAND.
+//
+// Options (defaults in brackets):
+//   --engines LIST      the engines to play [required]
+//   --mode MODE         round-robin, or gauntlet: the first engine against each other [round-robin]
+//   --games N           games per pairing, rounded up to even [20]
+//   --openings FILE     game records whose positions start the games [random openings]
+//   --opening-plies N   plies of each random opening [4]
+//   --seed N            rng seed for openings and the AIs [1]
+//   --threads N         games played at once [number of cores]
+//   --max-plies N       longer games are scored as draws [150]
+//   --usi-option LIST   comma-separated NAME=VALUE options for USI engines
+//   --usi-go LIMITS     limits for USI "go" [movetime 500]
+//   --records FILE      append every game to this file as a record
+
+use std::collections::VecDeque;
+use std::sync::{mpsc, Arc, Mutex};
+
+use mini_shogi::evaluation::Evaluators;
+use mini_shogi::game::GameState;
+use mini_shogi::game_record::GameRecord;
+use mini_shogi::tool_args::ToolArgs;
+use mini_shogi::tournament::{play_game, random_openings, Engine, EngineSpec, MatchGame, Score, TournamentSettings};
+
+struct Job {
+    pairing: usize,
+    /// Which engine of the pairing moves first.
+    first: usize,
+    opening: usize,
+    seed: u64,
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
+fn main() {
+    let args = ToolArgs::from_env();
+    let specs: Vec<EngineSpec> = args.get_str("engines")
+        .unwrap_or_else(|| exit_with("--engines is required, e.g. --engines minimax:2,minimax:3"))
+        .split(',')
+        .map(|text| EngineSpec::parse(text).unwrap_or_else(|error| exit_with(&error)))
+        .collect();
+    if specs.len() < 2 {
+        exit_with("A tournament needs at least two engines.");
+    }
+    let games_per_pairing = args.get::<usize>("games", 20).div_ceil(2) * 2;
+    let seed: u64 = args.get("seed", 1);
+    let threads: usize = args.get("threads", std::thread::available_parallelism().map_or(1, |n| n.get()));
+
+    let mut settings = TournamentSettings { max_plies: args.get("max-plies", 150), ..Default::default() };
+    if let Some(options) = args.get_str("usi-option") {
+        for option in options.split(',') {
+            let (name, value) = option.split_once('=').unwrap_or_else(|| exit_with("--usi-option needs NAME=VALUE"));
+            settings.usi_options.push((name.to_string(), value.to_string()));
+        }
+    }
+    if let Some(go) = args.get_str("usi-go") {
+        settings.usi_go = go.to_string();
+    }
+
+    let pairings: Vec<(usize, usize)> = match args.get_str("mode").unwrap_or("round-robin") {
+        "round-robin" => (0..specs.len()).flat_map(|a| (a + 1..specs.len()).map(move |b| (a, b))).collect(),
+        "gauntlet" => (1..specs.len()).map(|b| (0, b)).collect(),
+        other => exit_with(&format!("unknown mode '{}'", other)),
+    };
+
+    let openings = match args.get_str("openings") {
+        Some(file) => GameRecord::load_all(file).unwrap_or_else(|error| exit_with(&error)),
+        None => random_openings(games_per_pairing / 2, args.get("opening-plies", 4), seed),
+    };
+    if openings.is_empty() {
+        exit_with("No openings.");
+    }
+
+    let mut jobs = VecDeque::new();
+    for (pairing, _) in pairings.iter().enumerate() {
+        for game in 0..games_per_pairing {
+            jobs.push_back(Job {
+                pairing,
+                first: game % 2,
+                opening: (game / 2) % openings.len(),
+                seed: seed.wrapping_add((pairing * games_per_pairing + game) as u64),
+            });
+        }
+    }
+    let total = jobs.len();
+    println!("{} pairings, {} games, {} threads", pairings.len(), total, threads);
+
+    let evaluators = Evaluators::load();
+    let jobs = Arc::new(Mutex::new(jobs));
+    let (tx, rx) = mpsc::channel::<(Job, MatchGame)>();
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            let (jobs, tx) = (jobs.clone(), tx.clone());
+            let (specs, pairings, openings, settings, evaluators) = (&specs, &pairings, &openings, &settings, &evaluators);
+            scope.spawn(move || loop {
+                let Some(job) = jobs.lock().unwrap().pop_front() else { break };
+                let (a, b) = pairings[job.pairing];
+                let players = if job.first == 0 { [a, b] } else { [b, a] };
+
+                let engines: Result<Vec<Engine>, String> = players.iter().enumerate()
+                    .map(|(i, p)| Engine::new(&specs[*p], evaluators, settings, job.seed * 2 + i as u64))
+                    .collect();
+                let result = match engines {
+                    Ok(mut engines) => {
+                        let (first, second) = engines.split_at_mut(1);
+                        play_game([&mut first[0], &mut second[0]], &openings[job.opening], settings)
+                    },
+                    Err(error) => exit_with(&format!("Could not start engine: {}", error)),
+                };
+                if tx.send((job, result)).is_err() { break }
+            });
+        }
+        drop(tx);
+
+        // Collect results as they come in.
+        let mut scores = vec![Score::default(); pairings.len()];
+        let mut done = 0;
+        for (job, game) in rx {
+            done += 1;
+            let (a, b) = pairings[job.pairing];
+            // The result from engine a's side.
+            let a_player = job.first;
+            let score = &mut scores[job.pairing];
+            match (game.result, a_player) {
+                (GameState::WinPlayer0, 0) | (GameState::WinPlayer1, 1) => score.wins += 1,
+                (GameState::WinPlayer0, _) | (GameState::WinPlayer1, _) => score.losses += 1,
+                _ => score.draws += 1,
+            }
+            let players = if a_player == 0 { (a, b) } else { (b, a) };
+            println!("game {}/{}: {} vs {}: {:?} in {} plies{}",
+                done, total, specs[players.0].name(), specs[players.1].name(), game.result,
+                game.record.moves.len(),
+                game.error.as_ref().map(|e| format!(" ({})", e)).unwrap_or_default());
+
+            if let Some(file) = args.get_str("records") {
+                if let Err(error) = game.record.append_to(file) {
+                    eprintln!("Could not save record: {}", error);
+                }
+            }
+        }
+        report(&specs, &pairings, &scores);
+    });
+}
+
+fn report(specs: &[EngineSpec], pairings: &[(usize, usize)], scores: &[Score]) {
+    let elo_text = |score: &Score| match score.elo() {
+        Some((elo, margin)) => format!("{:+.0} +/- {:.0}", elo, margin),
+        None => "-".to_string(),
+    };
+
+    println!("\nPairings (wins-draws-losses for the first engine):");
+    for ((a, b), score) in pairings.iter().zip(scores) {
+        println!("  {} vs {}: {}-{}-{}, {:.1}/{}, Elo {}",
+            specs[*a].name(), specs[*b].name(), score.wins, score.draws, score.losses,
+            score.points(), score.games(), elo_text(score));
+    }
+
+    println!("\nTotals (Elo against all opponents faced):");
+    let mut totals = vec![Score::default(); specs.len()];
+    for ((a, b), score) in pairings.iter().zip(scores) {
+        totals[*a].add(score);
+        totals[*b].add(&score.reversed());
+    }
+    for (spec, total) in specs.iter().zip(&totals) {
+        if total.games() == 0 { continue }
+        println!("  {}: {}-{}-{}, {:.1}/{}, Elo {}",
+            spec.name(), total.wins, total.draws, total.losses, total.points(), total.games(), elo_text(total));
+    }
+}
@@ -0,0 +1,207 @@
+// Train NNUE
+// Trains the network evaluator on positions from the 'selfplay' tool and writes a
+// quantized network file that the app loads at startup. Training uses floats; the
+// network is only converted to integers at the end. Needs the "nnue" feature:
+//
+//   cargo run --release --features nnue --bin train_nnue -- --data selfplay.txt
+//
+// Options (defaults in brackets):
+//   --data FILE   training positions [selfplay.txt]
+//   --epochs N    passes over the data [20]
+//   --batch N     positions per gradient step [256]
+//   --rate X      learning rate [0.001]
+//   --seed N      rng seed for the initial weights and shuffling [1]
+//   --out FILE    where to write the network [nnue.bin]
+
+use std::fs;
+
+use mini_shogi::nnue::{Network, HIDDEN_COUNT, INPUT_COUNT, MAX_FEATURE_WEIGHT, NNUE_FILE, QA, WDL_SCALE};
+use mini_shogi::self_play::parse_training_line;
+use mini_shogi::tool_args::ToolArgs;
+
+// Offsets into the flat parameter vector.
+const FEATURE_WEIGHTS: usize = 0;
+const FEATURE_BIASES: usize = FEATURE_WEIGHTS + INPUT_COUNT * HIDDEN_COUNT;
+const OUTPUT_WEIGHTS: usize = FEATURE_BIASES + HIDDEN_COUNT;
+const OUTPUT_BIAS: usize = OUTPUT_WEIGHTS + HIDDEN_COUNT * 2;
+const PARAM_COUNT: usize = OUTPUT_BIAS + 1;
+
+struct Sample {
+    /// Active features for the player to move, then for the opponent.
+    features: [Vec<usize>; 2],
+    /// The game result for the player to move.
+    result: f32,
+}
+
+struct Trainer {
+    params: Vec<f32>,
+    gradient: Vec<f32>,
+    // Adam state
+    m: Vec<f32>,
+    v: Vec<f32>,
+    step: i32,
+}
+
+impl Trainer {
+    fn new(rng: &mut fastrand::Rng) -> Self {
+        let mut params = vec![0.; PARAM_COUNT];
+        for p in &mut params[FEATURE_WEIGHTS..FEATURE_BIASES] {
+            *p = (rng.f32() - 0.5) * 0.2;
+        }
+        for p in &mut params[OUTPUT_WEIGHTS..OUTPUT_BIAS] {
+            *p = (rng.f32() - 0.5) * 0.2;
+        }
+        Self {
+            params,
+            gradient: vec![0.; PARAM_COUNT],
+            m: vec![0.; PARAM_COUNT],
+            v: vec![0.; PARAM_COUNT],
+            step: 0,
+        }
+    }
+
+    /// Returns the hidden layer (before activation) for each perspective, and the output.
+    fn forward(&self, sample: &Sample) -> ([[f32; HIDDEN_COUNT]; 2], f32) {
+        let mut hidden = [[0.; HIDDEN_COUNT]; 2];
+        let mut output = self.params[OUTPUT_BIAS];
+        for (side, side_hidden) in hidden.iter_mut().enumerate() {
+            side_hidden.copy_from_slice(&self.params[FEATURE_BIASES..OUTPUT_WEIGHTS]);
+            for feature in &sample.features[side] {
+                let row = FEATURE_WEIGHTS + feature * HIDDEN_COUNT;
+                for (h, w) in side_hidden.iter_mut().zip(&self.params[row..row + HIDDEN_COUNT]) {
+                    *h += w;
+                }
+            }
+            for (j, h) in side_hidden.iter().enumerate() {
+                output += h.clamp(0., 1.) * self.params[OUTPUT_WEIGHTS + side * HIDDEN_COUNT + j];
+            }
+        }
+        (hidden, output)
+    }
+
+    fn sigmoid(x: f32) -> f32 {
+        1.0 / (1.0 + (-x).exp())
+    }
+
+    /// Adds the sample's gradient and returns its loss.
+    fn backward(&mut self, sample: &Sample) -> f32 {
+        let (hidden, output) = self.forward(sample);
+        let predicted = Self::sigmoid(WDL_SCALE * output);
+        let g = 2. * (predicted - sample.result) * predicted * (1. - predicted) * WDL_SCALE;
+
+        self.gradient[OUTPUT_BIAS] += g;
+        for (side, side_hidden) in hidden.iter().enumerate() {
+            for (j, h) in side_hidden.iter().copied().enumerate() {
+                let out_index = OUTPUT_WEIGHTS + side * HIDDEN_COUNT + j;
+                self.gradient[out_index] += g * h.clamp(0., 1.);
+                // Clipped ReLU passes the gradient only between 0 and 1.
+                if h <= 0. || h >= 1. { continue }
+                let d = g * self.params[out_index];
+                self.gradient[FEATURE_BIASES + j] += d;
+                for feature in &sample.features[side] {
+                    self.gradient[FEATURE_WEIGHTS + feature * HIDDEN_COUNT + j] += d;
+                }
+            }
+        }
+        (predicted - sample.result).powi(2)
+    }
+
+    /// Applies and clears the accumulated gradient.
+    fn apply(&mut self, batch_size: usize, rate: f32) {
+        const BETA1: f32 = 0.9;
+        const BETA2: f32 = 0.999;
+        const EPSILON: f32 = 1e-8;
+        self.step += 1;
+        let correction1 = 1. - BETA1.powi(self.step);
+        let correction2 = 1. - BETA2.powi(self.step);
+        let max_feature = MAX_FEATURE_WEIGHT as f32 / QA as f32;
+        for i in 0..PARAM_COUNT {
+            let g = self.gradient[i] / batch_size as f32;
+            self.gradient[i] = 0.;
+            self.m[i] = BETA1 * self.m[i] + (1. - BETA1) * g;
+            self.v[i] = BETA2 * self.v[i] + (1. - BETA2) * g * g;
+            self.params[i] -= rate * (self.m[i] / correction1) / ((self.v[i] / correction2).sqrt() + EPSILON);
+            // Keep the first layer within what the quantized network can hold.
+            if i < OUTPUT_WEIGHTS {
+                self.params[i] = self.params[i].clamp(-max_feature, max_feature);
+            }
+        }
+    }
+
+    fn loss(&self, samples: &[Sample]) -> f32 {
+        let total: f32 = samples.iter()
+            .map(|s| (Self::sigmoid(WDL_SCALE * self.forward(s).1) - s.result).powi(2))
+            .sum();
+        total / samples.len().max(1) as f32
+    }
+
+    fn network(&self) -> Network {
+        Network::from_float(
+            &self.params[FEATURE_WEIGHTS..FEATURE_BIASES],
+            &self.params[FEATURE_BIASES..OUTPUT_WEIGHTS],
+            &self.params[OUTPUT_WEIGHTS..OUTPUT_BIAS],
+            self.params[OUTPUT_BIAS],
+        )
+    }
+}
+
+fn main() {
+    let args = ToolArgs::from_env();
+    let data_file = args.get_str("data").unwrap_or("selfplay.txt");
+    let epochs: usize = args.get("epochs", 20);
+    let batch_size: usize = args.get("batch", 256).max(1);
+    let rate: f32 = args.get("rate", 0.001);
+    let seed: u64 = args.get("seed", 1);
+    let out_file = args.get_str("out").unwrap_or(NNUE_FILE);
+
+    let text = fs::read_to_string(data_file).unwrap_or_else(|e| {
+        eprintln!("Could not read {}: {}", data_file, e);
+        std::process::exit(1);
+    });
+    let mut samples = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() { continue }
+        match parse_training_line(line) {
+            Ok((game, result)) => {
+                let us = game.current_player;
+                samples.push(Sample {
+                    features: [Network::active_features(&game, us), Network::active_features(&game, 1 - us)],
+                    result: if us == 0 { result as f32 } else { 1. - result as f32 },
+                });
+            },
+            Err(error) => eprintln!("{}:{}: {}", data_file, number + 1, error),
+        }
+    }
+    if samples.is_empty() {
+        eprintln!("No training positions in {}", data_file);
+        std::process::exit(1);
+    }
+
+    // Hold back a tenth of the positions to check for overfitting.
+    let mut rng = fastrand::Rng::with_seed(seed);
+    rng.shuffle(&mut samples);
+    let validation = samples.split_off(samples.len() - samples.len() / 10);
+    println!("{} training positions, {} validation positions", samples.len(), validation.len());
+
+    let mut trainer = Trainer::new(&mut rng);
+    for epoch in 1..=epochs {
+        rng.shuffle(&mut samples);
+        let mut total = 0.;
+        for batch in samples.chunks(batch_size) {
+            for sample in batch {
+                total += trainer.backward(sample);
+            }
+            trainer.apply(batch.len(), rate);
+        }
+        println!("epoch {}: training loss {:.5}, validation loss {:.5}",
+            epoch, total / samples.len() as f32, trainer.loss(&validation));
+    }
+
+    match trainer.network().save(out_file) {
+        Ok(()) => println!("Wrote {}", out_file),
+        Err(error) => {
+            eprintln!("Could not write {}: {}", out_file, error);
+            std::process::exit(1);
+        },
+    }
+}
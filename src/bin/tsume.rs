@@ -0,0 +1,154 @@
+// Tsume
+// Checks the mate puzzles, finds mates in a position, or makes new puzzles from random
+// positions. A puzzle passes if its solution is the only way to mate in its length.
+//
+//   cargo run --release --bin tsume -- --verify
+//   cargo run --release --bin tsume -- --sfen "2k2/5/2P2/5/K4 b G 1"
+//   cargo run --release --bin tsume -- --generate 5 --plies 3 >> assets/puzzles.txt
+//
+// Options (defaults in brackets):
+//   --verify [FILE]   checks every puzzle in the file [assets/puzzles.txt]
+//   --sfen SFEN       looks for a mate by the player to move
+//   --generate N      prints N new puzzles, as puzzle text
+//   --plies N         the mate length for --generate, and the longest mate --sfen
+//                     shortens [3]
+//   --nodes N         the solver's node budget [2000000]
+//   --seed N          rng seed for --generate [1]
+
+use std::time::Instant;
+
+use mini_shogi::game::{Game, GRID_COUNT, NONE};
+use mini_shogi::mate_solver::{self, MateResult, MateSolver, DEFAULT_MAX_NODES};
+use mini_shogi::piece::PieceKind::{self, *};
+use mini_shogi::puzzle::{Puzzle, PUZZLES_FILE};
+use mini_shogi::tool_args::ToolArgs;
+
+fn main() {
+    let args = ToolArgs::from_env();
+    let nodes: usize = args.get("nodes", DEFAULT_MAX_NODES);
+    let plies: usize = args.get("plies", 3);
+
+    if let Some(sfen) = args.get_str("sfen") {
+        solve(sfen, nodes, plies);
+    } else if args.has("generate") {
+        generate(args.get("generate", 1), plies, nodes, args.get("seed", 1));
+    } else {
+        verify(args.get_str("verify").unwrap_or(PUZZLES_FILE), nodes);
+    }
+}
+
+fn verify(path: &str, nodes: usize) {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|error| {
+        eprintln!("Could not read {}: {}", path, error);
+        std::process::exit(1);
+    });
+    let puzzles = Puzzle::parse_all(&text);
+    let mut failures = 0;
+    for (index, puzzle) in puzzles.iter().enumerate() {
+        let start = Instant::now();
+        match puzzle.verify(nodes) {
+            Ok(()) => println!("{:>3}. {} (mate in {}): ok, {:.2}s", index + 1, puzzle.name, puzzle.moves(), start.elapsed().as_secs_f64()),
+            Err(error) => {
+                failures += 1;
+                println!("{:>3}. {}: {}", index + 1, puzzle.name, error);
+            },
+        }
+    }
+    println!("{} puzzles, {} failed", puzzles.len(), failures);
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn solve(sfen: &str, nodes: usize, plies: usize) {
+    let game = Game::from_sfen(sfen).unwrap_or_else(|error| {
+        eprintln!("Invalid SFEN: {}", error);
+        std::process::exit(2);
+    });
+    let start = Instant::now();
+    let mut solver = MateSolver::new(nodes);
+    let result = solver.solve(&game);
+    let seconds = start.elapsed().as_secs_f64();
+    match result {
+        MateResult::Mate(line) => {
+            println!("Mate in {} plies: {} ({} nodes, {:.2}s)", line.len(), usi_line(&game, &line).join(" "), solver.nodes(), seconds);
+            if let Some(length) = mate_solver::mate_in(&game, plies.min(line.len())) {
+                if length < line.len() {
+                    println!("Shortest, mate in {} plies: {}", length, usi_line(&game, &main_line(&game, length)).join(" "));
+                }
+            }
+        },
+        MateResult::NoMate => println!("No mate ({} nodes, {:.2}s)", solver.nodes(), seconds),
+        MateResult::Unknown => println!("Unknown: the node budget ran out ({} nodes, {:.2}s)", solver.nodes(), seconds),
+    }
+}
+
+/// The mate in plies with the defender's longest replies.
+fn main_line(game: &Game, plies: usize) -> Vec<Game> {
+    let mut line = Vec::new();
+    let mut node = *game;
+    for remaining in (1..=plies).rev().step_by(2) {
+        let Some(check) = mate_solver::mating_moves(&node, remaining).first().copied() else { break };
+        line.push(check);
+        let Some(reply) = mate_solver::best_defense(&check, remaining.saturating_sub(2)) else { break };
+        line.push(reply);
+        node = reply;
+    }
+    line
+}
+
+fn usi_line(game: &Game, line: &[Game]) -> Vec<String> {
+    let mut parent = *game;
+    line.iter().map(|child| {
+        let usi = parent.usi_for_child(child);
+        parent = *child;
+        usi
+    }).collect()
+}
+
+/// Tries random positions, with player 1's king near the top of the board, until count
+/// of them are puzzles.
+fn generate(count: usize, plies: usize, nodes: usize, seed: u64) {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let kinds = [Gold, Silver, Rook, Bishop, Pawn];
+    let mut found = 0;
+    let mut tried = 0;
+
+    while found < count {
+        tried += 1;
+        let Some(game) = random_position(&mut rng, &kinds) else { continue };
+        if mate_solver::mate_in(&game, plies) != Some(plies) { continue }
+
+        let puzzle = Puzzle {
+            name: format!("Mate in {} #{}", plies.div_ceil(2), seed * 1000 + found as u64 + 1),
+            sfen: game.to_sfen(),
+            solution: usi_line(&game, &main_line(&game, plies)),
+        };
+        if puzzle.verify(nodes).is_ok() {
+            found += 1;
+            println!("{}", puzzle.to_text());
+            eprintln!("found {} of {} after {} positions", found, count, tried);
+        }
+    }
+}
+
+fn random_position(rng: &mut fastrand::Rng, kinds: &[PieceKind]) -> Option<Game> {
+    let mut game = Game::empty();
+    game.put_piece(rng.usize(15..GRID_COUNT), King, 1).ok()?;
+    game.put_piece(rng.usize(0..10), King, 0).ok()?;
+    for _ in 0..rng.usize(2..=5) {
+        let kind = kinds[rng.usize(..kinds.len())];
+        // Mostly the attacker's, often in hand.
+        let player = if rng.usize(..4) == 0 { 1 } else { 0 };
+        if player == 0 && rng.bool() {
+            game.put_in_hand(kind, 0).ok()?;
+        } else {
+            let index = rng.usize(..GRID_COUNT);
+            if game.grid[index] != NONE { return None }
+            game.put_piece(index, kind, player).ok()?;
+        }
+    }
+    game.validate().ok()?;
+    if game.is_in_check(0) { return None }
+    Some(game)
+}
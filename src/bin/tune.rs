@@ -0,0 +1,77 @@
+// Tune
+// Generates self-play games, then fits the evaluation weights to the results
+// (Texel tuning) and writes a weights file that the app loads at startup.
+//
+//   cargo run --release --bin tune -- --games 200 --seed 7 --out eval_weights.txt
+//
+// Options (defaults in brackets):
+//   --games N         self-play games to generate [200]
+//   --depth N         search depth used in the games [3]
+//   --seed N          rng seed; the same seed gives the same result [1]
+//   --random-plies N  random moves at the start of each game [4]
+//   --max-plies N     longer games are scored as draws [150]
+//   --skip-plies N    positions skipped at the start of each game [6]
+//   --iterations N    gradient descent steps [1000]
+//   --rate X          learning rate [0.005]
+//   --start FILE      starting weights [eval_weights.txt if present, else built-in]
+//   --out FILE        where to write the tuned weights [eval_weights.txt]
+
+use mini_shogi::evaluation::{EvalWeights, Evaluator, EVAL_WEIGHTS_FILE};
+use mini_shogi::game::GameState;
+use mini_shogi::self_play::SelfPlay;
+use mini_shogi::tool_args::ToolArgs;
+use mini_shogi::tuner::Tuner;
+
+fn main() {
+    let args = ToolArgs::from_env();
+    let games: usize = args.get("games", 200);
+    let depth: usize = args.get("depth", 3);
+    let seed: u64 = args.get("seed", 1);
+    let skip_plies: usize = args.get("skip-plies", 6);
+    let iterations: usize = args.get("iterations", 1000);
+    let rate: f64 = args.get("rate", 0.005);
+    let start_file = args.get_str("start").unwrap_or(EVAL_WEIGHTS_FILE);
+    let out_file = args.get_str("out").unwrap_or(EVAL_WEIGHTS_FILE);
+
+    let start_weights = EvalWeights::load_or_default(start_file);
+    let mut self_play = SelfPlay::new(seed, [depth, depth], Evaluator::Handcrafted(start_weights));
+    self_play.random_plies = args.get("random-plies", self_play.random_plies);
+    self_play.max_plies = args.get("max-plies", self_play.max_plies);
+
+    // Self-play
+    let mut tuner = Tuner::new(&start_weights);
+    let mut wins = [0, 0];
+    let mut draws = 0;
+    for index in 0..games {
+        let game = self_play.play_game();
+        match game.result {
+            GameState::WinPlayer0 => wins[0] += 1,
+            GameState::WinPlayer1 => wins[1] += 1,
+            _ => draws += 1,
+        }
+        tuner.add_game(&game, skip_plies);
+        println!("game {}/{}: {:?} in {} plies, {} positions",
+            index + 1, games, game.result, game.positions.len() - 1, tuner.positions.len());
+    }
+    println!("player 0 wins: {}, player 1 wins: {}, draws: {}", wins[0], wins[1], draws);
+
+    if tuner.positions.is_empty() {
+        eprintln!("No quiet positions to tune with.");
+        std::process::exit(1);
+    }
+
+    // Tuning
+    let scale = tuner.fit_scale();
+    println!("sigmoid scale: {:.4}, starting error: {:.6}", scale, tuner.error(scale));
+    tuner.tune(iterations, rate, 100, |iteration, error| {
+        println!("iteration {}: error {:.6}", iteration, error);
+    });
+
+    match tuner.weights().save(out_file) {
+        Ok(()) => println!("Wrote {}", out_file),
+        Err(error) => {
+            eprintln!("Could not write {}: {}", out_file, error);
+            std::process::exit(1);
+        },
+    }
+}
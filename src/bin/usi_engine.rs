@@ -0,0 +1,15 @@
+// USI Engine
+// Runs the Minimax AI as a USI engine on stdin and stdout, so shogi GUIs and other
+// programs can play against it, including the 'tournament' tool:
+//
+//   cargo build --release --bin usi_engine
+//   cargo run --release --bin tournament -- --engines "minimax:4,usi:target/release/usi_engine"
+//
+// Options: USI_Ponder, USI_Hash (megabytes), OwnBook, Depth and MoveTime (milliseconds
+// per move when "go" sets no time). Supports "go ponder" and "ponderhit".
+
+use mini_shogi::usi::UsiServer;
+
+fn main() {
+    UsiServer::new().run();
+}
@@ -0,0 +1,138 @@
+// Clock
+// Game clocks with the usual time controls: sudden death, Fischer (an increment added
+// after each move) and byoyomi (once the main time is used up, each move must be made
+// within a fixed period). Also the time manager that decides how long the AI may think.
+
+use std::time::Duration;
+
+/// Remaining time under which a clock is shown as low.
+const LOW_TIME: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeControl {
+    Unlimited,
+    SuddenDeath { main: Duration },
+    Fischer { main: Duration, increment: Duration },
+    Byoyomi { main: Duration, period: Duration },
+}
+
+impl TimeControl {
+    /// The choices offered in Settings.
+    pub const PRESETS: [TimeControl; 6] = [
+        TimeControl::Unlimited,
+        TimeControl::SuddenDeath { main: Duration::from_secs(5 * 60) },
+        TimeControl::SuddenDeath { main: Duration::from_secs(60) },
+        TimeControl::Fischer { main: Duration::from_secs(3 * 60), increment: Duration::from_secs(2) },
+        TimeControl::Byoyomi { main: Duration::from_secs(5 * 60), period: Duration::from_secs(30) },
+        TimeControl::Byoyomi { main: Duration::ZERO, period: Duration::from_secs(10) },
+    ];
+
+    pub fn name(&self) -> String {
+        let minutes = |d: &Duration| d.as_secs() as f64 / 60.;
+        match self {
+            TimeControl::Unlimited => "No clock".to_string(),
+            TimeControl::SuddenDeath { main } => format!("Sudden death {}m", minutes(main)),
+            TimeControl::Fischer { main, increment } => format!("Fischer {}m + {}s", minutes(main), increment.as_secs()),
+            TimeControl::Byoyomi { main, period } if main.is_zero() => format!("Byoyomi {}s", period.as_secs()),
+            TimeControl::Byoyomi { main, period } => format!("{}m + {}s byoyomi", minutes(main), period.as_secs()),
+        }
+    }
+
+    fn main_time(&self) -> Duration {
+        match self {
+            TimeControl::Unlimited => Duration::MAX,
+            TimeControl::SuddenDeath { main } | TimeControl::Fischer { main, .. } | TimeControl::Byoyomi { main, .. } => *main,
+        }
+    }
+
+    fn period(&self) -> Duration {
+        match self {
+            TimeControl::Byoyomi { period, .. } => *period,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+pub struct Clock {
+    pub control: TimeControl,
+    /// Main time left, per player.
+    main: [Duration; 2],
+    /// Byoyomi left for the current move, per player. Reset by each move.
+    period: [Duration; 2],
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Self {
+            control,
+            main: [control.main_time(); 2],
+            period: [control.period(); 2],
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.control != TimeControl::Unlimited
+    }
+
+    /// Charges the player for time spent on their move. Returns true if their time
+    /// has run out.
+    pub fn tick(&mut self, player: usize, time_delta: Duration) -> bool {
+        if !self.is_enabled() { return false }
+        let overrun = time_delta.saturating_sub(self.main[player]);
+        self.main[player] = self.main[player].saturating_sub(time_delta);
+        if !overrun.is_zero() {
+            self.period[player] = self.period[player].saturating_sub(overrun);
+        }
+        self.main[player].is_zero() && self.period[player].is_zero()
+    }
+
+    /// Called when the player completes a move: adds the Fischer increment, or starts a
+    /// fresh byoyomi period.
+    pub fn move_made(&mut self, player: usize) {
+        match self.control {
+            TimeControl::Fischer { increment, .. } => self.main[player] += increment,
+            TimeControl::Byoyomi { period, .. } => self.period[player] = period,
+            _ => {},
+        }
+    }
+
+    /// The time left as shown next to the player's reserve, e.g. "4:59", or "B 0:27"
+    /// once in byoyomi. Empty without a clock.
+    pub fn text(&self, player: usize) -> String {
+        let format = |d: Duration| {
+            let secs = d.as_secs_f64().ceil() as u64;
+            format!("{}:{:02}", secs / 60, secs % 60)
+        };
+        match self.control {
+            TimeControl::Unlimited => String::new(),
+            TimeControl::Byoyomi { .. } if self.main[player].is_zero() => format!("B {}", format(self.period[player])),
+            _ => format(self.main[player]),
+        }
+    }
+
+    pub fn is_low(&self, player: usize) -> bool {
+        self.is_enabled() && self.main[player] + self.period[player] < LOW_TIME
+    }
+
+    /// How long the player's AI may think about its move, or None without a clock.
+    pub fn move_budget(&self, player: usize) -> Option<Duration> {
+        let increment = match self.control {
+            TimeControl::Unlimited => return None,
+            TimeControl::Fischer { increment, .. } => increment,
+            _ => Duration::ZERO,
+        };
+        Some(move_budget(self.main[player], increment, self.period[player]))
+    }
+}
+
+/// The time manager: spreads the main time over the moves expected to remain, and
+/// uses most of the increment or byoyomi, with a margin so the flag doesn't fall
+/// while a move is being made.
+pub fn move_budget(main: Duration, increment: Duration, byoyomi: Duration) -> Duration {
+    const MOVES_TO_GO: u32 = 25;
+    const MIN_BUDGET: Duration = Duration::from_millis(50);
+    let budget = main / MOVES_TO_GO + increment.mul_f64(0.8) + byoyomi.mul_f64(0.8);
+    // Never more than half the main time, unless byoyomi follows it.
+    let cap = main / 2 + byoyomi.mul_f64(0.8);
+    budget.min(cap).max(MIN_BUDGET)
+}
@@ -0,0 +1,192 @@
+// Analysis
+// Multi-PV analysis of the position shown, toggled with Analyze. The best lines are
+// listed with their scores, the best is drawn as arrows, and a line clicked in the list
+// is played out on the board.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use num_format::{Locale, ToFormattedString};
+
+use crate::ai::{AI, AIProgress, PvLine};
+use crate::ai_sender::AISender;
+use crate::game::Game;
+
+use super::AppState::*;
+use super::{Controller, BAR_ANALYZE_ID};
+
+/// The number of best moves an analysis shows.
+const ANALYSIS_LINE_COUNT: usize = 4;
+/// The moves shown of each analysis line.
+const ANALYSIS_LINE_MOVES: usize = 8;
+/// The pause between the moves of a previewed line, in seconds.
+const PREVIEW_PAUSE: f32 = 0.5;
+
+pub struct Analyzer {
+    stop: Option<Arc<AtomicBool>>, // set while an analysis runs
+    thread: Option<JoinHandle<()>>,
+    ply: usize, // the position analyzed, as an index into node_history
+    lines: Vec<PvLine>,
+    preview_line: Option<usize>, // the line shown on the board
+    preview: VecDeque<(Game, Game)>, // the preview moves still to show, as the positions before and after
+    preview_wait: f32,
+}
+
+impl Analyzer {
+    pub fn new() -> Self {
+        Self {
+            stop: None,
+            thread: None,
+            ply: 0,
+            lines: Vec::new(),
+            preview_line: None,
+            preview: VecDeque::new(),
+            preview_wait: 0.,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stop.is_some()
+    }
+}
+
+impl Controller {
+    /// Shows on the Analyze toggle whether analysis is running.
+    pub(super) fn set_analyze_button(&mut self, selected: bool) {
+        if let Some(button) = self.button_bar.button_for_id(BAR_ANALYZE_ID) {
+            button.set_selected(selected);
+        }
+    }
+
+    /// Analyzes the position shown, current or from history, for the player to move, in
+    /// the background.
+    pub(super) fn start_analysis(&mut self) {
+        if !matches!(self.state, HumanTurn | Player0Won | Player1Won | Draw) {
+            self.set_analyze_button(false);
+            return;
+        }
+        self.stop_pondering();
+        self.hint_rx = None;
+        self.view_game.clear_hint();
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+        self.state = Analysis;
+        self.pv_text = String::from("analysis");
+
+        // These variables are captured by the thread.
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop.clone();
+        self.analyzer.ply = self.history_ply.unwrap_or(self.node_history.len() - 1);
+        let game_copy = self.node_history[self.analyzer.ply];
+        let context = self.ai_context(&self.players[game_copy.current_player]);
+        let message_sender = AISender::new(self.ai_tx.clone(), None);
+
+        self.analyzer.thread = Some(std::thread::spawn(move || {
+            AI::analyze(game_copy, context, ANALYSIS_LINE_COUNT, stop_clone, message_sender);
+        }));
+        self.analyzer.stop = Some(stop);
+    }
+
+    /// Stops any analysis and puts the board back. The caller sets the next state.
+    pub(super) fn stop_analysis(&mut self) {
+        let Some(stop) = self.analyzer.stop.take() else { return };
+        stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.analyzer.thread.take() {
+            let _ = thread.join();
+        }
+        // Drop the updates still waiting, so none is taken for a move.
+        while self.ai_rx.try_recv().is_ok() {}
+
+        self.analyzer.lines.clear();
+        self.analyzer.preview_line = None;
+        self.analyzer.preview.clear();
+        self.view_game.set_analysis_lines(Vec::new(), None);
+        self.view_game.set_pv_arrows(Vec::new());
+        self.history_ply = None;
+        self.view_game.set_history_selection(None);
+        self.view_game.update_with_game(&self.game);
+        self.mark_last_move(self.node_history.len() - 1);
+        self.pv_text = String::new();
+        self.set_analyze_button(false);
+    }
+
+    pub(super) fn show_analysis(&mut self, progress: &AIProgress) {
+        self.analyzer.lines = progress.lines.clone();
+        self.update_analysis_lines();
+
+        // The best line's score goes on the graph, for player 0.
+        if let Some(best) = progress.lines.first() {
+            let ply = self.analyzer.ply;
+            let player = self.node_history[ply].current_player;
+            self.scores[ply] = Some(if player == 0 { best.score } else { -best.score });
+            self.view_game.set_scores(&self.scores);
+        }
+
+        let nodes_string = progress.nodes.to_formatted_string(&Locale::en);
+        let mut text = format!("analysis | depth: {} | nodes: {}", progress.depth, nodes_string);
+        if progress.is_complete {
+            text.push_str(" | done");
+        }
+        self.pv_text = text;
+    }
+
+    fn update_analysis_lines(&mut self) {
+        let analyzer = &self.analyzer;
+        let game = &self.node_history[analyzer.ply];
+        let texts = analyzer.lines.iter().enumerate()
+            .map(|(rank, line)| {
+                let moves = &line.pv[..line.pv.len().min(ANALYSIS_LINE_MOVES)];
+                format!("{}. {:>9}  {}", rank + 1, Self::format_score(line.score), Self::format_moves(game, moves))
+            })
+            .collect();
+        // The best line is drawn on the board, unless a line is being played out there.
+        let arrows = match (analyzer.preview_line, analyzer.lines.first()) {
+            (None, Some(best)) => Self::pv_squares(game, &best.pv),
+            _ => Vec::new(),
+        };
+        self.view_game.set_analysis_lines(texts, analyzer.preview_line);
+        self.view_game.set_pv_arrows(arrows);
+    }
+
+    /// Plays the line out on the board, or puts the board back if it is already shown.
+    pub(super) fn analysis_line_selected(&mut self, rank: usize) {
+        if self.state != Analysis { return; }
+        let Some(line) = self.analyzer.lines.get(rank) else { return };
+
+        let pv = line.pv.clone();
+        let analyzed = self.node_history[self.analyzer.ply];
+        self.analyzer.preview.clear();
+        self.analyzer.preview_wait = 0.;
+        self.view_game.update_with_game(&analyzed);
+        self.mark_last_move(self.analyzer.ply);
+
+        if self.analyzer.preview_line == Some(rank) {
+            self.analyzer.preview_line = None;
+        } else {
+            self.analyzer.preview_line = Some(rank);
+            let mut node = analyzed;
+            for the_move in pv {
+                let Some(child) = node.child_for_move(the_move) else { break };
+                self.analyzer.preview.push_back((node, child));
+                node = child;
+            }
+        }
+        self.update_analysis_lines();
+    }
+
+    /// Shows the next position of the previewed line once the last move has settled.
+    pub(super) fn advance_preview(&mut self, time_delta: Duration) {
+        let analyzer = &mut self.analyzer;
+        if analyzer.preview.is_empty() { return; }
+        analyzer.preview_wait += time_delta.as_secs_f32();
+        if analyzer.preview_wait < PREVIEW_PAUSE { return; }
+        analyzer.preview_wait = 0.;
+        if let Some((parent, node)) = analyzer.preview.pop_front() {
+            self.view_game.update_with_game(&node);
+            self.view_game.set_last_move(node.last_move_squares(&parent));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// Editor
+// The position editor, opened with Edit: the position being set up and the piece that
+// clicks place. Play or Analyze starts from the position once it is valid.
+
+use std::sync::mpsc::{self, Receiver};
+
+use crate::game::{Game, GameLocation, NONE};
+use crate::piece::PieceKind;
+use crate::view::view_editor::{ViewEditor, ViewEditorMessage};
+
+use super::AppState::*;
+use super::Controller;
+
+const EDITOR_HELP: &str = "Click a square to place or remove a piece, a reserve to add the piece to a hand, a piece in hand to remove it.";
+
+pub struct Editor {
+    view: ViewEditor,
+    view_rx: Receiver<ViewEditorMessage>,
+    game: Game, // the position being set up
+    tool: Option<PieceKind>, // the kind clicks place, or None to erase
+    owner: usize, // the player whose pieces are placed
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        let (view_tx, view_rx) = mpsc::channel();
+        Self {
+            view: ViewEditor::new(view_tx),
+            view_rx,
+            game: Game::new(),
+            tool: Some(PieceKind::Pawn),
+            owner: 0,
+        }
+    }
+
+    pub fn process_events(&mut self) {
+        self.view.process_events();
+    }
+
+    pub fn draw(&mut self) {
+        self.view.draw();
+    }
+
+    /// Places the tool's piece on the square, or removes the piece there if it is the same
+    /// or the tool erases.
+    fn edit_square(&mut self, index: usize) -> Result<(), String> {
+        let id = self.game.grid[index];
+        match self.tool {
+            Some(kind) if id == NONE || {
+                let piece = self.game.piece_for(id);
+                piece.kind != kind || piece.player != self.owner
+            } => self.game.put_piece(index, kind, self.owner),
+            _ => {
+                self.game.remove_piece(id);
+                Ok(())
+            },
+        }
+    }
+
+    /// A piece on the board edits its square; one in hand is removed.
+    fn edit_piece(&mut self, id: usize) -> Result<(), String> {
+        let piece = *self.game.piece_for(id);
+        if piece.location == GameLocation::Board {
+            self.edit_square(piece.location_index)
+        } else {
+            self.game.remove_piece(id);
+            Ok(())
+        }
+    }
+
+    /// Adds the tool's piece, unpromoted, to the player's hand.
+    fn edit_hand(&mut self, player: usize) -> Result<(), String> {
+        let Some(kind) = self.tool else { return Ok(()) };
+        self.game.put_in_hand(kind, player)
+    }
+}
+
+impl Controller {
+    /// Opens the editor on the position shown.
+    pub(super) fn start_editing(&mut self) {
+        if !matches!(self.state, HumanTurn | AIThinking | Analysis | Player0Won | Player1Won | Draw) { return; }
+        let shown = self.history_ply.map_or(self.game, |ply| self.node_history[ply]);
+        self.stop_analysis();
+        self.stop_review();
+        self.stop_pondering();
+        self.spectator.drop_pending_move();
+        self.hint_rx = None;
+        self.view_game.clear_hint();
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+        // A position that isn't valid, such as one won by capturing the king, starts the
+        // editor from the usual one instead.
+        self.editor.game = Game::from_sfen(&shown.to_sfen()).ok()
+            .filter(|game| game.validate().is_ok())
+            .unwrap_or(self.start_position);
+        self.editor.view.set_side_to_move(self.editor.game.current_player);
+        self.button_bar.visible = false;
+        self.state = Editing;
+        self.show_edit(Ok(()));
+    }
+
+    /// Shows the position being set up, and the edit's error, if any.
+    fn show_edit(&mut self, result: Result<(), String>) {
+        self.view_game.reset_game(&self.editor.game);
+        self.pv_text = match result {
+            Ok(()) => String::from(EDITOR_HELP),
+            Err(error) => format!("Cannot do that: {}.", error),
+        };
+    }
+
+    pub(super) fn edit_square(&mut self, index: usize) {
+        // Erasing an empty square does nothing.
+        if self.editor.game.grid[index] == NONE && self.editor.tool.is_none() { return }
+        let result = self.editor.edit_square(index);
+        self.show_edit(result);
+    }
+
+    pub(super) fn edit_piece(&mut self, id: usize) {
+        let result = self.editor.edit_piece(id);
+        self.show_edit(result);
+    }
+
+    pub(super) fn edit_hand(&mut self, player: usize) {
+        if self.editor.tool.is_none() { return }
+        let result = self.editor.edit_hand(player);
+        self.show_edit(result);
+    }
+
+    pub(super) fn check_editor_messages(&mut self) {
+        let Ok(message) = self.editor.view_rx.try_recv() else { return };
+        match message {
+            ViewEditorMessage::ToolSelected(tool) => self.editor.tool = tool,
+            ViewEditorMessage::OwnerChanged(player) => self.editor.owner = player,
+            ViewEditorMessage::SideToMoveChanged(player) => self.editor.game.current_player = player,
+            ViewEditorMessage::Clear => {
+                let player = self.editor.game.current_player;
+                self.editor.game = Game::empty();
+                self.editor.game.current_player = player;
+                self.show_edit(Ok(()));
+            },
+            ViewEditorMessage::StartPosition => {
+                self.editor.game = Game::new();
+                self.editor.game.prepare();
+                self.editor.view.set_side_to_move(0);
+                self.show_edit(Ok(()));
+            },
+            ViewEditorMessage::Play => self.finish_editing(false),
+            ViewEditorMessage::Analyze => self.finish_editing(true),
+            ViewEditorMessage::Cancel => {
+                self.button_bar.visible = true;
+                self.view_game.reset_game(&self.game);
+                self.mark_last_move(self.node_history.len() - 1);
+                self.pv_text = String::new();
+                self.state = NextPlayer;
+            },
+        }
+    }
+
+    /// Starts a game, or an analysis, from the position set up, if it is valid.
+    fn finish_editing(&mut self, analyze: bool) {
+        if let Err(error) = self.editor.game.validate() {
+            self.pv_text = error;
+            return;
+        }
+        self.button_bar.visible = true;
+        self.start_position = self.editor.game;
+        self.puzzles.close();
+        self.start_series();
+        self.restart();
+        if analyze {
+            // Analysis begins from a turn, whoever is to move.
+            self.state = HumanTurn;
+            self.start_analysis();
+            self.set_analyze_button(true);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+// Ending
+// The ways a game ends other than on the board: a flag falling on the game clock,
+// resignation, an agreed draw, or an abort before both players have moved.
+
+use std::time::Duration;
+
+use crate::game::{EndReason, GameState};
+
+use super::AppState::*;
+use super::Controller;
+use super::PlayerKind::*;
+
+/// An AI accepts a draw offer when its last search scored the game at most this for it,
+/// in pawns.
+const DRAW_ACCEPT_SCORE: f64 = -0.5;
+
+impl Controller {
+    /// Runs the clock of the player to move, and shows both clocks.
+    pub(super) fn update_clock(&mut self, time_delta: Duration) {
+        if !self.clock.is_enabled() {
+            self.view_game.set_clock_text(0, String::new(), false);
+            self.view_game.set_clock_text(1, String::new(), false);
+            return;
+        }
+        let p = self.game.current_player;
+        let running = matches!(self.state, HumanTurn | AITurnBegin | AIThinking)
+            && !(self.spectator.is_paused() && self.spectating())
+            && !self.puzzles.is_active();
+        if running && self.clock.tick(p, time_delta) {
+            self.flag_fall(p);
+        }
+        for player in 0..2 {
+            self.view_game.set_clock_text(player, self.clock.text(player), self.clock.is_low(player));
+        }
+    }
+
+    /// Ends the game when the player's time runs out.
+    fn flag_fall(&mut self, player: usize) {
+        self.end_game(Self::win_for(1 - player), EndReason::Time);
+    }
+
+    /// The human player resigns: the one to move, or the one waiting for the AI.
+    pub(super) fn resign(&mut self) {
+        if !matches!(self.state, HumanTurn | AITurnBegin | AIThinking | WaitingOnAnimation) || self.puzzles.is_active() { return; }
+        let p = self.game.current_player;
+        let Some(loser) = [p, 1 - p].into_iter().find(|id| self.players[*id].kind == Human) else { return };
+        self.end_game(Self::win_for(1 - loser), EndReason::Resignation);
+    }
+
+    /// The human to move offers a draw, or accepts the opponent's offer. An AI opponent
+    /// decides at once, from its score; a human one accepts by pressing Draw on their turn.
+    pub(super) fn offer_draw(&mut self) {
+        if self.state != HumanTurn || self.puzzles.is_active() { return; }
+        let p = self.game.current_player;
+        let opponent = 1 - p;
+        if self.draw_offer == Some(opponent) {
+            self.end_game(GameState::Draw, EndReason::DrawAgreed);
+            return;
+        }
+        match self.players[opponent].kind {
+            Human => {
+                self.draw_offer = Some(p);
+                self.pv_text = format!("Player {} offers a draw. Player {} may press Draw to accept.", p + 1, opponent + 1);
+            },
+            AI => {
+                match self.ai_scores[opponent] {
+                    Some(score) if score <= DRAW_ACCEPT_SCORE => self.end_game(GameState::Draw, EndReason::DrawAgreed),
+                    _ => self.pv_text = String::from("The AI declines the draw."),
+                }
+            },
+        }
+    }
+
+    /// Calls the game off, as long as both players haven't yet moved.
+    pub(super) fn abort(&mut self) {
+        if !matches!(self.state, HumanTurn | AITurnBegin | AIThinking | WaitingOnAnimation) || self.puzzles.is_active() { return; }
+        if self.record.moves.len() >= 2 {
+            self.pv_text = String::from("Too late to abort. Resign or offer a draw instead.");
+            return;
+        }
+        self.end_game(GameState::Draw, EndReason::Aborted);
+    }
+
+    pub(super) fn win_for(player: usize) -> GameState {
+        if player == 0 { GameState::WinPlayer0 } else { GameState::WinPlayer1 }
+    }
+
+    /// Ends the game other than by the position. An AI search still running is left to
+    /// finish, and its move ignored.
+    pub(super) fn end_game(&mut self, state: GameState, reason: EndReason) {
+        self.game_over = Some((state, reason));
+        self.draw_offer = None;
+        self.view_game.set_end_reason(Some(reason));
+        self.hint_rx = None;
+        self.view_game.clear_hint();
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+        self.next_player();
+    }
+}
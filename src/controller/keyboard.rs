@@ -0,0 +1,84 @@
+// Keyboard
+// Playing from the keyboard: shortcuts for the button bar's commands, undo and flip, and
+// moves typed in USI notation. The board cursor is handled by ViewGame.
+
+use macroquad::prelude::*;
+
+use crate::view::command_key_down;
+
+use super::AppState::*;
+use super::{Controller, BAR_ABORT_ID, BAR_ABOUT_ID, BAR_ANALYZE_ID, BAR_DRAW_ID, BAR_EDIT_ID, BAR_HINT_ID,
+    BAR_QUIT_ID, BAR_RESIGN_ID, BAR_RESTART_ID, BAR_RULES_ID, BAR_SETTINGS_ID};
+
+/// The keys that, with Control or Command, do what the button bar's buttons do.
+const SHORTCUTS: [(KeyCode, usize); 11] = [
+    (KeyCode::I, BAR_ABOUT_ID),
+    (KeyCode::L, BAR_RULES_ID),
+    (KeyCode::Comma, BAR_SETTINGS_ID),
+    (KeyCode::N, BAR_RESTART_ID),
+    (KeyCode::E, BAR_EDIT_ID),
+    (KeyCode::R, BAR_RESIGN_ID),
+    (KeyCode::D, BAR_DRAW_ID),
+    (KeyCode::W, BAR_ABORT_ID),
+    (KeyCode::H, BAR_HINT_ID),
+    (KeyCode::A, BAR_ANALYZE_ID),
+    (KeyCode::Q, BAR_QUIT_ID),
+];
+
+impl Controller {
+    /// F11 for full screen, and the menu and board commands, with Control or Command held.
+    pub(super) fn process_shortcuts(&mut self) {
+        if is_key_pressed(KeyCode::F11) {
+            self.fullscreen = !self.fullscreen;
+            set_fullscreen(self.fullscreen);
+        }
+        if !command_key_down() { return }
+        let in_game = matches!(self.state, HumanTurn | AIThinking | Analysis | WaitingOnAnimation | Player0Won | Player1Won | Draw);
+        if in_game && is_key_pressed(KeyCode::Z) {
+            self.undo();
+        }
+        if in_game && is_key_pressed(KeyCode::F) {
+            self.flip_board();
+        }
+        if !self.button_bar.visible { return }
+        let Some((_, button_id)) = SHORTCUTS.iter().find(|(key, _)| is_key_pressed(*key)) else { return };
+        // The bar shows analysis running with its toggle, as if clicked.
+        if *button_id == BAR_ANALYZE_ID {
+            let running = self.analyzer.is_running();
+            self.set_analyze_button(!running);
+        }
+        self.bar_command(*button_id);
+    }
+
+    /// Makes a move typed in USI notation, such as "2b3c" or "P*4c". Promotion is never
+    /// a choice here, so the '+' may be left off.
+    pub(super) fn move_typed(&mut self, usi: &str) {
+        let game = match self.state {
+            HumanTurn => self.game,
+            Tutorial => self.tutor.game(),
+            _ => return,
+        };
+        let typed = usi.trim_end_matches('+');
+        let child = game.child_nodes(game.current_player).into_iter()
+            .find(|child| game.usi_for_child(child).trim_end_matches('+') == typed);
+        let Some(child) = child else {
+            self.pv_text = format!("{} isn't a legal move.", usi);
+            return;
+        };
+        let Some(the_move) = child.last_move else { return };
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+        match self.state {
+            HumanTurn => {
+                if self.history_ply.is_some() {
+                    self.show_present();
+                }
+                self.play_node(child);
+            },
+            _ => {
+                self.view_game.select_piece(the_move.0);
+                self.tutorial_square(the_move.1);
+            },
+        }
+    }
+}
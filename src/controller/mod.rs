@@ -0,0 +1,1089 @@
+// Controller
+// Handles the app flow and is the intermediary between the view and model. Each mode
+// other than playing a game keeps its state and handlers in its own module here, and the
+// controller passes events and messages on to it.
+
+mod analysis;
+mod editor;
+mod ending;
+mod keyboard;
+mod ponder;
+mod puzzles;
+mod review;
+mod spectator;
+mod tutorial;
+
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+
+use macroquad::prelude::*;
+use num_format::{Locale, ToFormattedString};
+
+use crate::ai::{AI, AIContext, AIProgress};
+use crate::ai_minimax::WIN_LOSS_VAL;
+use crate::ai_sender::{AIMessage, AISender};
+use crate::clock::{Clock, TimeControl};
+use crate::evaluation::{EvaluatorKind, Evaluators};
+use crate::game::*;
+use crate::game::{Game, GameState};
+use crate::game_record::{GameRecord, SAVED_GAMES_FILE};
+use crate::mate_solver;
+use crate::opening_book::{OpeningBook, OPENING_BOOK_FILE};
+use crate::strength::Strength;
+use crate::tablebase::{Tablebases, TABLEBASE_DIR};
+use crate::theme::{ColorScheme, PieceSet, ThemeChoice};
+use crate::transposition::{TranspositionTable, DEFAULT_TABLE_MEGABYTES};
+use crate::controller::AppState::*;
+use crate::controller::PlayerKind::*;
+use crate::view::button::{Button, ButtonMode};
+use crate::view::button_bar::ButtonBar;
+use crate::view::button_bar::ButtonBarOrientation::*;
+use crate::view::set_layout_camera;
+use crate::view::view_about::ViewAbout;
+use crate::view::view_about::ViewAboutMessage;
+use crate::view::view_game::{Overlays, ViewGame, ViewGameMessage};
+use crate::view::view_intro::ViewIntro;
+use crate::view::view_settings::{ViewSettings, ViewSettingsMessage};
+use crate::view::view_rules::ViewRules;
+use crate::view::view_rules::ViewRulesMessage;
+
+use analysis::Analyzer;
+use editor::Editor;
+use ponder::Ponder;
+use puzzles::Puzzles;
+use review::Reviewer;
+use spectator::Spectator;
+use tutorial::Tutor;
+
+#[derive(Clone, Copy)]
+pub struct Player {
+    pub id: usize,
+    pub kind: PlayerKind,
+    pub strength: Strength,
+    /// How well the Hint button plays for a human player.
+    pub hint_strength: Strength,
+    pub search_rounds: usize,
+    pub evaluator: EvaluatorKind,
+    /// Play from the opening book when the position is in it.
+    pub use_book: bool,
+    /// Search on the opponent's time.
+    pub ponder: bool,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum AppState {
+    About,
+    Settings,
+    Rules,
+    HumanTurn,
+    AITurnBegin,
+    AIThinking,
+    Analysis,
+    Editing,
+    Tutorial,
+    WaitingOnAnimation,
+    NextPlayer,
+    Player0Won,
+    Player1Won,
+    Draw,
+    Exit,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlayerKind {
+    Human,
+    AI,
+}
+
+// The button bar at top
+const BAR_ABOUT_ID: usize = 0;
+const BAR_RULES_ID: usize = 1;
+const BAR_SETTINGS_ID: usize = 2;
+const BAR_RESTART_ID: usize = 3;
+const BAR_QUIT_ID: usize = 4;
+const BAR_ANALYZE_ID: usize = 5;
+const BAR_HINT_ID: usize = 6;
+const BAR_RESIGN_ID: usize = 7;
+const BAR_DRAW_ID: usize = 8;
+const BAR_ABORT_ID: usize = 9;
+const BAR_EDIT_ID: usize = 10;
+
+pub struct Controller {
+    players: Vec<Player>,
+    game: Game,
+    evaluators: Evaluators,
+    opening_book: Option<Arc<OpeningBook>>,
+    tablebases: Option<Arc<Tablebases>>,
+    transposition_tables: Vec<Arc<TranspositionTable>>, // one per player
+
+    button_bar: ButtonBar, // the command bar at top
+
+    view_intro: ViewIntro,
+
+    view_about: ViewAbout,
+    view_about_rx: Receiver<ViewAboutMessage>,
+
+    view_settings: ViewSettings,
+    view_settings_rx: Receiver<ViewSettingsMessage>,
+    
+    view_rules: ViewRules,
+    view_rules_rx: Receiver<ViewRulesMessage>,
+
+    view_game: ViewGame,
+    view_game_rx: Receiver<ViewGameMessage>,
+
+    pub state: AppState,
+    previous_state: Option<AppState>,
+    start_position: Game, // where games start, set in the editor
+    node_history: Vec<Game>, // every position of the game, by ply
+    history_ply: Option<usize>, // the earlier position shown, chosen on the score graph
+    record: GameRecord,
+    scores: Vec<Option<f64>>, // by ply, for player 0
+    clock: Clock,
+    game_over: Option<(GameState, EndReason)>, // set when the game ends other than by the position
+    draw_offer: Option<usize>, // the player offering a draw, until the opponent moves
+    ai_scores: [Option<f64>; 2], // each AI player's last search score, for itself
+    hint_rx: Option<Receiver<AIProgress>>, // set while a hint is being searched for
+    expected_reply: Option<Move>, // the human's reply the AI expects, from its last pv
+    ponder: Option<Ponder>,
+    ai_tx: Sender<AIMessage>,
+    ai_rx: Receiver<AIMessage>,
+    pv_text: String,
+    fullscreen: bool,
+    flip_button: Button, // turns the board around
+    undo_button: Button, // takes back the last move, or two against the AI
+    piece_sets: Vec<PieceSet>,
+    color_schemes: Vec<ColorScheme>,
+    theme: ThemeChoice, // the piece set and colour scheme in use
+
+    // The modes, each in its own module.
+    analyzer: Analyzer,
+    spectator: Spectator, // when both players are AI
+    reviewer: Reviewer,
+    editor: Editor,
+    puzzles: Puzzles,
+    tutor: Tutor,
+}
+
+impl Controller {
+    pub async fn new() -> Self {
+        let (view_about_tx, view_about_rx) = mpsc::channel();
+        let (view_settings_tx, view_settings_rx) = mpsc::channel();
+        let (view_rules_tx, view_rules_rx) = mpsc::channel();
+        let (view_game_tx, view_game_rx) = mpsc::channel();
+        let (ai_tx, ai_rx) = mpsc::channel();
+
+        Self {
+            players: Vec::new(),
+            game: Game::new(),
+            evaluators: Evaluators::load(),
+            opening_book: None,
+            tablebases: None,
+            transposition_tables: (0..2).map(|_| Arc::new(TranspositionTable::new(DEFAULT_TABLE_MEGABYTES))).collect(),
+            button_bar: ButtonBar::new((4., 4.), Horizontal, 4.),
+
+            view_intro: ViewIntro::new().await,
+
+            view_about: ViewAbout::new(view_about_tx).await,
+            view_about_rx,
+
+            view_settings: ViewSettings::new(view_settings_tx).await,
+            view_settings_rx,
+
+            view_rules: ViewRules::new(view_rules_tx).await,
+            view_rules_rx,
+
+            view_game: ViewGame::new(view_game_tx, COLS, ROWS).await,
+            view_game_rx,
+
+            previous_state: None,
+            state: NextPlayer,
+            start_position: Game::new(),
+            node_history: Vec::new(),
+            history_ply: None,
+            record: GameRecord::new(&Game::new()),
+            scores: vec![None],
+            clock: Clock::new(TimeControl::Unlimited),
+            game_over: None,
+            draw_offer: None,
+            ai_scores: [None; 2],
+            hint_rx: None,
+            expected_reply: None,
+            ponder: None,
+            ai_tx, ai_rx,
+            pv_text: String::from(""),
+            fullscreen: false,
+            flip_button: Button::new((20., 736.), 0, "Flip", None),
+            undo_button: Button::new((20., 708.), 0, "Undo", None),
+            piece_sets: PieceSet::available(),
+            color_schemes: ColorScheme::available(),
+            theme: ThemeChoice::default(),
+            analyzer: Analyzer::new(),
+            spectator: Spectator::new(),
+            reviewer: Reviewer::new(),
+            editor: Editor::new(),
+            puzzles: Puzzles::new(),
+            tutor: Tutor::new(),
+        }
+    }
+
+    pub async fn prepare(&mut self) {
+        // Construct ButtonBar (menu bar)
+        let mut button = Button::new((0.,0.), 0, "About", Some(BAR_ABOUT_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 0, "Rules", Some(BAR_RULES_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 1, "Settings", Some(BAR_SETTINGS_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 1, "Restart", Some(BAR_RESTART_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 0, "Edit", Some(BAR_EDIT_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 0, "Resign", Some(BAR_RESIGN_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 0, "Draw", Some(BAR_DRAW_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 0, "Abort", Some(BAR_ABORT_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 0, "Hint", Some(BAR_HINT_ID));
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 1, "Analyze", Some(BAR_ANALYZE_ID));
+        button.mode = ButtonMode::Toggle;
+        self.button_bar.add_button(button);
+
+        button = Button::new((0.,0.), 0, "Quit", Some(BAR_QUIT_ID));
+        self.button_bar.add_button(button);
+
+        // Use the network evaluator if one was loaded.
+        let evaluator = if self.evaluators.has_network() { EvaluatorKind::Network } else { EvaluatorKind::Handcrafted };
+        self.players.push( Player {id: 0, kind: Human, strength: Strength::Intermediate, hint_strength: Strength::Advanced, search_rounds: 500, evaluator, use_book: true, ponder: true} );
+        self.players.push( Player {id: 1, kind: AI, strength: Strength::Intermediate, hint_strength: Strength::Advanced, search_rounds: 500, evaluator, use_book: true, ponder: true} );
+
+        let book = OpeningBook::load_or_empty(OPENING_BOOK_FILE);
+        if !book.is_empty() {
+            self.opening_book = Some(Arc::new(book));
+        }
+        self.tablebases = Tablebases::load_dir(TABLEBASE_DIR).map(Arc::new);
+        self.game.prepare();
+        self.start_position = self.game;
+        self.record = GameRecord::new(&self.game);
+        self.node_history = vec![self.game];
+        self.view_settings.set_theme_names(
+            self.piece_sets.iter().map(|set| set.name.clone()).collect(),
+            self.color_schemes.iter().map(|scheme| scheme.name.clone()).collect(),
+        );
+        self.view_settings.prepare(self.players.clone(), self.clock.control, Overlays::default(), self.theme);
+        self.view_game.prepare().await;
+        self.view_game.reset_game(&self.game);
+    }
+
+    /// The main control loop.
+    pub async fn go(&mut self) {
+        loop {
+            // Event and state management
+
+            // Check own events first.
+            if let Some(button_id) = self.button_bar.process_events() {
+                self.bar_command(button_id);
+            }
+            self.process_shortcuts();
+            // View events
+            match self.state {
+                About => {
+                    self.view_about.process_events();
+                    self.check_messages().await;
+                },
+                Settings => {
+                    self.view_settings.process_events();
+                    self.check_messages().await;
+                },
+                Rules => {
+                    self.view_rules.process_events();
+                    self.check_messages().await;
+                }
+                HumanTurn | AIThinking | Analysis | WaitingOnAnimation | Player0Won | Player1Won | Draw => {
+                    self.process_review_events();
+                    if self.flip_button.process_events().is_some() {
+                        self.flip_board();
+                    }
+                    if self.may_undo() && self.undo_button.process_events().is_some() {
+                        self.undo();
+                    }
+                    self.view_game.process_events();
+                    if self.spectating() {
+                        self.spectator.process_events();
+                    }
+                    if self.puzzles.is_active() {
+                        self.puzzles.process_events();
+                    }
+                    self.check_messages().await;
+                },
+                Editing => {
+                    self.editor.process_events();
+                    self.view_game.process_events();
+                    self.check_messages().await;
+                },
+                Tutorial => {
+                    self.tutor.process_events();
+                    self.view_game.process_events();
+                    self.check_messages().await;
+                },
+                AITurnBegin => {
+                    self.begin_ai_turn();
+                },
+                NextPlayer => {
+                    self.next_player();
+                },
+                Exit => {
+                    break;
+                },
+            }
+            // Animation updates
+            let time_delta = Duration::from_secs_f32(get_frame_time());
+            self.update_clock(time_delta);
+            self.update_spectating(time_delta);
+            if self.view_intro.visible {
+                self.view_intro.update(time_delta);
+            }
+            if self.state == WaitingOnAnimation {
+                let active = self.view_game.update(time_delta);
+                if !active && self.state == WaitingOnAnimation {
+                    self.state = NextPlayer;
+                }
+            } else {
+                // Analysis previews, and pieces returning from one.
+                let active = self.view_game.update(time_delta);
+                if !active && self.state == Analysis {
+                    self.advance_preview(time_delta);
+                }
+            }
+            // Drawing
+            set_layout_camera();
+            self.view_game.draw_board();
+            self.view_game.draw_ui(&self.state, &self.pv_text);
+            match self.state {
+                About => {
+                    self.view_about.draw();
+                }
+                Settings => {
+                     self.view_settings.draw();
+                },
+                Rules => {
+                    self.view_rules.draw();
+                }
+                Editing => {
+                    self.editor.draw();
+                }
+                Tutorial => {
+                    self.tutor.draw();
+                }
+                HumanTurn | AIThinking | Analysis | Player0Won | Player1Won | Draw | WaitingOnAnimation if self.spectating() => {
+                    self.spectator.draw();
+                },
+                HumanTurn | AIThinking | Analysis | Player0Won | Player1Won | Draw | WaitingOnAnimation if self.puzzles.is_active() => {
+                    self.puzzles.draw();
+                },
+                _ => {},
+            }
+            self.draw_review_button();
+            if matches!(self.state, HumanTurn | AIThinking | Analysis | WaitingOnAnimation | Player0Won | Player1Won | Draw) {
+                self.flip_button.draw();
+            }
+            if self.may_undo() {
+                self.undo_button.draw();
+            }
+            // ButtonBar (menu)
+            self.button_bar.draw();
+
+            // Intro draws last since it is on top of other views.
+            if self.view_intro.visible {
+                self.view_intro.draw();
+            }
+
+            // Call next_frame for non-transitional states.
+            match self.state {
+                NextPlayer | AITurnBegin => {},
+                _ => next_frame().await,
+            }
+        }
+    }
+
+    /// Carries out the menu command of the button bar's button.
+    fn bar_command(&mut self, button_id: usize) {
+        match button_id {
+            BAR_ABOUT_ID => {
+                self.previous_state = Some(self.state);
+                self.state = About;
+                self.button_bar.visible = false;
+            },
+            BAR_RULES_ID => {
+                self.previous_state = Some(self.state);
+                self.state = Rules;
+                self.button_bar.visible = false;
+            }
+            BAR_SETTINGS_ID => {
+                self.previous_state = Some(self.state);
+                self.state = Settings;
+                self.button_bar.visible = false;
+            }
+            BAR_RESTART_ID => {
+                self.start_series();
+                self.restart();
+            }
+            BAR_ANALYZE_ID => {
+                if self.analyzer.is_running() {
+                    self.stop_analysis();
+                    self.next_player();
+                } else {
+                    self.start_analysis();
+                }
+            }
+            BAR_EDIT_ID => self.start_editing(),
+            BAR_HINT_ID => self.request_hint(),
+            BAR_RESIGN_ID => self.resign(),
+            BAR_DRAW_ID => self.offer_draw(),
+            BAR_ABORT_ID => self.abort(),
+            BAR_QUIT_ID => self.state = Exit,
+            _ => panic!(),
+        }
+    }
+
+    async fn check_messages(&mut self) {
+        // From ViewAbout
+        let received = self.view_about_rx.try_recv();
+        if received.is_ok() {
+            match received.unwrap() {
+                ViewAboutMessage::ShouldClose => {
+                    self.button_bar.visible = true;
+                    self.state = self.previous_state.unwrap();
+                },
+            }
+        }
+
+        // From ViewSettings
+        let received = self.view_settings_rx.try_recv();
+        if received.is_ok() {
+            match received.unwrap() {
+                ViewSettingsMessage::ShouldStart(players, time_control, overlays, theme) => {
+                    self.button_bar.visible = true;
+                    self.view_game.set_overlays(overlays);
+                    self.set_theme(theme);
+                    self.stop_analysis();
+                    self.stop_review();
+                    self.stop_pondering();
+                    self.clear_transposition_tables();
+                    self.players = players;
+                    // Face the board toward a lone human playing second.
+                    let humans: Vec<usize> = self.players.iter().filter(|p| p.kind == Human).map(|p| p.id).collect();
+                    self.view_game.set_flipped(humans == [1]);
+                    self.spectator.drop_pending_move();
+                    self.start_series();
+                    // A new time control starts both clocks afresh.
+                    if time_control != self.clock.control {
+                        self.clock = Clock::new(time_control);
+                    }
+                    self.next_player();
+                },
+            }
+        }
+
+        // From ViewRules
+        let received = self.view_rules_rx.try_recv();
+        if received.is_ok() {
+            match received.unwrap() {
+                ViewRulesMessage::ShouldClose => {
+                    self.button_bar.visible = true;
+                    self.state = self.previous_state.unwrap();
+                },
+                ViewRulesMessage::Puzzles => {
+                    self.button_bar.visible = true;
+                    self.state = self.previous_state.unwrap();
+                    self.open_puzzles();
+                },
+                ViewRulesMessage::Tutorial => self.start_tutorial(),
+            }
+        }
+
+        // From ViewGame
+        let received = self.view_game_rx.try_recv();
+        if received.is_ok() {
+            match received.unwrap() {
+                ViewGameMessage::PieceSelected(id) if self.state == Editing => self.edit_piece(id),
+                ViewGameMessage::SquareSelected(index) if self.state == Editing => self.edit_square(index),
+                ViewGameMessage::ReserveSelected(player, _) if self.state == Editing => self.edit_hand(player),
+                ViewGameMessage::PieceSelected(id) if self.state == Tutorial => self.tutorial_piece(id),
+                ViewGameMessage::SquareSelected(index) if self.state == Tutorial => self.tutorial_square(index),
+                ViewGameMessage::ReserveSelected(_, Some(id)) if self.state == Tutorial => self.tutorial_piece(id),
+                ViewGameMessage::PieceDragStarted(id) => {
+                    self.piece_drag_started(id);
+                },
+                ViewGameMessage::PieceDropped(id, index) => {
+                    self.piece_dropped(id, index);
+                },
+                ViewGameMessage::PieceSelected(id) => {
+                    self.piece_selected(id);
+                },
+                ViewGameMessage::SquareSelected(index) => {
+                    self.square_selected(index);
+                },
+                ViewGameMessage::ReserveSelected(player, top_piece) => {
+                    self.reserve_selected(player, top_piece);
+                },
+                ViewGameMessage::AnalysisLineSelected(rank) => {
+                    self.analysis_line_selected(rank);
+                },
+                ViewGameMessage::PlySelected(ply) => {
+                    self.ply_selected(ply);
+                },
+                ViewGameMessage::MoveTyped(usi) => {
+                    self.move_typed(&usi);
+                },
+                ViewGameMessage::ShouldExit => {
+                    self.state = Exit;
+                },
+            }
+        }
+
+        // From the modes' views, and a game review
+        self.check_spectator_messages();
+        self.check_editor_messages();
+        self.check_puzzle_messages();
+        self.check_tutorial_messages();
+        self.check_review_messages();
+
+        // From a hint search
+        if let Some(hint_rx) = &self.hint_rx {
+            if let Ok(progress) = hint_rx.try_recv() {
+                self.hint_rx = None;
+                self.show_hint(&progress);
+            }
+        }
+
+        // From AI
+        let received = self.ai_rx.try_recv();
+        if received.is_ok() {
+            match received.unwrap() {
+                // From a search begun before the editor or tutorial opened. Leaving them
+                // starts the turn again.
+                _ if matches!(self.state, Editing | Tutorial) => {},
+                AIMessage::AIUpdate(progress) => {
+                    if self.analyzer.is_running() {
+                        self.show_analysis(&progress);
+                    } else if self.ponder.is_some() {
+                        self.pv_text = format!("ponder | {}", self.format_ai_progress(&progress));
+                    } else {
+                    //if self.state == AIThinking {
+                        self.pv_text = self.format_ai_progress(&progress);
+                        if self.history_ply.is_none() {
+                            self.view_game.set_pv_arrows(Self::pv_squares(&self.game, &progress.pv));
+                        }
+                    //}
+                    }
+                }
+                AIMessage::SearchCompleted(progress) if self.analyzer.is_running() => {
+                    self.show_analysis(&progress);
+                },
+                // The AI's flag fell while it was thinking.
+                AIMessage::SearchCompleted(_) if self.game_over.is_some() => {},
+                // From a game since restarted.
+                AIMessage::SearchCompleted(progress) if !self.fits_game(&progress) => {},
+                // When the AI plays itself, its move waits for update_spectating().
+                AIMessage::SearchCompleted(progress) if self.spectating() => {
+                    self.spectator.hold_move(progress);
+                },
+                AIMessage::SearchCompleted(progress) => {
+                    self.play_ai_move(progress);
+                },
+            }
+        }
+    }
+
+    /// Whether the search's move can be played in the current position.
+    fn fits_game(&self, progress: &AIProgress) -> bool {
+        let Some(node) = progress.best_node else { return false };
+        node.last_move
+            .and_then(|the_move| self.game.child_for_move(the_move))
+            .is_some_and(|child| child.position_key() == node.position_key())
+    }
+
+    fn play_ai_move(&mut self, progress: AIProgress) {
+        let p = self.game.current_player;
+        self.expected_reply = progress.pv.get(1).copied();
+        self.ai_scores[p] = Some(progress.score);
+        let node = progress.best_node.unwrap();
+        self.use_node(node);
+        if !progress.from_book {
+            let score = if p == 0 { progress.score } else { -progress.score };
+            self.scores[self.record.moves.len()] = Some(score);
+            self.view_game.set_scores(&self.scores);
+        }
+        self.pv_text = self.format_ai_progress(&progress);
+        self.state = WaitingOnAnimation;
+    }
+
+    fn piece_selected(&mut self, id: usize) {
+        if self.state != HumanTurn { return; }
+        if self.history_ply.is_some() {
+            self.show_present();
+            return;
+        }
+        // Own piece?
+        if self.game.player_for_piece_id(id) == self.game.current_player {
+            // Select it.
+            self.view_game.select_piece(id);
+            // Highlight move-to squares.
+            let move_indices = self.game.move_indices_for_piece(id);
+            self.view_game.set_move_indicies(move_indices);
+        } else {
+            // Opponent's piece. Is it on a move-to square?
+            let location_index = self.game.location_index_for(id);
+            if self.view_game.is_move_index(location_index) {
+                // Capture
+                if let Some(piece_id) = self.view_game.selected_piece_id() {
+                    self.perform_move(piece_id, location_index);
+                }
+            } 
+            // Unselect everything
+            self.view_game.unselect_piece();
+            self.view_game.unhighlight_all_squares();
+        }
+    }
+  
+    // A square with a piece was selected.
+    fn square_selected(&mut self, index: usize) {
+        if self.state != HumanTurn { return; }
+        if self.history_ply.is_some() {
+            self.show_present();
+            return;
+        }
+
+        if self.view_game.is_move_index(index) {
+            // Move
+            if let Some(piece_id) = self.view_game.selected_piece_id() {
+                self.perform_move(piece_id, index);
+            }
+        }
+        // Regardless, unselect everything.
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+    }
+
+    /// A drag of the player's own piece selects it, as a click does, so it follows the
+    /// mouse with its moves highlighted.
+    fn piece_drag_started(&mut self, id: usize) {
+        match self.state {
+            HumanTurn if self.history_ply.is_none() && self.game.player_for_piece_id(id) == self.game.current_player => {
+                self.piece_selected(id);
+            },
+            Tutorial => self.tutorial_drag_started(id),
+            _ => {},
+        }
+    }
+
+    /// A dragged piece dropped on one of its move squares makes the move. Any other drop
+    /// leaves it selected, back where it was.
+    fn piece_dropped(&mut self, id: usize, index: Option<usize>) {
+        if self.view_game.selected_piece_id() != Some(id) { return; }
+        let Some(index) = index else { return };
+        match self.state {
+            HumanTurn if self.view_game.is_move_index(index) => self.square_selected(index),
+            Tutorial => self.tutorial_dropped(id, index),
+            _ => {},
+        }
+    }
+
+    /// A reserve box was clicked beside its pieces. Its top piece is selected, as if
+    /// clicked.
+    fn reserve_selected(&mut self, player: usize, top_piece: Option<usize>) {
+        if self.state != HumanTurn { return; }
+        if player != self.game.current_player { return; }
+        if let Some(id) = top_piece {
+            self.piece_selected(id);
+        }
+    }
+
+    /// Find the child node matching the piece id
+    fn find_node(&mut self, id: usize, location_index: usize) -> Option<Game> {
+        let nodes = self.game.child_nodes(self.game.current_player);
+        for node in nodes {
+            let piece = node.piece_for(id);
+            if piece.location_index == location_index {
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    fn perform_move(&mut self, id: usize, location_index: usize) {
+        let node_option = self.find_node(id, location_index);
+        match node_option {
+            Some(node) => self.play_node(node),
+            None => panic!("Cannot find node in perform_move!")
+        }
+    }
+
+    /// Plays the human's move to the child position.
+    fn play_node(&mut self, node: Game) {
+        // In a puzzle, only a move that still mates in time is played.
+        if let Some(plies) = self.puzzle_plies_left() {
+            if !mate_solver::is_mating_move(&self.game, &node, plies) {
+                self.pv_text = format!("That doesn't mate in {}. Try another move.", plies.div_ceil(2));
+                return;
+            }
+        }
+        self.use_node(node); 
+        self.state = WaitingOnAnimation;
+    }
+
+    fn use_node(&mut self, node: Game) {
+        self.history_ply = None;
+        self.view_game.set_history_selection(None);
+        // A hint for the old position no longer applies.
+        self.hint_rx = None;
+        self.view_game.clear_hint();
+        self.view_game.set_pv_arrows(Vec::new());
+        self.record.moves.push(self.game.usi_for_child(&node));
+        self.scores.push(None);
+        self.spectator.move_made();
+        self.clock.move_made(self.game.current_player);
+        // Moving declines the opponent's draw offer.
+        if self.draw_offer != Some(self.game.current_player) {
+            self.draw_offer = None;
+        }
+        self.view_game.update_with_game(&node);
+        self.view_game.set_last_move(node.last_move_squares(&self.game));
+        self.node_history.push(node);
+        self.game = node;
+        self.update_move_list();
+    }
+
+    fn format_ai_progress(&self, progress: &AIProgress) -> String {
+        // let nodes_string = progress.nodes.to_formatted_string(&Locale::en);
+        // let mut text = format!("nodes: {}", nodes_string);
+        if progress.from_book {
+            return format!("book | {}", self.format_pv(progress));
+        }
+        let percent_string = (progress.percent_complete * 100.0) as usize;
+        let mut text = format!("{}%", percent_string);
+
+        let ms = progress.duration.as_millis();
+        let ms_string = progress.duration.as_millis().to_formatted_string(&Locale::en);
+        if ms > 0 {
+            let nps = (progress.nodes as f64 / ms as f64 * 1_000.0) as usize;
+            let nps_string = nps.to_formatted_string(&Locale::en);
+            text.push_str(&format!(" | ms: {} | nps: {}", ms_string, nps_string));
+        } else {
+            text.push_str(" | ms: 0 | nps: --");
+        }
+        let score_string = (progress.score as isize).to_formatted_string(&Locale::en);
+        text.push_str(&format!(" | score: {}", score_string));
+        text.push_str(&format!(" | {}", self.format_pv(progress)));
+        text
+    }
+
+    fn format_pv(&self, progress: &AIProgress) -> String {
+        format!("pv: {}", Self::format_moves(&self.game, &progress.pv))
+    }
+
+    /// The from and to squares of each move of the line played from the game.
+    fn pv_squares(game: &Game, moves: &[Move]) -> Vec<(Option<usize>, usize)> {
+        let mut squares = Vec::new();
+        let mut node = *game;
+        for the_move in moves {
+            let Some(child) = node.child_for_move(*the_move) else { break };
+            squares.extend(child.last_move_squares(&node));
+            node = child;
+        }
+        squares
+    }
+
+    /// Lists the moves, naming the pieces as they are in game.
+    fn format_moves(game: &Game, moves: &[Move]) -> String {
+        let mut text = String::new();
+        for i in 0..moves.len() {
+            let the_move = moves[i];
+            let piece = game.piece_for(the_move.0);
+            let piece_str = piece.string_rep();
+            let cap_str = match the_move.2 {
+                true => "x",
+                false => "",
+            };
+            text.push_str(&format!("{}{}{}", piece_str, cap_str, the_move.1));
+            if i < moves.len() - 1 {
+                text.push_str(", ");
+            }
+        }
+        text
+    }
+
+    /// Formats an analysis score for the player to move: "+1.5", or "win in 3" (plies)
+    /// when the search sees the end of the game.
+    fn format_score(score: f64) -> String {
+        if score.abs() >= WIN_LOSS_VAL / 2.0 {
+            let plies = (WIN_LOSS_VAL - score.abs()).round() as usize;
+            let outcome = if score > 0. { "win" } else { "loss" };
+            return format!("{} in {}", outcome, plies);
+        }
+        format!("{:+.1}", score)
+    }
+
+    /// Searches for a move to suggest to the human, at their hint strength. The move is
+    /// highlighted, not played, and the ply is noted in the game record.
+    fn request_hint(&mut self) {
+        if self.state != HumanTurn || self.hint_rx.is_some() { return; }
+        let ply = self.record.moves.len();
+        if !self.record.hints.contains(&ply) {
+            self.record.hints.push(ply);
+        }
+
+        // These variables are captured by the thread.
+        let player = self.players[self.game.current_player];
+        let game_copy = self.game;
+        let context = self.ai_context(&player);
+        let (tx, rx) = mpsc::channel();
+        self.hint_rx = Some(rx);
+        self.pv_text = format!("hint ({}): thinking...", player.hint_strength.name());
+
+        std::thread::spawn(move || {
+            AI::hint(player, game_copy, context, tx);
+        });
+    }
+
+    fn show_hint(&mut self, progress: &AIProgress) {
+        if self.state != HumanTurn { return; }
+        let Some(the_move) = progress.best_node.and_then(|node| node.last_move) else { return };
+        self.view_game.show_hint(the_move.0, the_move.1);
+        self.pv_text = format!("hint: {}", Self::format_moves(&self.game, &[the_move]));
+    }
+
+    /// Shows the position at the ply clicked on the score graph or in the move list, or
+    /// goes back to the current one if it is clicked again.
+    fn ply_selected(&mut self, ply: usize) {
+        if !matches!(self.state, HumanTurn | AIThinking | Player0Won | Player1Won | Draw) { return; }
+        let present = self.node_history.len() - 1;
+        if ply >= present || self.history_ply == Some(ply) {
+            self.show_present();
+            return;
+        }
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+        self.history_ply = Some(ply);
+        self.view_game.set_history_selection(Some(ply));
+        self.view_game.update_with_game(&self.node_history[ply]);
+        self.mark_last_move(ply);
+        let score = match self.scores[ply] {
+            Some(score) => format!(" | player 1: {}", Self::format_score(score)),
+            None => String::new(),
+        };
+        // The review's verdict on the move that led here.
+        let annotation = self.record.annotations.iter()
+            .find(|annotation| annotation.ply + 1 == ply)
+            .map(|annotation| format!(" | {}, best {}", annotation.judgement.name(), annotation.best_move))
+            .unwrap_or_default();
+        self.pv_text = format!("ply {} of {}{}{} | click again to return", ply, present, score, annotation);
+    }
+
+    /// Goes back to the current position from one shown from history.
+    fn show_present(&mut self) {
+        if self.history_ply.take().is_none() { return; }
+        self.view_game.set_history_selection(None);
+        self.view_game.update_with_game(&self.game);
+        self.mark_last_move(self.node_history.len() - 1);
+        self.pv_text = String::new();
+    }
+
+    /// Marks the move that led to the position at ply, if any.
+    fn mark_last_move(&mut self, ply: usize) {
+        let squares = ply.checked_sub(1)
+            .and_then(|parent| self.node_history[ply].last_move_squares(&self.node_history[parent]));
+        self.view_game.set_last_move(squares);
+    }
+
+    fn next_player(&mut self) {
+        // A game ended by the app, such as on time, stays over.
+        let state = match self.game_over {
+            Some((state, _)) => state,
+            None => *self.game.update_state(),
+        };
+        if state != GameState::Ongoing && self.record.result.is_none() {
+            self.record.result = Some(state);
+            self.record.reason = Some(self.game_over.map_or(EndReason::KingCaptured, |(_, reason)| reason));
+            self.stop_pondering();
+            if self.spectating() && self.record.reason != Some(EndReason::Aborted) {
+                self.count_result(state);
+            }
+            // Puzzles aren't games worth keeping.
+            if !self.puzzles.is_active() {
+                if let Err(error) = self.record.append_to(SAVED_GAMES_FILE) {
+                    println!("Could not save the game: {}", error);
+                }
+            }
+        }
+        match state {
+            GameState::Draw => {
+                self.state = Draw;
+            },
+            GameState::WinPlayer0 => {
+                self.state = Player0Won;
+            },
+            GameState::WinPlayer1 => {
+                self.state = Player1Won;
+            },
+            _ if self.puzzles.is_active() => self.play_puzzle_turn(),
+            _ => {
+                let p = self.game.current_player;
+                if self.players[p].kind == Human {
+                    self.state = HumanTurn;
+                    self.start_pondering();
+                } else {
+                    self.state = AITurnBegin;
+                    self.pv_text = String::new();
+                }
+            },
+        }
+    }
+
+    fn begin_ai_turn(&mut self) {
+        self.state = AIThinking;
+
+        // If the human played the expected move, the ponder search goes on as this turn's.
+        if self.take_ponder_hit() { return; }
+
+        // These variables are captured by the thread.
+        let player = self.players[self.game.current_player];
+        let game_copy = self.game;
+        let context = self.ai_context(&player);
+        let message_sender = AISender::new(self.ai_tx.clone(), None);
+
+        std::thread::spawn(move || {
+            AI::think(player, game_copy, context, message_sender);
+        });
+    }
+
+    fn ai_context(&self, player: &Player) -> AIContext {
+        AIContext {
+            evaluator: self.evaluators.evaluator(player.evaluator),
+            book: self.opening_book.clone(),
+            tablebases: self.tablebases.clone(),
+            transposition_table: Some(self.transposition_tables[player.id].clone()),
+            time_budget: self.clock.move_budget(player.id),
+        }
+    }
+
+    /// Starts a new game from the starting position, or the one set in the editor, with
+    /// the same players and time control.
+    fn restart(&mut self) {
+        self.stop_analysis();
+        self.stop_review();
+        self.stop_pondering();
+        self.clear_transposition_tables();
+        self.expected_reply = None;
+        self.game = self.start_position;
+        self.record = GameRecord::new(&self.game);
+        self.node_history = vec![self.game];
+        self.history_ply = None;
+        self.view_game.set_history_selection(None);
+        self.clock = Clock::new(self.clock.control);
+        self.game_over = None;
+        self.draw_offer = None;
+        self.ai_scores = [None; 2];
+        self.spectator.drop_pending_move();
+        self.scores = vec![None];
+        self.view_game.set_scores(&self.scores);
+        self.update_move_list();
+        self.view_game.set_end_reason(None);
+        self.hint_rx = None;
+        self.view_game.reset_game(&self.game);
+        self.state = NextPlayer;
+    }
+
+    /// Whether moves can be taken back: it is a human's turn in a game without a clock,
+    /// and a move of theirs to take back.
+    fn may_undo(&self) -> bool {
+        self.state == HumanTurn && !self.puzzles.is_active() && !self.clock.is_enabled() && self.undo_ply().is_some()
+    }
+
+    /// The ply of the human's previous turn.
+    fn undo_ply(&self) -> Option<usize> {
+        (0..self.node_history.len() - 1).rev()
+            .find(|ply| self.players[self.node_history[*ply].current_player].kind == Human)
+    }
+
+    /// Takes moves back to the human's previous turn: the last move between two humans,
+    /// or the AI's reply too when playing it.
+    fn undo(&mut self) {
+        if !self.may_undo() { return }
+        let Some(ply) = self.undo_ply() else { return };
+        self.stop_pondering();
+        self.hint_rx = None;
+        self.view_game.clear_hint();
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+        self.history_ply = None;
+        self.view_game.set_history_selection(None);
+        self.expected_reply = None;
+        self.draw_offer = None;
+        self.node_history.truncate(ply + 1);
+        self.game = self.node_history[ply];
+        self.record.moves.truncate(ply);
+        self.record.hints.retain(|hint_ply| *hint_ply < ply);
+        self.scores.truncate(ply + 1);
+        self.ai_scores = [None; 2];
+        self.view_game.set_scores(&self.scores);
+        self.update_move_list();
+        self.view_game.reset_game(&self.game);
+        self.mark_last_move(ply);
+        self.pv_text = String::new();
+        self.state = NextPlayer;
+    }
+
+    fn flip_board(&mut self) {
+        let flipped = !self.view_game.is_flipped();
+        self.view_game.set_flipped(flipped);
+    }
+
+    /// Changes the look of the board to the theme, where it differs from the one in use.
+    fn set_theme(&mut self, theme: ThemeChoice) {
+        if theme.piece_set != self.theme.piece_set {
+            self.view_game.set_piece_set(&self.piece_sets[theme.piece_set]);
+        }
+        if theme.color_scheme != self.theme.color_scheme {
+            self.view_game.set_color_scheme(self.color_schemes[theme.color_scheme].clone());
+        }
+        self.theme = theme;
+    }
+
+    /// Lists the moves played, marked with any judgement from a review.
+    fn update_move_list(&mut self) {
+        let entries = self.node_history.windows(2).enumerate()
+            .map(|(ply, pair)| {
+                let the_move = pair[1].last_move.map(|the_move| Self::format_moves(&pair[0], &[the_move]));
+                let judgement = self.record.annotations.iter()
+                    .find(|annotation| annotation.ply == ply)
+                    .map(|annotation| annotation.judgement);
+                let symbol = judgement.map_or("", |judgement| judgement.symbol());
+                (format!("{}.{}{}", ply + 1, the_move.unwrap_or_default(), symbol), judgement)
+            })
+            .collect();
+        self.view_game.set_move_list(entries);
+    }
+
+    /// Empties the transposition tables, whose entries depend on the evaluator.
+    fn clear_transposition_tables(&mut self) {
+        for table in &self.transposition_tables {
+            table.clear();
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,68 @@
+// Ponder
+// Searching on the human's time: while the human thinks, an AI player that ponders
+// searches the position after the reply it expects. If the human plays it, that search
+// goes on as the AI's turn.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::ai::AI;
+use crate::ai_sender::AISender;
+use crate::game::{Game, GameState};
+
+use super::Controller;
+use super::PlayerKind::*;
+
+/// A search of the position after the AI's expected reply, on the human's time.
+pub struct Ponder {
+    game: Game,
+    pondering: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl Controller {
+    /// While the human thinks, searches the position after the reply the AI expects,
+    /// if the AI player ponders. See take_ponder_hit().
+    pub(super) fn start_pondering(&mut self) {
+        let player = self.players[1 - self.game.current_player];
+        if player.kind != AI || !player.ponder || self.ponder.is_some() { return; }
+        let Some(reply) = self.expected_reply else { return };
+        let Some(mut expected) = self.game.child_for_move(reply) else { return };
+        if *expected.update_state() != GameState::Ongoing { return; }
+
+        // These variables are captured by the thread.
+        let pondering = Arc::new(AtomicBool::new(true));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (pondering_clone, stop_clone) = (pondering.clone(), stop.clone());
+        let context = self.ai_context(&player);
+        let message_sender = AISender::new(self.ai_tx.clone(), None);
+
+        let thread = std::thread::spawn(move || {
+            AI::ponder(player, expected, context, pondering_clone, stop_clone, message_sender);
+        });
+        self.ponder = Some(Ponder { game: expected, pondering, stop, thread });
+    }
+
+    /// If the human played the expected move, lets the ponder search go on as this turn's
+    /// and returns true. Otherwise stops it.
+    pub(super) fn take_ponder_hit(&mut self) -> bool {
+        let Some(ponder) = self.ponder.take() else { return false };
+        if ponder.game.last_move == self.game.last_move {
+            ponder.pondering.store(false, Ordering::Relaxed);
+            return true;
+        }
+        self.ponder = Some(ponder);
+        self.stop_pondering();
+        false
+    }
+
+    pub(super) fn stop_pondering(&mut self) {
+        let Some(ponder) = self.ponder.take() else { return };
+        ponder.stop.store(true, Ordering::Relaxed);
+        let _ = ponder.thread.join();
+        // Drop its progress updates.
+        while self.ai_rx.try_recv().is_ok() {}
+    }
+}
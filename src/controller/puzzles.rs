@@ -0,0 +1,142 @@
+// Puzzles
+// Mate puzzles, opened from the Rules page. The human attacks and the mate solver
+// defends; a move that doesn't mate in time is refused.
+
+use std::sync::mpsc::{self, Receiver};
+
+use crate::game::{EndReason, Game};
+use crate::mate_solver;
+use crate::puzzle::Puzzle;
+use crate::view::view_puzzle::{ViewPuzzle, ViewPuzzleMessage};
+
+use super::AppState::*;
+use super::Controller;
+
+pub struct Puzzles {
+    view: ViewPuzzle,
+    view_rx: Receiver<ViewPuzzleMessage>,
+    puzzles: Vec<Puzzle>,
+    current: Option<usize>, // the puzzle being solved, set in puzzle mode
+}
+
+impl Puzzles {
+    pub fn new() -> Self {
+        let (view_tx, view_rx) = mpsc::channel();
+        Self {
+            view: ViewPuzzle::new(view_tx),
+            view_rx,
+            puzzles: Puzzle::builtin(),
+            current: None,
+        }
+    }
+
+    /// True in puzzle mode.
+    pub fn is_active(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Leaves puzzle mode. The caller sets up the next game.
+    pub fn close(&mut self) {
+        self.current = None;
+    }
+
+    pub fn process_events(&mut self) {
+        self.view.process_events();
+    }
+
+    pub fn draw(&mut self) {
+        self.view.draw();
+    }
+
+    /// The plies the puzzle's mate may still take, counting the attacker's next move.
+    pub fn plies_left(&self, plies_played: usize) -> Option<usize> {
+        let puzzle = &self.puzzles[self.current?];
+        Some(puzzle.plies().saturating_sub(plies_played))
+    }
+}
+
+impl Controller {
+    /// Opens the puzzle last solved, or the first.
+    pub(super) fn open_puzzles(&mut self) {
+        self.start_puzzle(self.puzzles.current.unwrap_or(0));
+    }
+
+    /// Sets up the puzzle at index for the human to solve, whoever the players are.
+    fn start_puzzle(&mut self, index: usize) {
+        let Some(puzzle) = self.puzzles.puzzles.get(index) else {
+            self.pv_text = String::from("There are no puzzles.");
+            return;
+        };
+        let game = match puzzle.start() {
+            Ok(game) => game,
+            Err(error) => {
+                println!("Could not start puzzle {}: {}", puzzle.name, error);
+                return;
+            },
+        };
+        let title = format!("Puzzle {} of {}: {}", index + 1, self.puzzles.puzzles.len(), puzzle.name);
+        self.puzzles.view.set_title(title);
+        self.puzzles.current = Some(index);
+        self.start_position = game;
+        self.start_series();
+        self.restart();
+    }
+
+    /// The plies the puzzle's mate may still take, or None outside puzzle mode.
+    pub(super) fn puzzle_plies_left(&self) -> Option<usize> {
+        self.puzzles.plies_left(self.record.moves.len())
+    }
+
+    /// In a puzzle the human attacks and the solver defends as long as it can, until
+    /// the defender has no move left.
+    pub(super) fn play_puzzle_turn(&mut self) {
+        let attacker = self.start_position.current_player;
+        if self.game.current_player == attacker {
+            self.state = HumanTurn;
+            return;
+        }
+        let plies = self.puzzle_plies_left().unwrap_or(0);
+        match mate_solver::best_defense(&self.game, plies.saturating_sub(1)) {
+            Some(reply) => {
+                self.use_node(reply);
+                self.state = WaitingOnAnimation;
+            },
+            None => {
+                self.pv_text = String::from("Solved!");
+                self.end_game(Self::win_for(attacker), EndReason::Checkmate);
+            },
+        }
+    }
+
+    /// Shows the puzzle's solution, with the pieces named as in the move list.
+    fn show_solution(&mut self) {
+        let Some(index) = self.puzzles.current else { return };
+        let Ok(positions) = self.puzzles.puzzles[index].positions() else { return };
+        let moves: Vec<String> = positions.windows(2)
+            .filter_map(|pair| pair[1].last_move.map(|the_move| Self::format_moves(&pair[0], &[the_move])))
+            .collect();
+        self.pv_text = format!("solution: {}", moves.join(", "));
+    }
+
+    /// Leaves puzzle mode for a game from the starting position.
+    fn exit_puzzles(&mut self) {
+        self.puzzles.close();
+        self.start_position = Game::new();
+        self.start_position.prepare();
+        self.start_series();
+        self.restart();
+    }
+
+    pub(super) fn check_puzzle_messages(&mut self) {
+        let Ok(message) = self.puzzles.view_rx.try_recv() else { return };
+        let index = self.puzzles.current.unwrap_or(0);
+        let count = self.puzzles.puzzles.len();
+        match message {
+            ViewPuzzleMessage::Previous => self.start_puzzle((index + count - 1) % count),
+            ViewPuzzleMessage::Next => self.start_puzzle((index + 1) % count),
+            ViewPuzzleMessage::Retry => self.restart(),
+            ViewPuzzleMessage::ShowSolution => self.show_solution(),
+            ViewPuzzleMessage::Exit => self.exit_puzzles(),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+// Review
+// The post-game review, started with the Review button: every position of the finished
+// game is searched in the background, and the moves that fell short of the engine's
+// best are marked in the move list.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::game_analysis::{self, GameReview, Judgement, ReviewMessage};
+use crate::game_record::ANALYZED_GAMES_FILE;
+use crate::view::button::Button;
+
+use super::AppState::*;
+use super::Controller;
+
+/// The search time for each position of a finished game's review.
+const REVIEW_MOVE_TIME: Duration = Duration::from_millis(500);
+
+pub struct Reviewer {
+    button: Button, // shown once a game is over
+    rx: Option<Receiver<ReviewMessage>>, // set while a review runs
+    stop: Option<Arc<AtomicBool>>,
+    reviewed: bool, // the game's review is done
+}
+
+impl Reviewer {
+    pub fn new() -> Self {
+        Self {
+            button: Button::new((360., 136.), 1, "Review", None),
+            rx: None,
+            stop: None,
+            reviewed: false,
+        }
+    }
+}
+
+impl Controller {
+    /// Whether the finished game can be reviewed: it has moves, and hasn't been
+    /// reviewed yet.
+    fn may_review(&self) -> bool {
+        matches!(self.state, Player0Won | Player1Won | Draw) && !self.record.moves.is_empty()
+            && self.reviewer.rx.is_none() && !self.reviewer.reviewed && !self.puzzles.is_active()
+    }
+
+    /// Starts a review when the Review button is clicked.
+    pub(super) fn process_review_events(&mut self) {
+        if self.may_review() && self.reviewer.button.process_events().is_some() {
+            self.start_review();
+        }
+    }
+
+    pub(super) fn draw_review_button(&mut self) {
+        if self.may_review() {
+            self.reviewer.button.draw();
+        }
+    }
+
+    /// Searches every position of the finished game in the background, to judge each
+    /// move against the engine's best.
+    fn start_review(&mut self) {
+        self.stop_analysis();
+        // These variables are captured by the thread.
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop.clone();
+        let positions = self.node_history.clone();
+        let mut context = self.ai_context(&self.players[0]);
+        context.book = None;
+        context.time_budget = None;
+        let (tx, rx) = mpsc::channel();
+        self.reviewer.rx = Some(rx);
+        self.reviewer.stop = Some(stop);
+        self.pv_text = String::from("review: starting");
+
+        std::thread::spawn(move || {
+            game_analysis::review(positions, context, REVIEW_MOVE_TIME, stop_clone, tx);
+        });
+    }
+
+    pub(super) fn stop_review(&mut self) {
+        if let Some(stop) = self.reviewer.stop.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+        self.reviewer.rx = None;
+        self.reviewer.reviewed = false;
+    }
+
+    /// Puts the review's scores on the graph and its judgements in the move list, and
+    /// saves the annotated game.
+    fn show_review(&mut self, review: GameReview) {
+        self.scores = review.scores.into_iter().map(Some).collect();
+        self.view_game.set_scores(&self.scores);
+        self.record.annotations = review.annotations;
+        self.reviewer.reviewed = true;
+        self.update_move_list();
+        if let Err(error) = self.record.append_to(ANALYZED_GAMES_FILE) {
+            println!("Could not save the reviewed game: {}", error);
+        }
+
+        let count = |judgement: Judgement| self.record.annotations.iter().filter(|annotation| annotation.judgement == judgement).count();
+        self.pv_text = format!("review: {} inaccuracies, {} mistakes, {} blunders | click a move to see it",
+            count(Judgement::Inaccuracy), count(Judgement::Mistake), count(Judgement::Blunder));
+    }
+
+    pub(super) fn check_review_messages(&mut self) {
+        let Some(rx) = &self.reviewer.rx else { return };
+        match rx.try_recv() {
+            Ok(ReviewMessage::Progress(done, total)) => {
+                self.pv_text = format!("review: {} of {} positions", done, total);
+            },
+            Ok(ReviewMessage::Done(review)) => {
+                self.reviewer.rx = None;
+                self.reviewer.stop = None;
+                self.show_review(review);
+            },
+            Err(_) => {},
+        }
+    }
+}
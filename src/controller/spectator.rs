@@ -0,0 +1,151 @@
+// Spectator
+// Watching the AI play itself: its moves are paced, the game can be paused or stepped
+// through, and games can be played back-to-back as a series with a running tally.
+
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use crate::ai::AIProgress;
+use crate::game::GameState;
+use crate::view::view_spectator::{ViewSpectator, ViewSpectatorMessage};
+
+use super::AppState::*;
+use super::Controller;
+use super::PlayerKind::*;
+
+/// The default pause between moves, in seconds.
+const SPECTATOR_DELAY: f32 = 1.0;
+/// The pause before the next game of a series, in seconds.
+const NEXT_GAME_PAUSE: f32 = 2.0;
+
+pub struct Spectator {
+    view: ViewSpectator,
+    view_rx: Receiver<ViewSpectatorMessage>,
+    pending_move: Option<AIProgress>, // the AI's move, held until it may be played
+    paused: bool,
+    step_requested: bool,
+    move_delay: f32, // the least time between moves, in seconds
+    move_wait: f32, // the time since the last move
+    series_games: usize, // the games to play back-to-back
+    series_game: usize, // the game being played, from 1
+    tally: [usize; 3], // player 0 wins, player 1 wins, draws
+}
+
+impl Spectator {
+    pub fn new() -> Self {
+        let (view_tx, view_rx) = mpsc::channel();
+        Self {
+            view: ViewSpectator::new(view_tx, SPECTATOR_DELAY, 1),
+            view_rx,
+            pending_move: None,
+            paused: false,
+            step_requested: false,
+            move_delay: SPECTATOR_DELAY,
+            move_wait: 0.,
+            series_games: 1,
+            series_game: 1,
+            tally: [0; 3],
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Holds the AI's move until update_spectating() plays it.
+    pub fn hold_move(&mut self, progress: AIProgress) {
+        self.pending_move = Some(progress);
+    }
+
+    /// Forgets a held move, which no longer fits the game.
+    pub fn drop_pending_move(&mut self) {
+        self.pending_move = None;
+    }
+
+    /// Starts the wait for the next move.
+    pub fn move_made(&mut self) {
+        self.move_wait = 0.;
+    }
+
+    pub fn process_events(&mut self) {
+        self.view.process_events();
+    }
+
+    pub fn draw(&mut self) {
+        self.view.draw();
+    }
+
+    fn update_tally(&mut self) {
+        let text = format!("Game {} of {} | Player 1: {}  Player 2: {}  Draws: {}",
+            self.series_game, self.series_games.max(self.series_game), self.tally[0], self.tally[1], self.tally[2]);
+        self.view.set_tally(text);
+    }
+}
+
+impl Controller {
+    /// True when the AI plays itself.
+    pub(super) fn spectating(&self) -> bool {
+        self.players.iter().all(|player| player.kind == AI) && !self.puzzles.is_active()
+    }
+
+    /// Counts the current game as the first of a new series.
+    pub(super) fn start_series(&mut self) {
+        self.spectator.series_game = 1;
+        self.spectator.tally = [0; 3];
+        self.spectator.update_tally();
+    }
+
+    /// Adds the finished game to the series' tally.
+    pub(super) fn count_result(&mut self, state: GameState) {
+        let index = match state {
+            GameState::WinPlayer0 => 0,
+            GameState::WinPlayer1 => 1,
+            _ => 2,
+        };
+        self.spectator.tally[index] += 1;
+        self.spectator.update_tally();
+    }
+
+    /// Paces the game when the AI plays itself: its move is played once the delay since
+    /// the last move has passed, unless paused, and the next game of a series starts
+    /// after a finished one.
+    pub(super) fn update_spectating(&mut self, time_delta: Duration) {
+        if !self.spectating() { return; }
+        let spectator = &mut self.spectator;
+        spectator.move_wait += time_delta.as_secs_f32();
+        // A step goes on at once; otherwise wait out the delay.
+        let may_go_on = |delay: f32| spectator.step_requested || (!spectator.paused && spectator.move_wait >= delay);
+
+        match self.state {
+            AIThinking if spectator.pending_move.is_some() && may_go_on(spectator.move_delay) => {
+                spectator.step_requested = false;
+                let progress = spectator.pending_move.take().unwrap();
+                self.play_ai_move(progress);
+            },
+            Player0Won | Player1Won | Draw if spectator.series_game < spectator.series_games
+                && may_go_on(NEXT_GAME_PAUSE.max(spectator.move_delay)) => {
+                spectator.step_requested = false;
+                spectator.series_game += 1;
+                spectator.update_tally();
+                self.restart();
+            },
+            _ => {},
+        }
+    }
+
+    pub(super) fn check_spectator_messages(&mut self) {
+        let Ok(message) = self.spectator.view_rx.try_recv() else { return };
+        match message {
+            ViewSpectatorMessage::Paused(paused) => self.spectator.paused = paused,
+            ViewSpectatorMessage::Step => {
+                self.spectator.paused = true;
+                self.spectator.step_requested = true;
+            },
+            ViewSpectatorMessage::DelayChanged(delay) => self.spectator.move_delay = delay,
+            ViewSpectatorMessage::GamesChanged(games) => {
+                self.spectator.series_games = games;
+                self.spectator.update_tally();
+            },
+        }
+    }
+}
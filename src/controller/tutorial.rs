@@ -0,0 +1,189 @@
+// Tutorial
+// The lessons opened from the Rules page. Each sets up a position and asks for moves of
+// one piece; the learner keeps the move, so the steps are all theirs.
+
+use std::sync::mpsc::{self, Receiver};
+
+use crate::game::{Game, GameLocation};
+use crate::tutorial::Lesson;
+use crate::view::view_tutorial::{ViewTutorial, ViewTutorialMessage};
+
+use super::AppState::*;
+use super::Controller;
+
+pub struct Tutor {
+    view: ViewTutorial,
+    view_rx: Receiver<ViewTutorialMessage>,
+    lessons: Vec<Lesson>,
+    lesson: usize, // the lesson shown
+    step: usize, // the step to make next
+    game: Game, // the lesson's position
+}
+
+impl Tutor {
+    pub fn new() -> Self {
+        let (view_tx, view_rx) = mpsc::channel();
+        Self {
+            view: ViewTutorial::new(view_tx),
+            view_rx,
+            lessons: Lesson::builtin(),
+            lesson: 0,
+            step: 0,
+            game: Game::new(),
+        }
+    }
+
+    /// The lesson's position.
+    pub fn game(&self) -> Game {
+        self.game
+    }
+
+    pub fn process_events(&mut self) {
+        self.view.process_events();
+    }
+
+    pub fn draw(&mut self) {
+        self.view.draw();
+    }
+
+    /// Shows the lesson's text with the step to make, or what it taught once done.
+    fn update_instructions(&mut self) {
+        let lesson = &self.lessons[self.lesson];
+        let text = match lesson.steps.get(self.step) {
+            Some(step) => format!("{}\n\n{}", lesson.text, step.prompt),
+            None => format!("{}\n\nPress Next for the next lesson.", lesson.done),
+        };
+        self.view.set_instructions(&text);
+    }
+}
+
+impl Controller {
+    /// Leaves the game for the tutorial's first lesson. Exiting the tutorial goes back to
+    /// the game.
+    pub(super) fn start_tutorial(&mut self) {
+        if self.tutor.lessons.is_empty() { return; }
+        self.stop_analysis();
+        self.stop_review();
+        self.stop_pondering();
+        self.spectator.drop_pending_move();
+        self.hint_rx = None;
+        self.view_game.clear_hint();
+        self.button_bar.visible = false;
+        self.state = Tutorial;
+        self.show_lesson(0);
+    }
+
+    /// Sets up the lesson's position and highlights the moves of the piece it is about.
+    fn show_lesson(&mut self, index: usize) {
+        let lesson = &self.tutor.lessons[index];
+        let game = match lesson.start() {
+            Ok(game) => game,
+            Err(error) => {
+                println!("Could not start lesson {}: {}", lesson.title, error);
+                return;
+            },
+        };
+        let shown_piece = lesson.shown_piece(&game);
+        let title = format!("Lesson {} of {}: {}", index + 1, self.tutor.lessons.len(), lesson.title);
+        self.tutor.view.set_title(title);
+        self.tutor.lesson = index;
+        self.tutor.step = 0;
+        self.tutor.game = game;
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+        self.view_game.reset_game(&game);
+        if let Some(id) = shown_piece {
+            self.select_lesson_piece(id);
+        }
+        self.pv_text = String::new();
+        self.tutor.update_instructions();
+    }
+
+    fn select_lesson_piece(&mut self, id: usize) {
+        self.view_game.select_piece(id);
+        self.view_game.set_move_indicies(self.tutor.game.move_indices_for_piece(id));
+    }
+
+    /// The learner's own piece is selected; one of the opponent's is a capture.
+    pub(super) fn tutorial_piece(&mut self, id: usize) {
+        let piece = *self.tutor.game.piece_for(id);
+        if piece.player == self.tutor.game.current_player {
+            self.view_game.unhighlight_all_squares();
+            self.select_lesson_piece(id);
+        } else if piece.location == GameLocation::Board {
+            self.tutorial_square(piece.location_index);
+        }
+    }
+
+    /// A drag of the learner's own piece selects it.
+    pub(super) fn tutorial_drag_started(&mut self, id: usize) {
+        if self.tutor.game.player_for_piece_id(id) == self.tutor.game.current_player {
+            self.tutorial_piece(id);
+        }
+    }
+
+    /// The tutorial explains a drop on the wrong square, but not one back in place.
+    pub(super) fn tutorial_dropped(&mut self, id: usize, index: usize) {
+        if self.tutor.game.location_index_for(id) != index
+            || self.tutor.game.piece_for(id).location != GameLocation::Board {
+            self.tutorial_square(index);
+        }
+    }
+
+    /// Makes the selected piece's move to the square if it is the one the step asks for,
+    /// or says what went wrong.
+    pub(super) fn tutorial_square(&mut self, index: usize) {
+        let Some(id) = self.view_game.selected_piece_id() else { return };
+        let lesson = &self.tutor.lessons[self.tutor.lesson];
+        let Some(step) = lesson.steps.get(self.tutor.step) else {
+            self.pv_text = String::from("This lesson is done. Press Next, or Retry to go again.");
+            return;
+        };
+        let child = self.tutor.game.child_nodes_for_piece(id).into_iter()
+            .find(|child| child.piece_for(id).location_index == index);
+        let Some(mut child) = child else {
+            self.pv_text = format!("That piece can't move there. {}", lesson.wrong_text());
+            return;
+        };
+        if !step.accepts(&self.tutor.game.usi_for_child(&child)) {
+            self.pv_text = lesson.wrong_text().to_string();
+            return;
+        }
+
+        // The learner keeps the move.
+        let parent = self.tutor.game;
+        child.current_player = parent.current_player;
+        self.tutor.game = child;
+        self.tutor.step += 1;
+        self.view_game.unselect_piece();
+        self.view_game.unhighlight_all_squares();
+        self.view_game.update_with_game(&child);
+        self.view_game.set_last_move(child.last_move_squares(&parent));
+        let done = self.tutor.step == self.tutor.lessons[self.tutor.lesson].steps.len();
+        if !done {
+            self.select_lesson_piece(id);
+        }
+        self.pv_text = String::from(if done { "Well done!" } else { "Good." });
+        self.tutor.update_instructions();
+    }
+
+    pub(super) fn check_tutorial_messages(&mut self) {
+        let Ok(message) = self.tutor.view_rx.try_recv() else { return };
+        let lesson = self.tutor.lesson;
+        let count = self.tutor.lessons.len();
+        match message {
+            ViewTutorialMessage::Previous => self.show_lesson((lesson + count - 1) % count),
+            ViewTutorialMessage::Next => self.show_lesson((lesson + 1) % count),
+            ViewTutorialMessage::Retry => self.show_lesson(lesson),
+            ViewTutorialMessage::Exit => {
+                self.button_bar.visible = true;
+                self.view_game.unselect_piece();
+                self.view_game.unhighlight_all_squares();
+                self.view_game.reset_game(&self.game);
+                self.mark_last_move(self.node_history.len() - 1);
+                self.pv_text = String::new();
+                self.state = NextPlayer;
+            },
+        }
+    }
+}
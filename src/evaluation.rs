@@ -0,0 +1,321 @@
+// Evaluation
+// Scores non-terminal positions for the AI. The score is a weighted sum of simple
+// features (pieces on the board, pieces in reserve and a bonus per piece and square),
+// which keeps it linear so the weights can be fitted by the tuner and saved to a file.
+// With the "nnue" feature, a neural network (nnue.rs) can be used instead; Evaluator
+// wraps whichever one an AI player uses.
+
+use std::fs;
+#[cfg(feature = "nnue")]
+use std::sync::Arc;
+
+use crate::game::{Game, GameLocation, GRID_COUNT};
+#[cfg(feature = "nnue")]
+use crate::nnue::{Network, NetworkEvaluator, NNUE_FILE};
+use crate::piece::PieceKind;
+
+/// The weights file the app loads at startup, if present. Written by the 'tune' tool.
+pub const EVAL_WEIGHTS_FILE: &str = "eval_weights.txt";
+
+const KIND_COUNT: usize = 10;
+const HAND_KIND_COUNT: usize = 5;
+
+/// The total number of weights.
+pub const WEIGHT_COUNT: usize = KIND_COUNT + HAND_KIND_COUNT + KIND_COUNT * GRID_COUNT;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalWeights {
+    /// Piece values on the board, indexed by PieceKind::index().
+    pub board: [f64; KIND_COUNT],
+    /// Piece values in reserve, indexed by PieceKind::hand_index().
+    pub hand: [f64; HAND_KIND_COUNT],
+    /// Bonus for a piece kind on a square, as seen from the owner's side of the board.
+    pub squares: [[f64; GRID_COUNT]; KIND_COUNT],
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvalWeights {
+    /// The original hand-picked piece values, without square bonuses.
+    pub fn new() -> Self {
+        Self {
+            //      King Gold Silver SilverPro Rook RookPro Bishop BishopPro Pawn PawnPro
+            board: [0.,  9.,  6.,    7.,       6.,  8.,     4.,    3.,       1.,  3.],
+            //     Gold Silver Rook Bishop Pawn
+            hand: [9.,  6.,    6.,  4.,    1.],
+            squares: [[0.; GRID_COUNT]; KIND_COUNT],
+        }
+    }
+
+    /// A cruder version of these weights, for the weaker strength levels: piece values
+    /// only, with pieces in hand worth half as much. Beginners tend to miss drops.
+    pub fn simplified(&self) -> Self {
+        let mut weights = *self;
+        weights.squares = [[0.; GRID_COUNT]; KIND_COUNT];
+        for value in weights.hand.iter_mut() {
+            *value *= 0.5;
+        }
+        weights
+    }
+
+    /// Scores the node from the point of view of the given player.
+    pub fn evaluate(&self, node: &Game, player: usize) -> f64 {
+        let mut score = 0.;
+        for piece in &node.pieces {
+            let val = match piece.location {
+                GameLocation::Board => {
+                    let kind = piece.kind.index();
+                    let square = Self::relative_square(piece.player, piece.location_index);
+                    self.board[kind] + self.squares[kind][square]
+                },
+                GameLocation::Reserve => match piece.kind.hand_index() {
+                    Some(index) => self.hand[index],
+                    None => 0.,
+                },
+                GameLocation::OutOfGame => 0.,
+            };
+            if piece.player == player {
+                score += val;
+            } else {
+                score -= val;
+            }
+        }
+        score
+    }
+
+    /// Returns the features of the node from the point of view of the given player:
+    /// +1 for each of the player's features, -1 for each of the opponent's. The dot product
+    /// of this with to_vec() equals evaluate().
+    pub fn features(node: &Game, player: usize) -> Vec<f64> {
+        let mut features = vec![0.; WEIGHT_COUNT];
+        for piece in &node.pieces {
+            let sign = if piece.player == player { 1. } else { -1. };
+            match piece.location {
+                GameLocation::Board => {
+                    let kind = piece.kind.index();
+                    let square = Self::relative_square(piece.player, piece.location_index);
+                    features[kind] += sign;
+                    features[KIND_COUNT + HAND_KIND_COUNT + kind * GRID_COUNT + square] += sign;
+                },
+                GameLocation::Reserve => {
+                    if let Some(index) = piece.kind.hand_index() {
+                        features[KIND_COUNT + index] += sign;
+                    }
+                },
+                GameLocation::OutOfGame => {},
+            }
+        }
+        features
+    }
+
+    /// Player 1's squares are rotated so both players share the same square bonuses.
+    fn relative_square(player: usize, location_index: usize) -> usize {
+        if player == 0 {
+            location_index
+        } else {
+            GRID_COUNT - 1 - location_index
+        }
+    }
+
+    /// The weights as a flat vector, in the same order as features().
+    pub fn to_vec(&self) -> Vec<f64> {
+        let mut values = Vec::with_capacity(WEIGHT_COUNT);
+        values.extend_from_slice(&self.board);
+        values.extend_from_slice(&self.hand);
+        for squares in &self.squares {
+            values.extend_from_slice(squares);
+        }
+        values
+    }
+
+    /// The inverse of to_vec(). Panics if values is not WEIGHT_COUNT long.
+    pub fn from_slice(values: &[f64]) -> Self {
+        assert_eq!(values.len(), WEIGHT_COUNT, "EvalWeights.from_slice: wrong length");
+        let mut weights = Self::new();
+        weights.board.copy_from_slice(&values[..KIND_COUNT]);
+        weights.hand.copy_from_slice(&values[KIND_COUNT..KIND_COUNT + HAND_KIND_COUNT]);
+        for (kind, squares) in weights.squares.iter_mut().enumerate() {
+            let start = KIND_COUNT + HAND_KIND_COUNT + kind * GRID_COUNT;
+            squares.copy_from_slice(&values[start..start + GRID_COUNT]);
+        }
+        weights
+    }
+
+    /// Loads the weights file, or returns the default weights if there isn't one.
+    pub fn load_or_default(path: &str) -> Self {
+        match Self::load(path) {
+            Ok(weights) => weights,
+            Err(error) => {
+                if std::path::Path::new(path).exists() {
+                    println!("Could not load {}: {}. Using default weights.", path, error);
+                }
+                Self::new()
+            },
+        }
+    }
+
+    /// Reads weights written by save(). Lines are "board", "hand" or "square <PieceKind>"
+    /// followed by the values. Missing lines keep their default values.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut weights = Self::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let mut words = line.split_whitespace();
+            let target: &mut [f64] = match words.next() {
+                Some("board") => &mut weights.board,
+                Some("hand") => &mut weights.hand,
+                Some("square") => {
+                    let name = words.next().unwrap_or("");
+                    let kind = PieceKind::ALL.iter()
+                        .find(|k| format!("{:?}", k) == name)
+                        .ok_or(format!("unknown piece kind '{}'", name))?;
+                    &mut weights.squares[kind.index()]
+                },
+                Some(other) => return Err(format!("unknown line '{}'", other)),
+                None => continue,
+            };
+
+            let values: Vec<f64> = words
+                .map(|w| w.parse::<f64>().map_err(|e| format!("'{}': {}", w, e)))
+                .collect::<Result<_, _>>()?;
+            if values.len() != target.len() {
+                return Err(format!("expected {} values in '{}'", target.len(), line));
+            }
+            target.copy_from_slice(&values);
+        }
+        Ok(weights)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let join = |values: &[f64]| values.iter()
+            .map(|v| format!("{:.3}", v))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        let mut text = String::from("# Mini Shogi evaluation weights\n");
+        text.push_str(&format!("board {}\n", join(&self.board)));
+        text.push_str(&format!("hand {}\n", join(&self.hand)));
+        for kind in PieceKind::ALL {
+            text.push_str(&format!("square {:?} {}\n", kind, join(&self.squares[kind.index()])));
+        }
+        fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
+
+/// Which evaluator an AI player uses. Network needs the "nnue" feature and a network
+/// file; without them the handcrafted evaluator is used instead.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EvaluatorKind {
+    Handcrafted,
+    Network,
+}
+
+/// The search-time evaluator. The search calls make() and unmake() around each child
+/// node so evaluators that keep incremental state (the network) can follow along.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+pub enum Evaluator {
+    Handcrafted(EvalWeights),
+    #[cfg(feature = "nnue")]
+    Network(NetworkEvaluator),
+}
+
+impl Evaluator {
+    /// Prepares for a search from the given root position.
+    pub fn set_root(&mut self, _root: &Game) {
+        match self {
+            Evaluator::Handcrafted(_) => {},
+            #[cfg(feature = "nnue")]
+            Evaluator::Network(network) => network.set_root(_root),
+        }
+    }
+
+    pub fn make(&mut self, _parent: &Game, _child: &Game) {
+        match self {
+            Evaluator::Handcrafted(_) => {},
+            #[cfg(feature = "nnue")]
+            Evaluator::Network(network) => network.make(_parent, _child),
+        }
+    }
+
+    pub fn unmake(&mut self) {
+        match self {
+            Evaluator::Handcrafted(_) => {},
+            #[cfg(feature = "nnue")]
+            Evaluator::Network(network) => network.unmake(),
+        }
+    }
+
+    /// See EvalWeights::simplified(). The network has no simple form, so it is
+    /// replaced by the simplified default weights.
+    pub fn simplified(&self) -> Evaluator {
+        match self {
+            Evaluator::Handcrafted(weights) => Evaluator::Handcrafted(weights.simplified()),
+            #[cfg(feature = "nnue")]
+            Evaluator::Network(_) => Evaluator::Handcrafted(EvalWeights::new().simplified()),
+        }
+    }
+
+    /// Scores the node from the point of view of the given player.
+    pub fn evaluate(&self, node: &Game, player: usize) -> f64 {
+        match self {
+            Evaluator::Handcrafted(weights) => weights.evaluate(node, player),
+            #[cfg(feature = "nnue")]
+            Evaluator::Network(network) => network.evaluate(node, player),
+        }
+    }
+}
+
+/// The evaluators loaded at startup, from which each AI player's Evaluator is made.
+#[derive(Clone)]
+pub struct Evaluators {
+    pub weights: EvalWeights,
+    #[cfg(feature = "nnue")]
+    pub network: Option<Arc<Network>>,
+}
+
+impl Evaluators {
+    /// Loads the weights file and, with the "nnue" feature, the network file.
+    pub fn load() -> Self {
+        Self {
+            weights: EvalWeights::load_or_default(EVAL_WEIGHTS_FILE),
+            #[cfg(feature = "nnue")]
+            network: match Network::load(NNUE_FILE) {
+                Ok(network) => Some(Arc::new(network)),
+                Err(error) => {
+                    println!("No network loaded from {}: {}", NNUE_FILE, error);
+                    None
+                },
+            },
+        }
+    }
+
+    pub fn evaluator(&self, kind: EvaluatorKind) -> Evaluator {
+        match kind {
+            EvaluatorKind::Handcrafted => Evaluator::Handcrafted(self.weights),
+            #[cfg(feature = "nnue")]
+            EvaluatorKind::Network => match &self.network {
+                Some(network) => Evaluator::Network(NetworkEvaluator::new(network.clone())),
+                None => Evaluator::Handcrafted(self.weights),
+            },
+            #[cfg(not(feature = "nnue"))]
+            EvaluatorKind::Network => Evaluator::Handcrafted(self.weights),
+        }
+    }
+
+    /// True if the Network evaluator is actually available.
+    pub fn has_network(&self) -> bool {
+        #[cfg(feature = "nnue")]
+        return self.network.is_some();
+        #[cfg(not(feature = "nnue"))]
+        false
+    }
+}
@@ -2,16 +2,19 @@
 // Primitives are used to keep Game instances on the stack and hashable.
 
 use std::collections::HashSet;
+use std::sync::OnceLock;
 
 use crate::game::GameState::*;
 use crate::game::GameLocation::*;
-use crate::piece::Piece;
+use crate::piece::{Piece, PieceKind};
 use crate::piece::PieceKind::*;
 
 pub const COLS: usize = 5;
 pub const ROWS: usize = 5;
-const GRID_COUNT: usize = 25;
+pub const GRID_COUNT: usize = 25;
 const PIECES_PER_PLAYER: usize = 6;
+/// The most pieces a position can hold, on the board and in hand.
+pub const MAX_PIECES: usize = PIECES_PER_PLAYER * 2;
 
 #[allow(dead_code)]
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -22,6 +25,48 @@ pub enum GameState {
     WinPlayer1,
 }
 
+/// Why a game ended. Only a capture of the king shows in the position; the others are
+/// decided by the app.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EndReason {
+    KingCaptured,
+    /// The loser's clock ran out.
+    Time,
+    Resignation,
+    /// A draw offer was accepted.
+    DrawAgreed,
+    /// Called off before both players had moved. Recorded as a draw.
+    Aborted,
+    /// The loser had no move that saves the king, as at the end of a puzzle.
+    Checkmate,
+}
+
+impl EndReason {
+    /// As written in game records.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EndReason::KingCaptured => "king",
+            EndReason::Time => "time",
+            EndReason::Resignation => "resignation",
+            EndReason::DrawAgreed => "agreement",
+            EndReason::Aborted => "aborted",
+            EndReason::Checkmate => "checkmate",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<EndReason> {
+        match name {
+            "king" => Some(EndReason::KingCaptured),
+            "time" => Some(EndReason::Time),
+            "resignation" => Some(EndReason::Resignation),
+            "agreement" => Some(EndReason::DrawAgreed),
+            "aborted" => Some(EndReason::Aborted),
+            "checkmate" => Some(EndReason::Checkmate),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GameLocation {
     OutOfGame,
@@ -48,6 +93,12 @@ pub struct Game {
     pub last_move: Option<Move>,
 }
 
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Game {
     pub fn new() -> Self {
         // This will be replaced by the proper pieces during 'prepare'.
@@ -203,6 +254,46 @@ impl Game {
         move_indices
     }
 
+    /// Returns true if any of the player's pieces on the board could move to the index.
+    pub fn is_attacked(&self, index: usize, by_player: usize) -> bool {
+        self.pieces.iter()
+            .filter(|p| p.player == by_player && p.location == Board)
+            .any(|p| self.move_indices_for_piece(p.id).contains(&index))
+    }
+
+    /// Returns true if the player's king can be captured by the opponent.
+    pub fn is_in_check(&self, player: usize) -> bool {
+        match self.king_id_(player) {
+            Some(id) => self.is_attacked(self.pieces[id].location_index, 1 - player),
+            None => false,
+        }
+    }
+
+    /// The player's pieces on the board, other than the king, that the opponent attacks
+    /// and none of the player's other pieces defends.
+    pub fn hanging_pieces(&self, player: usize) -> Vec<usize> {
+        self.pieces.iter()
+            .filter(|p| p.player == player && p.location == Board && p.kind != King)
+            .filter(|p| self.is_attacked(p.location_index, 1 - player))
+            .filter(|p| {
+                // Defended if the player could take the piece back, were it the opponent's.
+                let mut probe = *self;
+                probe.pieces[p.id].player = 1 - player;
+                !probe.is_attacked(p.location_index, player)
+            })
+            .map(|p| p.id)
+            .collect()
+    }
+
+    /// The squares the move that led from parent to this game went from and to. A drop
+    /// has no from square.
+    pub fn last_move_squares(&self, parent: &Game) -> Option<(Option<usize>, usize)> {
+        let the_move = self.last_move?;
+        let piece = parent.piece_for(the_move.0);
+        let from = (piece.location == Board).then_some(piece.location_index);
+        Some((from, the_move.1))
+    }
+
     /// Checks the given board move-to square and returns:
     ///   -1 if move is out of bounds or lands on own player,
     ///    0 if move is to empty square,
@@ -342,6 +433,313 @@ impl Game {
         }
     }
 
+    /// Creates a game from an SFEN string such as "rbsgk/4p/5/P4/KGSBR b - 1".
+    /// Upper case pieces and "b" belong to player 0, who moves up the board. Hand
+    /// pieces are listed like "2Pg". The move number is optional and ignored.
+    pub fn from_sfen(sfen: &str) -> Result<Game, String> {
+        let mut game = Game::new();
+        // Unused pieces stay OutOfGame, but need their own ids.
+        for (index, piece) in game.pieces.iter_mut().enumerate() {
+            piece.id = index;
+        }
+        let mut fields = sfen.split_whitespace();
+        let board = fields.next().ok_or("empty SFEN")?;
+        let side = fields.next().unwrap_or("b");
+        let hand = fields.next().unwrap_or("-");
+
+        let rows: Vec<&str> = board.split('/').collect();
+        if rows.len() != ROWS {
+            return Err(format!("expected {} rows, found {}", ROWS, rows.len()));
+        }
+        let mut piece_id = 0;
+        for (r, row) in rows.iter().enumerate() {
+            let y = ROWS - 1 - r;
+            let mut x = 0;
+            let mut promoted = false;
+            for c in row.chars() {
+                if let Some(n) = c.to_digit(10) {
+                    x += n as usize;
+                    continue;
+                }
+                if c == '+' {
+                    promoted = true;
+                    continue;
+                }
+                if x >= COLS {
+                    return Err(format!("row '{}' is too long", row));
+                }
+                let mut piece = Game::sfen_piece(c, piece_id)?;
+                if promoted {
+                    piece.kind = piece.promotion_kind().ok_or(format!("'+{}' cannot be promoted", c))?;
+                    promoted = false;
+                }
+                let index = Game::column_row_to_index(x, y);
+                piece.location = Board;
+                piece.location_index = index;
+                game.pieces[piece_id] = piece;
+                game.grid[index] = piece_id;
+                piece_id += 1;
+                x += 1;
+            }
+            if x != COLS {
+                return Err(format!("row '{}' does not have {} columns", row, COLS));
+            }
+        }
+
+        if hand != "-" {
+            let mut count = 0;
+            for c in hand.chars() {
+                if let Some(n) = c.to_digit(10) {
+                    count = count * 10 + n as usize;
+                    continue;
+                }
+                for _ in 0..count.max(1) {
+                    let mut piece = Game::sfen_piece(c, piece_id)?;
+                    if piece.kind == King {
+                        return Err("a king cannot be in hand".to_string());
+                    }
+                    let index = game.available_reserve_index(piece.player).ok_or("too many pieces in hand")?;
+                    piece.location = Reserve;
+                    piece.location_index = index;
+                    game.pieces[piece_id] = piece;
+                    game.reserves[piece.player][index] = piece_id;
+                    piece_id += 1;
+                }
+                count = 0;
+            }
+        }
+
+        game.current_player = match side {
+            "b" => 0,
+            "w" => 1,
+            _ => return Err(format!("unknown side to move '{}'", side)),
+        };
+        game.update_state();
+        Ok(game)
+    }
+
+    /// Creates the piece for an SFEN letter, checking that there's room for it.
+    fn sfen_piece(c: char, id: usize) -> Result<Piece, String> {
+        if !"KGSRBPkgsrbp".contains(c) {
+            return Err(format!("unknown piece '{}'", c));
+        }
+        if id >= PIECES_PER_PLAYER * 2 {
+            return Err(format!("more than {} pieces", PIECES_PER_PLAYER * 2));
+        }
+        Ok(Game::create_piece(c, id))
+    }
+
+    /// Returns the position as an SFEN string. See from_sfen().
+    pub fn to_sfen(&self) -> String {
+        let mut sfen = String::new();
+        for y in (0..ROWS).rev() {
+            let mut empty = 0;
+            for x in 0..COLS {
+                let id = self.grid[Game::column_row_to_index(x, y)];
+                if id == NONE {
+                    empty += 1;
+                    continue;
+                }
+                if empty > 0 {
+                    sfen.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                sfen.push_str(&self.pieces[id].sfen_rep());
+            }
+            if empty > 0 {
+                sfen.push_str(&empty.to_string());
+            }
+            if y > 0 {
+                sfen.push('/');
+            }
+        }
+
+        sfen.push_str(if self.current_player == 0 { " b " } else { " w " });
+
+        let mut hand = String::new();
+        for player in 0..2 {
+            for kind in [Rook, Bishop, Gold, Silver, Pawn] {
+                let count = self.pieces.iter()
+                    .filter(|p| p.player == player && p.location == Reserve && p.kind == kind)
+                    .count();
+                if count == 0 { continue }
+                if count > 1 {
+                    hand.push_str(&count.to_string());
+                }
+                hand.push_str(&Piece::new(0, kind, player).sfen_rep());
+            }
+        }
+        if hand.is_empty() {
+            hand.push('-');
+        }
+        sfen.push_str(&hand);
+        sfen.push_str(" 1");
+        sfen
+    }
+
+    /// An empty board, for setting up positions with the editing functions below.
+    pub fn empty() -> Game {
+        Game::from_sfen("5/5/5/5/5 b -").expect("empty SFEN")
+    }
+
+    // Editing. These keep the pieces, grid and reserves consistent, but not the rules of
+    // play; see validate().
+
+    /// Puts a piece of the kind on the square, replacing any piece there.
+    pub fn put_piece(&mut self, index: usize, kind: PieceKind, player: usize) -> Result<(), String> {
+        if self.grid[index] != NONE {
+            self.remove_piece(self.grid[index]);
+        }
+        let id = self.unused_piece_id()?;
+        self.pieces[id] = Piece { id, kind, player, location: Board, location_index: index };
+        self.grid[index] = id;
+        self.after_edit();
+        Ok(())
+    }
+
+    /// Puts a piece of the kind in the player's hand, unpromoted.
+    pub fn put_in_hand(&mut self, kind: PieceKind, player: usize) -> Result<(), String> {
+        let mut piece = Piece::new(0, kind, player);
+        piece.kind = piece.demotion_kind().unwrap_or(kind);
+        if piece.kind == King {
+            return Err("a king cannot be in hand".to_string());
+        }
+        let id = self.unused_piece_id()?;
+        let index = self.available_reserve_index(player).ok_or("too many pieces in hand")?;
+        self.pieces[id] = Piece { id, location: Reserve, location_index: index, ..piece };
+        self.reserves[player][index] = id;
+        self.after_edit();
+        Ok(())
+    }
+
+    /// Takes the piece off the board or out of hand. Does nothing for NONE.
+    pub fn remove_piece(&mut self, id: usize) {
+        if id == NONE { return }
+        let piece = self.pieces[id];
+        match piece.location {
+            Board => self.grid[piece.location_index] = NONE,
+            Reserve => self.reserves[piece.player][piece.location_index] = NONE,
+            OutOfGame => return,
+        }
+        self.pieces[id] = Piece::new(id, Pawn, 0);
+        self.after_edit();
+    }
+
+    fn unused_piece_id(&self) -> Result<usize, String> {
+        self.pieces.iter().position(|piece| piece.location == OutOfGame)
+            .ok_or(format!("no more than {} pieces", MAX_PIECES))
+    }
+
+    fn after_edit(&mut self) {
+        self.last_move = None;
+        self.update_state();
+    }
+
+    /// Checks that the position can be played from: one king each, no two unpromoted
+    /// pawns of a player on a column (nifu), no unpromoted pawn on its last row, and the
+    /// player who just moved isn't in check.
+    pub fn validate(&self) -> Result<(), String> {
+        for player in 0..2 {
+            let kings = self.pieces.iter()
+                .filter(|p| p.player == player && p.kind == King && p.location == Board)
+                .count();
+            if kings != 1 {
+                return Err(format!("Player {} needs one king, not {}.", player + 1, kings));
+            }
+
+            let mut pawn_columns = HashSet::new();
+            for pawn in self.pieces.iter().filter(|p| p.player == player && p.kind == Pawn && p.location == Board) {
+                let (column, row) = Game::index_to_column_row(pawn.location_index);
+                if !pawn_columns.insert(column) {
+                    return Err(format!("Player {} has two pawns on a column.", player + 1));
+                }
+                let last_row = if player == 0 { ROWS - 1 } else { 0 };
+                if row == last_row {
+                    return Err(format!("Player {} has a pawn that cannot move.", player + 1));
+                }
+            }
+        }
+        let waiting = 1 - self.current_player;
+        if self.is_in_check(waiting) {
+            return Err(format!("Player {} is in check but not to move.", waiting + 1));
+        }
+        Ok(())
+    }
+
+    /// A Zobrist hash of the position: the pieces on the board, the pieces in hand and the
+    /// player to move. Unlike the derived Hash, it ignores piece ids and last_move, so
+    /// the same position always has the same key. Used by the opening book.
+    pub fn position_key(&self) -> u64 {
+        let keys = zobrist_keys();
+        let mut key = if self.current_player == 1 { keys.side } else { 0 };
+        let mut hand_counts = [[0; 5]; 2];
+        for piece in &self.pieces {
+            match piece.location {
+                Board => key ^= keys.board[piece.player][piece.kind.index()][piece.location_index],
+                Reserve => {
+                    if let Some(kind) = piece.kind.hand_index() {
+                        hand_counts[piece.player][kind] += 1;
+                    }
+                },
+                OutOfGame => {},
+            }
+        }
+        for (player, counts) in hand_counts.iter().enumerate() {
+            for (kind, count) in counts.iter().enumerate() {
+                key ^= keys.hand[player][kind][(*count).min(PIECES_PER_PLAYER * 2)];
+            }
+        }
+        key
+    }
+
+    /// Returns the USI square name of the location index, e.g. "5e" for index 0.
+    /// Files run from 5 on the left to 1 on the right, ranks from 'a' at the top.
+    pub fn usi_square(index: usize) -> String {
+        let (x, y) = Game::index_to_column_row(index);
+        format!("{}{}", COLS - x, (b'a' + (ROWS - 1 - y) as u8) as char)
+    }
+
+    /// The inverse of usi_square().
+    pub fn index_for_usi_square(square: &str) -> Option<usize> {
+        let mut chars = square.chars();
+        let file = chars.next()?.to_digit(10)? as usize;
+        let rank = chars.next()?;
+        if chars.next().is_some() || !(1..=COLS).contains(&file) || !('a'..='e').contains(&rank) {
+            return None;
+        }
+        let x = COLS - file;
+        let y = ROWS - 1 - (rank as u8 - b'a') as usize;
+        Some(Game::column_row_to_index(x, y))
+    }
+
+    /// Returns the USI notation of the move that led from this game to the child node,
+    /// e.g. "2b3c+" for a promoting move or "P*4c" for a drop.
+    pub fn usi_for_child(&self, child: &Game) -> String {
+        let the_move = child.last_move.expect("Game.usi_for_child: child has no last_move");
+        let before = self.pieces[the_move.0];
+        let after = child.pieces[the_move.0];
+        let to = Game::usi_square(the_move.1);
+        if before.location == Reserve {
+            return format!("{}*{}", Piece::new(0, before.kind, 0).string_rep(), to);
+        }
+        let promotion = if after.kind != before.kind { "+" } else { "" };
+        format!("{}{}{}", Game::usi_square(before.location_index), to, promotion)
+    }
+
+    /// Returns the child node for the USI move, or None if the move isn't legal here.
+    pub fn child_for_usi(&self, usi: &str) -> Option<Game> {
+        self.child_nodes(self.current_player)
+            .into_iter()
+            .find(|child| self.usi_for_child(child) == usi)
+    }
+
+    /// Returns the child node for the move, or None if the move isn't legal here.
+    pub fn child_for_move(&self, the_move: Move) -> Option<Game> {
+        self.child_nodes_for_piece(the_move.0)
+            .into_iter()
+            .find(|child| child.last_move == Some(the_move))
+    }
+
     /// Advance to the next player.
     pub fn next_player(&mut self) {
         self.current_player = 1 - self.current_player;
@@ -400,4 +798,103 @@ impl Game {
             println!("index: {}, id: {}", index, id);
         }
     }
-}
\ No newline at end of file
+}
+
+struct ZobristKeys {
+    board: [[[u64; GRID_COUNT]; 10]; 2],
+    hand: [[[u64; PIECES_PER_PLAYER * 2 + 1]; 5]; 2],
+    side: u64,
+}
+
+/// The Zobrist keys, generated once from a fixed seed. A local splitmix64 is used instead
+/// of fastrand so keys (and opening book files) never change with a dependency update.
+fn zobrist_keys() -> &'static ZobristKeys {
+    static KEYS: OnceLock<ZobristKeys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        let mut state: u64 = 0x4d69_6e69_5368_6f67; // "MiniShog"
+        let mut next = || {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        let mut keys = ZobristKeys {
+            board: [[[0; GRID_COUNT]; 10]; 2],
+            hand: [[[0; PIECES_PER_PLAYER * 2 + 1]; 5]; 2],
+            side: 0,
+        };
+        for key in keys.board.iter_mut().flatten().flatten() {
+            *key = next();
+        }
+        for counts in keys.hand.iter_mut().flatten() {
+            // An empty hand contributes nothing.
+            for key in counts.iter_mut().skip(1) {
+                *key = next();
+            }
+        }
+        keys.side = next();
+        keys
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(sfen: &str) -> Game {
+        let game = Game::from_sfen(sfen).unwrap();
+        assert_eq!(game.to_sfen(), sfen);
+        game
+    }
+
+    #[test]
+    fn sfen_round_trips() {
+        let game = round_trip("rbsgk/4p/5/P4/KGSBR b - 1");
+        assert_eq!(game.current_player, 0);
+        let game = round_trip("rbsgk/4p/5/P4/KGSBR w - 1");
+        assert_eq!(game.current_player, 1);
+    }
+
+    #[test]
+    fn sfen_round_trips_hands() {
+        let game = round_trip("4k/5/5/5/K4 b 2Prg 1");
+        let in_hand = |player: usize, kind: PieceKind| game.pieces.iter()
+            .filter(|p| p.player == player && p.location == Reserve && p.kind == kind)
+            .count();
+        assert_eq!(in_hand(0, Pawn), 2);
+        assert_eq!(in_hand(1, Rook), 1);
+        assert_eq!(in_hand(1, Gold), 1);
+        round_trip("2k2/5/2P2/5/K4 w BSgs 1");
+    }
+
+    #[test]
+    fn sfen_round_trips_promoted_pieces() {
+        let game = round_trip("k1+R2/2+s2/5/1+b3/4K w GSPp 1");
+        let kind_at = |x: usize, y: usize| game.pieces[game.grid[Game::column_row_to_index(x, y)]].kind;
+        assert_eq!(kind_at(2, 4), RookPro);
+        assert_eq!(kind_at(2, 3), SilverPro);
+        assert_eq!(kind_at(1, 1), BishopPro);
+        round_trip("4k/+P4/5/4+p/K4 b - 1");
+    }
+
+    #[test]
+    fn sfen_move_number_is_optional() {
+        assert_eq!(Game::from_sfen("k4/5/5/5/4K b -").unwrap().to_sfen(), "k4/5/5/5/4K b - 1");
+    }
+
+    #[test]
+    fn sfen_errors() {
+        let error = |sfen: &str| Game::from_sfen(sfen).unwrap_err();
+        assert_eq!(error("rbsgk/4p/5/P4/KGSBR b P 1"), "more than 12 pieces");
+        assert_eq!(error("rbsgkk/4p/5/P4/KGSBR b - 1"), "row 'rbsgkk' is too long");
+        assert_eq!(error("rbsgk/4p/6/P4/KGSBR b - 1"), "row '6' does not have 5 columns");
+        assert_eq!(error("rbsgk/3p/5/P4/KGSBR b - 1"), "row '3p' does not have 5 columns");
+        assert_eq!(error("rbsgk/4p/P4/KGSBR b - 1"), "expected 5 rows, found 4");
+        assert_eq!(error("rbsgk/4p/5/P4/KGSBX b - 1"), "unknown piece 'X'");
+        assert_eq!(error("4k/5/5/5/K4 b +K 1"), "unknown piece '+'");
+        assert_eq!(error("4k/5/+G4/5/K4 b - 1"), "'+G' cannot be promoted");
+        assert_eq!(error("4k/5/5/5/4K b k 1"), "a king cannot be in hand");
+        assert_eq!(error("4k/5/5/5/4K x - 1"), "unknown side to move 'x'");
+    }
+}
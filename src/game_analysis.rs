@@ -0,0 +1,154 @@
+// Game Analysis
+// Reviews a finished game: searches every position for a fixed time and compares each
+// move played with the engine's best. A move that loses enough of the score is marked as
+// an inaccuracy, a mistake or a blunder, and the marks are kept in the game record.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ai::{AIContext, Think};
+use crate::ai_minimax::{AIMinimax, WIN_LOSS_VAL};
+use crate::ai_sender::AISender;
+use crate::game::{Game, GameState};
+
+/// The least score drops, in pawns, for each judgement.
+pub const INACCURACY_DROP: f64 = 0.5;
+pub const MISTAKE_DROP: f64 = 1.5;
+pub const BLUNDER_DROP: f64 = 3.0;
+/// Scores are clamped to this before they are compared, so choosing a slower win, or
+/// losing a won position only a little, isn't a blunder.
+const SCORE_CLAMP: f64 = 10.0;
+/// The searches stop here if time allows more.
+const REVIEW_MAX_DEPTH: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Judgement {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Judgement {
+    /// The judgement for a move that lost drop pawns, if any.
+    pub fn for_drop(drop: f64) -> Option<Judgement> {
+        if drop >= BLUNDER_DROP { return Some(Judgement::Blunder) }
+        if drop >= MISTAKE_DROP { return Some(Judgement::Mistake) }
+        if drop >= INACCURACY_DROP { return Some(Judgement::Inaccuracy) }
+        None
+    }
+
+    /// The usual annotation symbol.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "?!",
+            Judgement::Mistake => "?",
+            Judgement::Blunder => "??",
+        }
+    }
+
+    /// As written in game records.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Judgement::Inaccuracy => "inaccuracy",
+            Judgement::Mistake => "mistake",
+            Judgement::Blunder => "blunder",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Judgement> {
+        match name {
+            "inaccuracy" => Some(Judgement::Inaccuracy),
+            "mistake" => Some(Judgement::Mistake),
+            "blunder" => Some(Judgement::Blunder),
+            _ => None,
+        }
+    }
+}
+
+/// A move found wanting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// The move, as an index into the record's moves.
+    pub ply: usize,
+    pub judgement: Judgement,
+    /// The engine's choice, in USI notation.
+    pub best_move: String,
+    /// How much the move lost for the player who made it, in pawns.
+    pub drop: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct GameReview {
+    /// The score of every position, by ply, for player 0.
+    pub scores: Vec<f64>,
+    pub annotations: Vec<Annotation>,
+}
+
+pub enum ReviewMessage {
+    /// Positions searched so far, of the total.
+    Progress(usize, usize),
+    Done(GameReview),
+}
+
+/// Reviews the game given by its successive positions, searching each for move_time.
+/// Sends progress as it goes, and nothing more if stop is set.
+pub fn review(positions: Vec<Game>, context: AIContext, move_time: Duration, stop: Arc<AtomicBool>, tx: Sender<ReviewMessage>) {
+    let mut scores = Vec::with_capacity(positions.len());
+    let mut best_nodes = Vec::with_capacity(positions.len());
+
+    for (index, game) in positions.iter().enumerate() {
+        let (score, best_node) = search(game, &context, move_time, &stop);
+        if stop.load(Ordering::Relaxed) { return }
+        scores.push(score);
+        best_nodes.push(best_node);
+        // The receiver is gone if the review was abandoned.
+        if tx.send(ReviewMessage::Progress(index + 1, positions.len())).is_err() { return }
+    }
+
+    let mut annotations = Vec::new();
+    for ply in 0..positions.len().saturating_sub(1) {
+        let Some(best_node) = best_nodes[ply] else { continue };
+        let played = positions[ply + 1];
+        if best_node.position_key() == played.position_key() { continue }
+
+        // Both for the player making the move.
+        let best_score = scores[ply].clamp(-SCORE_CLAMP, SCORE_CLAMP);
+        let played_score = (-scores[ply + 1]).clamp(-SCORE_CLAMP, SCORE_CLAMP);
+        let drop = best_score - played_score;
+        if let Some(judgement) = Judgement::for_drop(drop) {
+            annotations.push(Annotation {
+                ply,
+                judgement,
+                best_move: positions[ply].usi_for_child(&best_node),
+                drop,
+            });
+        }
+    }
+
+    let scores = positions.iter().zip(&scores)
+        .map(|(game, score)| if game.current_player == 0 { *score } else { -score })
+        .collect();
+    let _ = tx.send(ReviewMessage::Done(GameReview { scores, annotations }));
+}
+
+/// Returns the score for the player to move, and the best move's position.
+fn search(game: &Game, context: &AIContext, move_time: Duration, stop: &Arc<AtomicBool>) -> (f64, Option<Game>) {
+    let mut node = *game;
+    match *node.update_state() {
+        GameState::Ongoing => {},
+        GameState::Draw => return (0.0, None),
+        state => {
+            let won = (state == GameState::WinPlayer0) == (node.current_player == 0);
+            return (if won { WIN_LOSS_VAL } else { -WIN_LOSS_VAL }, None);
+        },
+    }
+    let mut ai = AIMinimax::new(node, REVIEW_MAX_DEPTH, context.evaluator.clone(), AISender::muted());
+    ai.tablebases = context.tablebases.clone();
+    ai.transposition_table = context.transposition_table.clone();
+    ai.time_limit = Some(move_time);
+    ai.stop_signal = Some(stop.clone());
+    let progress = ai.think();
+    (progress.score, progress.best_node)
+}
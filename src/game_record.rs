@@ -0,0 +1,169 @@
+// Game Record
+// A saved game: the starting position, the moves in USI notation and the result.
+// Records are plain text, one "key value" line per field, and a file may hold several
+// games separated by blank lines:
+//
+//   start rbsgk/4p/5/P4/KGSBR b - 1
+//   moves 1e1d 5a5b 2e1e
+//   result player0
+//   reason king
+//   hints 0 4
+//   annotation 1 blunder 2b3c 4.2
+//
+// Unknown keys are ignored, so older readers can load newer records.
+
+use std::fs;
+use std::io::Write;
+
+use crate::game::{EndReason, Game, GameState};
+use crate::game_analysis::{Annotation, Judgement};
+
+/// Where the app saves each finished game.
+pub const SAVED_GAMES_FILE: &str = "saved_games.txt";
+/// Where the app saves games once reviewed, with their annotations.
+pub const ANALYZED_GAMES_FILE: &str = "analyzed_games.txt";
+
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    /// SFEN of the starting position.
+    pub start: String,
+    /// The moves in USI notation.
+    pub moves: Vec<String>,
+    /// None while the game is unfinished.
+    pub result: Option<GameState>,
+    /// How the game ended, if known.
+    pub reason: Option<EndReason>,
+    /// The plies, as indexes into moves, at which the player to move took a hint.
+    pub hints: Vec<usize>,
+    /// The moves a review found wanting, one "annotation PLY JUDGEMENT BEST DROP" line
+    /// each.
+    pub annotations: Vec<Annotation>,
+}
+
+impl GameRecord {
+    /// A record for a game starting from the given position.
+    pub fn new(start: &Game) -> Self {
+        Self {
+            start: start.to_sfen(),
+            moves: Vec::new(),
+            result: None,
+            reason: None,
+            hints: Vec::new(),
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Builds a record from a list of successive positions, such as Controller's
+    /// node history or a self-play game.
+    pub fn from_positions(positions: &[Game], result: Option<GameState>) -> Self {
+        let mut record = Self::new(positions.first().expect("GameRecord: no positions"));
+        for pair in positions.windows(2) {
+            record.moves.push(pair[0].usi_for_child(&pair[1]));
+        }
+        record.result = result;
+        record
+    }
+
+    /// Replays the moves and returns every position, starting with the start position.
+    pub fn positions(&self) -> Result<Vec<Game>, String> {
+        let mut game = Game::from_sfen(&self.start)?;
+        let mut positions = vec![game];
+        for (index, usi) in self.moves.iter().enumerate() {
+            game = game.child_for_usi(usi).ok_or(format!("illegal move {} '{}'", index + 1, usi))?;
+            positions.push(game);
+        }
+        Ok(positions)
+    }
+
+    fn result_text(result: GameState) -> &'static str {
+        match result {
+            GameState::WinPlayer0 => "player0",
+            GameState::WinPlayer1 => "player1",
+            GameState::Draw => "draw",
+            GameState::Ongoing => "ongoing",
+        }
+    }
+
+    fn parse_result(text: &str) -> Option<GameState> {
+        match text {
+            "player0" => Some(GameState::WinPlayer0),
+            "player1" => Some(GameState::WinPlayer1),
+            "draw" => Some(GameState::Draw),
+            _ => None,
+        }
+    }
+
+    fn parse_annotation(text: &str) -> Option<Annotation> {
+        let mut words = text.split_whitespace();
+        Some(Annotation {
+            ply: words.next()?.parse().ok()?,
+            judgement: Judgement::from_name(words.next()?)?,
+            best_move: words.next()?.to_string(),
+            drop: words.next()?.parse().ok()?,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("start {}\nmoves {}\n", self.start, self.moves.join(" "));
+        if let Some(result) = self.result {
+            text.push_str(&format!("result {}\n", Self::result_text(result)));
+        }
+        if let Some(reason) = self.reason {
+            text.push_str(&format!("reason {}\n", reason.name()));
+        }
+        if !self.hints.is_empty() {
+            let hints: Vec<String> = self.hints.iter().map(|ply| ply.to_string()).collect();
+            text.push_str(&format!("hints {}\n", hints.join(" ")));
+        }
+        for annotation in &self.annotations {
+            text.push_str(&format!("annotation {} {} {} {:.1}\n",
+                annotation.ply, annotation.judgement.name(), annotation.best_move, annotation.drop));
+        }
+        text
+    }
+
+    /// Parses every record in the text. Records without a start line begin from the
+    /// standard starting position.
+    pub fn parse_all(text: &str) -> Vec<GameRecord> {
+        let mut records = Vec::new();
+        let mut current: Option<GameRecord> = None;
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('#') { continue }
+            if line.is_empty() {
+                records.extend(current.take());
+                continue;
+            }
+            let record = current.get_or_insert_with(|| {
+                let mut start = Game::new();
+                start.prepare();
+                GameRecord::new(&start)
+            });
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "start" => record.start = value.trim().to_string(),
+                "moves" => record.moves = value.split_whitespace().map(String::from).collect(),
+                "result" => record.result = Self::parse_result(value.trim()),
+                "reason" => record.reason = EndReason::from_name(value.trim()),
+                "hints" => record.hints = value.split_whitespace().filter_map(|ply| ply.parse().ok()).collect(),
+                "annotation" => record.annotations.extend(Self::parse_annotation(value)),
+                _ => {},
+            }
+        }
+        records.extend(current);
+        records
+    }
+
+    pub fn load_all(path: &str) -> Result<Vec<GameRecord>, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Self::parse_all(&text))
+    }
+
+    /// Appends the record to the file, creating it if needed.
+    pub fn append_to(&self, path: &str) -> Result<(), String> {
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| format!("{}: {}", path, e))?;
+        writeln!(file, "{}", self.to_text()).map_err(|e| format!("{}: {}", path, e))
+    }
+}
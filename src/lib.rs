@@ -0,0 +1,33 @@
+// Lib
+// The game, AI and views, shared by the app (main.rs) and the headless tools in src/bin.
+
+pub mod ai;
+pub mod ai_minimax;
+pub mod ai_monte_carlo;
+pub mod ai_monte_carlo_tree;
+pub mod ai_random;
+pub mod ai_sender;
+pub mod asset_loader;
+pub mod clock;
+pub mod controller;
+pub mod evaluation;
+pub mod game;
+pub mod game_analysis;
+pub mod game_record;
+pub mod mate_solver;
+#[cfg(feature = "nnue")]
+pub mod nnue;
+pub mod opening_book;
+pub mod piece;
+pub mod puzzle;
+pub mod self_play;
+pub mod strength;
+pub mod tablebase;
+pub mod theme;
+pub mod tool_args;
+pub mod tournament;
+pub mod transposition;
+pub mod tutorial;
+pub mod tuner;
+pub mod usi;
+pub mod view;
@@ -2,19 +2,7 @@
 
 use macroquad::prelude::*;
 
-mod ai;
-mod ai_minimax;
-//mod ai_monte_carlo;
-//mod ai_monte_carlo_tree;
-//mod ai_random;
-mod ai_sender;
-mod asset_loader;
-mod controller;
-mod game;
-mod piece;
-mod view;
-
-use crate::controller::Controller;
+use mini_shogi::controller::Controller;
 
 fn conf() -> Conf {
     Conf {
@@ -24,7 +12,7 @@ fn conf() -> Conf {
         high_dpi: true,
         //fullscreen: bool,
         //sample_count: 0,
-        window_resizable: false,
+        window_resizable: true,
         //icon: Option<Icon>,
         ..Default::default()
     }
@@ -35,7 +23,7 @@ async fn main() {
     // Set up backtracing for debugging.
     std::env::set_var("RUST_BACKTRACE", "1");
 
-    //println!("dpi_scale: {}", crate::view::dpi_scale());
+    //println!("dpi_scale: {}", mini_shogi::view::dpi_scale());
     let mut controller = Controller::new().await;
     controller.prepare().await;
     controller.go().await;
@@ -0,0 +1,226 @@
+// Mate Solver
+// Finds mates by checks (tsume): the attacker checks on every move and the defender
+// escapes as well as they can, until the defender has no move that saves the king.
+//
+// solve() is a depth-first proof-number search (df-pn). It proves or disproves a mate of
+// any length within a node budget, but the line it finds need not be the shortest.
+// mate_in() and the functions after it search exactly to a depth, for the length of a
+// puzzle, whether its solution is unique, and the defender's best replies.
+//
+// Following the usual rules, a check that leaves the attacker's own king attacked doesn't
+// count, mating with a pawn drop isn't allowed, and perpetual check isn't a mate.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::game::{Game, GameLocation};
+use crate::piece::PieceKind;
+
+/// Proof and disproof numbers stop here, so sums can't overflow.
+const INFINITY: u32 = u32::MAX / 2;
+/// The most positions solve() searches by default.
+pub const DEFAULT_MAX_NODES: usize = 2_000_000;
+/// Proven lines longer than this are cut short; they would be too long to read anyway.
+const MAX_LINE_PLIES: usize = 99;
+
+#[derive(Debug, Clone)]
+pub enum MateResult {
+    /// The positions after each move of a mating line, attacker's and defender's.
+    Mate(Vec<Game>),
+    NoMate,
+    /// The node budget ran out first.
+    Unknown,
+}
+
+/// The attacker's checks: moves that attack the defender's king without leaving their
+/// own king attacked, except a pawn drop that mates.
+pub fn checks(game: &Game) -> Vec<Game> {
+    let attacker = game.current_player;
+    game.child_nodes(attacker).into_iter()
+        .filter(|child| child.is_in_check(1 - attacker) && !child.is_in_check(attacker))
+        .filter(|child| !(is_pawn_drop(game, child) && evasions(child).is_empty()))
+        .collect()
+}
+
+/// The defender's moves that leave their king safe.
+pub fn evasions(game: &Game) -> Vec<Game> {
+    let defender = game.current_player;
+    game.child_nodes(defender).into_iter()
+        .filter(|child| !child.is_in_check(defender))
+        .collect()
+}
+
+fn is_pawn_drop(game: &Game, child: &Game) -> bool {
+    child.last_move.is_some_and(|the_move| {
+        let piece = game.piece_for(the_move.0);
+        piece.location == GameLocation::Reserve && piece.kind == PieceKind::Pawn
+    })
+}
+
+pub struct MateSolver {
+    /// Proof and disproof numbers by position key. Proof 0 is a mate, disproof 0 none.
+    table: HashMap<u64, (u32, u32)>,
+    /// The positions on the current search path, to spot repetitions.
+    path: HashSet<u64>,
+    nodes: usize,
+    max_nodes: usize,
+}
+
+impl MateSolver {
+    pub fn new(max_nodes: usize) -> Self {
+        Self {
+            table: HashMap::new(),
+            path: HashSet::new(),
+            nodes: 0,
+            max_nodes,
+        }
+    }
+
+    /// The positions searched by the last solve().
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// Looks for a mate by the player to move.
+    pub fn solve(&mut self, game: &Game) -> MateResult {
+        self.table.clear();
+        self.path.clear();
+        self.nodes = 0;
+        let (proof, disproof) = self.search(game, true, INFINITY - 1, INFINITY - 1);
+        if proof == 0 {
+            MateResult::Mate(self.proven_line(game))
+        } else if disproof == 0 {
+            MateResult::NoMate
+        } else {
+            MateResult::Unknown
+        }
+    }
+
+    /// Searches the position until its proof number reaches max_proof or its disproof
+    /// number max_disproof, and returns both. The attacker is to move if attacking.
+    fn search(&mut self, game: &Game, attacking: bool, max_proof: u32, max_disproof: u32) -> (u32, u32) {
+        self.nodes += 1;
+        let key = game.position_key();
+        let children = if attacking { checks(game) } else { evasions(game) };
+        if children.is_empty() {
+            // No check to give, or no escape.
+            let numbers = if attacking { (INFINITY, 0) } else { (0, INFINITY) };
+            self.table.insert(key, numbers);
+            return numbers;
+        }
+        let keys: Vec<u64> = children.iter().map(|child| child.position_key()).collect();
+
+        self.path.insert(key);
+        let numbers = loop {
+            let child_numbers: Vec<(u32, u32)> = keys.iter().map(|key| self.lookup(*key)).collect();
+            // The attacker needs one child proven; the defender needs one disproven.
+            let (proof, disproof) = Self::combine(attacking, &child_numbers);
+            if proof >= max_proof || disproof >= max_disproof || self.nodes >= self.max_nodes {
+                break (proof, disproof);
+            }
+
+            // Search the child most likely to settle the position, until it looks no
+            // better than the runner-up.
+            let deciding = |numbers: &(u32, u32)| if attacking { numbers.0 } else { numbers.1 };
+            let mut order: Vec<usize> = (0..children.len()).collect();
+            order.sort_by_key(|index| deciding(&child_numbers[*index]));
+            let best = order[0];
+            let runner_up = order.get(1).map_or(INFINITY, |index| deciding(&child_numbers[*index]));
+            let (child_proof, child_disproof) = child_numbers[best];
+            let (child_max_proof, child_max_disproof) = if attacking {
+                (max_proof.min(runner_up.saturating_add(1)), Self::cap(max_disproof - disproof + child_disproof))
+            } else {
+                (Self::cap(max_proof - proof + child_proof), max_disproof.min(runner_up.saturating_add(1)))
+            };
+            self.search(&children[best], !attacking, child_max_proof, child_max_disproof);
+        };
+        self.path.remove(&key);
+        self.table.insert(key, numbers);
+        numbers
+    }
+
+    /// The numbers of a position not yet settled. One already on the search path is a
+    /// repetition, which doesn't mate. This ignores that a position reached by another
+    /// path might not repeat, which can rarely miss a mate.
+    fn lookup(&self, key: u64) -> (u32, u32) {
+        if self.path.contains(&key) {
+            return (INFINITY, 0);
+        }
+        self.table.get(&key).copied().unwrap_or((1, 1))
+    }
+
+    fn combine(attacking: bool, child_numbers: &[(u32, u32)]) -> (u32, u32) {
+        let min_proof = child_numbers.iter().map(|numbers| numbers.0).min().unwrap_or(INFINITY);
+        let min_disproof = child_numbers.iter().map(|numbers| numbers.1).min().unwrap_or(INFINITY);
+        let sum_proof = Self::cap(child_numbers.iter().map(|numbers| numbers.0 as u64).sum::<u64>());
+        let sum_disproof = Self::cap(child_numbers.iter().map(|numbers| numbers.1 as u64).sum::<u64>());
+        if attacking { (min_proof, sum_disproof) } else { (sum_proof, min_disproof) }
+    }
+
+    fn cap(value: impl Into<u64>) -> u32 {
+        value.into().min(INFINITY as u64) as u32
+    }
+
+    /// Follows proven positions from a proven root to the mate.
+    fn proven_line(&self, game: &Game) -> Vec<Game> {
+        let mut line = Vec::new();
+        let mut node = *game;
+        let mut attacking = true;
+        while line.len() < MAX_LINE_PLIES {
+            let children = if attacking { checks(&node) } else { evasions(&node) };
+            let proven = children.into_iter()
+                .find(|child| self.table.get(&child.position_key()).is_some_and(|numbers| numbers.0 == 0));
+            let Some(child) = proven else { break };
+            line.push(child);
+            node = child;
+            attacking = !attacking;
+        }
+        line
+    }
+}
+
+/// The length in plies of the shortest mate by the player to move, if there is one
+/// within max_plies.
+pub fn mate_in(game: &Game, max_plies: usize) -> Option<usize> {
+    let mut memo = HashMap::new();
+    (1..=max_plies).step_by(2).find(|plies| mates_within(game, *plies, &mut memo))
+}
+
+/// Whether the check in child, played in game, mates within plies, counting itself.
+pub fn is_mating_move(game: &Game, child: &Game, plies: usize) -> bool {
+    let key = child.position_key();
+    let mut memo = HashMap::new();
+    checks(game).iter().any(|check| check.position_key() == key)
+        && check_mates_within(child, plies, &mut memo)
+}
+
+/// The attacker's moves that mate within plies.
+pub fn mating_moves(game: &Game, plies: usize) -> Vec<Game> {
+    let mut memo = HashMap::new();
+    checks(game).into_iter()
+        .filter(|check| check_mates_within(check, plies, &mut memo))
+        .collect()
+}
+
+/// The defender's reply that puts off the mate longest, looking up to max_plies ahead,
+/// or None if they have none. A reply that escapes the mate is best of all.
+pub fn best_defense(game: &Game, max_plies: usize) -> Option<Game> {
+    evasions(game).into_iter()
+        .max_by_key(|reply| mate_in(reply, max_plies).unwrap_or(usize::MAX))
+}
+
+/// Whether the attacker, to move, mates within plies.
+fn mates_within(game: &Game, plies: usize, memo: &mut HashMap<(u64, usize), bool>) -> bool {
+    if plies == 0 { return false; }
+    let key = (game.position_key(), plies);
+    if let Some(result) = memo.get(&key) {
+        return *result;
+    }
+    let result = checks(game).iter().any(|check| check_mates_within(check, plies, memo));
+    memo.insert(key, result);
+    result
+}
+
+/// Whether the check just played mates within plies, counting itself.
+fn check_mates_within(check: &Game, plies: usize, memo: &mut HashMap<(u64, usize), bool>) -> bool {
+    evasions(check).iter().all(|reply| plies >= 3 && mates_within(reply, plies - 2, memo))
+}
@@ -0,0 +1,320 @@
+// NNUE
+// A small, efficiently updatable neural network evaluator, enabled with the "nnue"
+// cargo feature. The inputs are piece-square features plus hand counts, seen from each
+// player's side of the board. The first layer output is kept in one accumulator per
+// player and updated incrementally as the search makes and unmakes moves, so only the
+// few pieces that changed are added or removed. Inference uses integers only.
+// Networks are trained by the 'train_nnue' tool from 'selfplay' data.
+
+use std::fs;
+use std::sync::Arc;
+
+use crate::game::{Game, GameLocation, GRID_COUNT, MAX_PIECES};
+use crate::piece::Piece;
+
+/// The network file the app loads at startup, if present.
+pub const NNUE_FILE: &str = "nnue.bin";
+
+const KIND_COUNT: usize = 10;
+const HAND_KIND_COUNT: usize = 5;
+/// Each of the first HAND_SLOTS pieces of a kind in hand has its own feature.
+const HAND_SLOTS: usize = 2;
+const BOARD_FEATURES: usize = 2 * KIND_COUNT * GRID_COUNT;
+
+pub const INPUT_COUNT: usize = BOARD_FEATURES + 2 * HAND_KIND_COUNT * HAND_SLOTS;
+pub const HIDDEN_COUNT: usize = 32;
+/// Fixed-point scale of the first layer. 1.0 is stored as QA.
+pub const QA: i32 = 255;
+/// Fixed-point scale of the output weights. 1.0 is stored as QB.
+pub const QB: i32 = 64;
+/// The network output is in pawns. sigmoid(WDL_SCALE * output) is the expected result
+/// for the player to move; the trainer uses this to fit game results.
+pub const WDL_SCALE: f32 = 0.25;
+
+/// Quantized feature weights and biases are clamped to this, so that the bias plus one
+/// weight for every piece in the game fits in an i16.
+pub const MAX_FEATURE_WEIGHT: i32 = i16::MAX as i32 / (MAX_PIECES as i32 + 1);
+
+const MAGIC: &[u8; 4] = b"MSNN";
+const VERSION: u32 = 1;
+
+/// The first layer output for each perspective (player 0 and player 1). Summed in i32,
+/// so networks loaded from any file can't overflow it.
+#[derive(Clone, Copy)]
+pub struct Accumulator {
+    values: [[i32; HIDDEN_COUNT]; 2],
+}
+
+pub struct Network {
+    /// INPUT_COUNT rows of HIDDEN_COUNT weights.
+    feature_weights: Vec<i16>,
+    feature_biases: [i16; HIDDEN_COUNT],
+    /// The first half applies to the player to move, the second half to the opponent.
+    output_weights: [i16; HIDDEN_COUNT * 2],
+    output_bias: i32,
+}
+
+impl Network {
+    /// Quantizes trained float weights. See the 'train_nnue' tool.
+    pub fn from_float(feature_weights: &[f32], feature_biases: &[f32], output_weights: &[f32], output_bias: f32) -> Self {
+        let quantize = |v: f32, scale: i32, max: i32| (v * scale as f32).round().clamp(-max as f32, max as f32) as i16;
+
+        let mut network = Self {
+            feature_weights: feature_weights.iter().map(|w| quantize(*w, QA, MAX_FEATURE_WEIGHT)).collect(),
+            feature_biases: [0; HIDDEN_COUNT],
+            output_weights: [0; HIDDEN_COUNT * 2],
+            output_bias: (output_bias * (QA * QB) as f32).round() as i32,
+        };
+        for (q, v) in network.feature_biases.iter_mut().zip(feature_biases) {
+            *q = quantize(*v, QA, MAX_FEATURE_WEIGHT);
+        }
+        for (q, v) in network.output_weights.iter_mut().zip(output_weights) {
+            *q = quantize(*v, QB, i16::MAX as i32);
+        }
+        network
+    }
+
+    /// Returns the active input features of the game as seen by the perspective player.
+    pub fn active_features(game: &Game, perspective: usize) -> Vec<usize> {
+        let mut features = Vec::with_capacity(16);
+        for piece in &game.pieces {
+            if let Some(feature) = Self::board_feature(piece, perspective) {
+                features.push(feature);
+            }
+        }
+        let counts = Self::hand_counts(game);
+        for (player, player_counts) in counts.iter().enumerate() {
+            for (kind, count) in player_counts.iter().enumerate() {
+                for slot in 0..(*count).min(HAND_SLOTS) {
+                    features.push(Self::hand_feature(player, kind, slot, perspective));
+                }
+            }
+        }
+        features
+    }
+
+    fn board_feature(piece: &Piece, perspective: usize) -> Option<usize> {
+        if piece.location != GameLocation::Board { return None }
+        let side = if piece.player == perspective { 0 } else { 1 };
+        // Rotate the board for player 1 so both perspectives look the same.
+        let square = if perspective == 0 { piece.location_index } else { GRID_COUNT - 1 - piece.location_index };
+        Some((side * KIND_COUNT + piece.kind.index()) * GRID_COUNT + square)
+    }
+
+    fn hand_feature(player: usize, kind: usize, slot: usize, perspective: usize) -> usize {
+        let side = if player == perspective { 0 } else { 1 };
+        BOARD_FEATURES + (side * HAND_KIND_COUNT + kind) * HAND_SLOTS + slot
+    }
+
+    fn hand_counts(game: &Game) -> [[usize; HAND_KIND_COUNT]; 2] {
+        let mut counts = [[0; HAND_KIND_COUNT]; 2];
+        for piece in &game.pieces {
+            if piece.location != GameLocation::Reserve { continue }
+            if let Some(kind) = piece.kind.hand_index() {
+                counts[piece.player][kind] += 1;
+            }
+        }
+        counts
+    }
+
+    fn add_feature(&self, values: &mut [i32; HIDDEN_COUNT], feature: usize) {
+        let row = &self.feature_weights[feature * HIDDEN_COUNT..(feature + 1) * HIDDEN_COUNT];
+        for (v, w) in values.iter_mut().zip(row) {
+            *v += *w as i32;
+        }
+    }
+
+    fn remove_feature(&self, values: &mut [i32; HIDDEN_COUNT], feature: usize) {
+        let row = &self.feature_weights[feature * HIDDEN_COUNT..(feature + 1) * HIDDEN_COUNT];
+        for (v, w) in values.iter_mut().zip(row) {
+            *v -= *w as i32;
+        }
+    }
+
+    /// Computes the accumulator from scratch.
+    pub fn refresh(&self, game: &Game) -> Accumulator {
+        let biases = self.feature_biases.map(|b| b as i32);
+        let mut accumulator = Accumulator { values: [biases; 2] };
+        for perspective in 0..2 {
+            for feature in Self::active_features(game, perspective) {
+                self.add_feature(&mut accumulator.values[perspective], feature);
+            }
+        }
+        accumulator
+    }
+
+    /// Returns the child's accumulator by applying only the differences between the
+    /// parent and child positions to the parent's accumulator.
+    pub fn update(&self, accumulator: &Accumulator, parent: &Game, child: &Game) -> Accumulator {
+        let mut result = *accumulator;
+        for perspective in 0..2 {
+            let values = &mut result.values[perspective];
+
+            // Pieces that moved, were captured, promoted or dropped.
+            for (before, after) in parent.pieces.iter().zip(child.pieces.iter()) {
+                if before == after { continue }
+                if let Some(feature) = Self::board_feature(before, perspective) {
+                    self.remove_feature(values, feature);
+                }
+                if let Some(feature) = Self::board_feature(after, perspective) {
+                    self.add_feature(values, feature);
+                }
+            }
+
+            // Hand counts.
+            let before = Self::hand_counts(parent);
+            let after = Self::hand_counts(child);
+            for player in 0..2 {
+                for kind in 0..HAND_KIND_COUNT {
+                    let old = before[player][kind].min(HAND_SLOTS);
+                    let new = after[player][kind].min(HAND_SLOTS);
+                    for slot in new..old {
+                        self.remove_feature(values, Self::hand_feature(player, kind, slot, perspective));
+                    }
+                    for slot in old..new {
+                        self.add_feature(values, Self::hand_feature(player, kind, slot, perspective));
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// The network output in pawns, from the point of view of the player to move.
+    pub fn output(&self, accumulator: &Accumulator, player_to_move: usize) -> f64 {
+        let us = &accumulator.values[player_to_move];
+        let them = &accumulator.values[1 - player_to_move];
+        let mut sum = self.output_bias;
+        for (i, v) in us.iter().chain(them.iter()).enumerate() {
+            let activation = (*v).clamp(0, QA);
+            sum += activation * self.output_weights[i] as i32;
+        }
+        sum as f64 / (QA * QB) as f64
+    }
+
+    /// Scores the game from the point of view of the given player, without an
+    /// existing accumulator.
+    pub fn evaluate(&self, game: &Game, player: usize) -> f64 {
+        let score = self.output(&self.refresh(game), game.current_player);
+        if game.current_player == player { score } else { -score }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        let mut reader = ByteReader { bytes: &bytes, offset: 0 };
+
+        if reader.take(4)? != MAGIC {
+            return Err("not a network file".to_string());
+        }
+        let version = reader.u32()?;
+        let inputs = reader.u32()? as usize;
+        let hidden = reader.u32()? as usize;
+        if version != VERSION || inputs != INPUT_COUNT || hidden != HIDDEN_COUNT {
+            return Err(format!("unsupported network: version {}, {}x{}", version, inputs, hidden));
+        }
+
+        let mut network = Self {
+            feature_weights: vec![0; INPUT_COUNT * HIDDEN_COUNT],
+            feature_biases: [0; HIDDEN_COUNT],
+            output_weights: [0; HIDDEN_COUNT * 2],
+            output_bias: 0,
+        };
+        for w in network.feature_weights.iter_mut() {
+            *w = reader.i16()?;
+        }
+        for w in network.feature_biases.iter_mut() {
+            *w = reader.i16()?;
+        }
+        for w in network.output_weights.iter_mut() {
+            *w = reader.i16()?;
+        }
+        network.output_bias = reader.u32()? as i32;
+        Ok(network)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(INPUT_COUNT as u32).to_le_bytes());
+        bytes.extend_from_slice(&(HIDDEN_COUNT as u32).to_le_bytes());
+        let weights = self.feature_weights.iter()
+            .chain(self.feature_biases.iter())
+            .chain(self.output_weights.iter());
+        for w in weights {
+            bytes.extend_from_slice(&w.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        fs::write(path, bytes).map_err(|e| e.to_string())
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.offset + count;
+        if end > self.bytes.len() {
+            return Err("file is too short".to_string());
+        }
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        let bytes = self.take(2)?;
+        Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+}
+
+/// The search-time state of the network evaluator: a stack of accumulators that follows
+/// the search as it makes and unmakes moves.
+#[derive(Clone)]
+pub struct NetworkEvaluator {
+    network: Arc<Network>,
+    stack: Vec<Accumulator>,
+}
+
+impl NetworkEvaluator {
+    pub fn new(network: Arc<Network>) -> Self {
+        Self {
+            network,
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn set_root(&mut self, root: &Game) {
+        self.stack.clear();
+        self.stack.push(self.network.refresh(root));
+    }
+
+    pub fn make(&mut self, parent: &Game, child: &Game) {
+        let accumulator = match self.stack.last() {
+            Some(top) => self.network.update(top, parent, child),
+            None => self.network.refresh(child),
+        };
+        self.stack.push(accumulator);
+    }
+
+    pub fn unmake(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Scores the node (the position at the top of the stack) for the given player.
+    pub fn evaluate(&self, node: &Game, player: usize) -> f64 {
+        let score = match self.stack.last() {
+            Some(top) => self.network.output(top, node.current_player),
+            None => return self.network.evaluate(node, player),
+        };
+        if node.current_player == player { score } else { -score }
+    }
+}
@@ -0,0 +1,135 @@
+// Opening Book
+// Known good opening moves, keyed by Game::position_key(). Each position has a list of
+// moves (in USI notation) with weights; a move is picked at random in proportion to its
+// weight so the AI doesn't play the same line every game. Built by the 'book' tool.
+//
+// The file is plain text, one move per line: "<position key in hex> <usi move> <weight>".
+
+use std::collections::HashMap;
+use std::fs;
+
+use crate::game::Game;
+
+/// The book file the app loads at startup, if present.
+pub const OPENING_BOOK_FILE: &str = "opening_book.txt";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookMove {
+    pub usi: String,
+    pub weight: u32,
+}
+
+#[derive(Default)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// The number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds weight to the move played from the given position.
+    pub fn add(&mut self, game: &Game, usi: &str, weight: u32) {
+        let moves = self.entries.entry(game.position_key()).or_default();
+        match moves.iter_mut().find(|m| m.usi == usi) {
+            Some(book_move) => book_move.weight += weight,
+            None => moves.push(BookMove { usi: usi.to_string(), weight }),
+        }
+    }
+
+    /// Removes moves with less than min_weight, and positions left without moves.
+    pub fn prune(&mut self, min_weight: u32) {
+        for moves in self.entries.values_mut() {
+            moves.retain(|m| m.weight >= min_weight);
+        }
+        self.entries.retain(|_, moves| !moves.is_empty());
+    }
+
+    /// Returns the book moves for the position, if any.
+    pub fn moves_for(&self, game: &Game) -> Option<&[BookMove]> {
+        self.entries.get(&game.position_key()).map(|moves| moves.as_slice())
+    }
+
+    /// Picks a book move for the position at random, weighted by the move weights, and
+    /// returns the resulting child node. Moves that aren't legal in the position (a
+    /// key collision or a stale book) are skipped.
+    pub fn choose(&self, game: &Game, rng: &mut fastrand::Rng) -> Option<Game> {
+        let candidates: Vec<(Game, u32)> = self.moves_for(game)?
+            .iter()
+            .filter(|m| m.weight > 0)
+            .filter_map(|m| game.child_for_usi(&m.usi).map(|child| (child, m.weight)))
+            .collect();
+
+        let total: u32 = candidates.iter().map(|(_, weight)| weight).sum();
+        if total == 0 { return None }
+
+        let mut pick = rng.u32(0..total);
+        for (child, weight) in candidates {
+            if pick < weight {
+                return Some(child);
+            }
+            pick -= weight;
+        }
+        None
+    }
+
+    /// Loads the book, or returns an empty book if there isn't one.
+    pub fn load_or_empty(path: &str) -> Self {
+        match Self::load(path) {
+            Ok(book) => book,
+            Err(error) => {
+                if std::path::Path::new(path).exists() {
+                    println!("Could not load {}: {}. Using no opening book.", path, error);
+                }
+                Self::new()
+            },
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut book = Self::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 3 {
+                return Err(format!("line {}: expected 'key move weight'", number + 1));
+            }
+            let key = u64::from_str_radix(fields[0], 16).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            let weight = fields[2].parse::<u32>().map_err(|e| format!("line {}: {}", number + 1, e))?;
+            book.entries.entry(key).or_default().push(BookMove { usi: fields[1].to_string(), weight });
+        }
+        Ok(book)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        // Sorted so the same book always produces the same file.
+        let mut keys: Vec<&u64> = self.entries.keys().collect();
+        keys.sort();
+
+        let mut text = String::from("# Mini Shogi opening book: position key, move, weight\n");
+        for key in keys {
+            let mut moves = self.entries[key].clone();
+            moves.sort_by(|a, b| b.weight.cmp(&a.weight).then(a.usi.cmp(&b.usi)));
+            for book_move in moves {
+                text.push_str(&format!("{:016x} {} {}\n", key, book_move.usi, book_move.weight));
+            }
+        }
+        fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
@@ -17,6 +17,42 @@ pub enum PieceKind {
     PawnPro,
 }
 
+impl PieceKind {
+    /// All kinds, in the order used by index().
+    pub const ALL: [PieceKind; 10] = [
+        King, Gold, Silver, SilverPro, Rook, RookPro, Bishop, BishopPro, Pawn, PawnPro,
+    ];
+
+    /// A stable 0..10 index, handy for lookup tables.
+    pub fn index(&self) -> usize {
+        match self {
+            King => 0,
+            Gold => 1,
+            Silver => 2,
+            SilverPro => 3,
+            Rook => 4,
+            RookPro => 5,
+            Bishop => 6,
+            BishopPro => 7,
+            Pawn => 8,
+            PawnPro => 9,
+        }
+    }
+
+    /// A 0..5 index for the kinds that can be held in reserve (hand).
+    /// Returns None for King and the promoted kinds.
+    pub fn hand_index(&self) -> Option<usize> {
+        match self {
+            Gold => Some(0),
+            Silver => Some(1),
+            Rook => Some(2),
+            Bishop => Some(3),
+            Pawn => Some(4),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct Piece {
     pub id: usize,
@@ -96,6 +132,14 @@ impl Piece {
         }
     }
 
+    /// The SFEN letter, upper case for player 0 and prefixed with '+' if promoted.
+    pub fn sfen_rep(&self) -> String {
+        let base = self.demotion_kind().unwrap_or(self.kind);
+        let letter = Piece::new(self.id, base, self.player).string_rep().to_string();
+        let letter = if self.player == 0 { letter } else { letter.to_lowercase() };
+        if base != self.kind { format!("+{}", letter) } else { letter }
+    }
+
     #[allow(dead_code)]
     pub fn string_rep(&self) -> &str {
         match self.kind {
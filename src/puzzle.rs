@@ -0,0 +1,139 @@
+// Puzzle
+// Mate problems (tsume): a position and its solution, the attacker's checks and the
+// defender's best replies in USI notation. Puzzles are plain text like game records,
+// one "key value" line per field and a blank line between puzzles:
+//
+//   name Gold on the head
+//   sfen 2k2/5/2P2/5/K4 b G 1
+//   solution G*3b
+//
+// The built-in collection is assets/puzzles.txt, compiled in. The 'tsume' tool checks
+// that every puzzle in it has a unique solution.
+
+use crate::game::Game;
+use crate::mate_solver::{self, MateResult, MateSolver};
+
+/// The file of the built-in puzzles, for the tools.
+pub const PUZZLES_FILE: &str = "assets/puzzles.txt";
+const PUZZLES: &str = include_str!("../assets/puzzles.txt");
+
+#[derive(Debug, Clone, Default)]
+pub struct Puzzle {
+    pub name: String,
+    /// SFEN of the position, with the attacker to move.
+    pub sfen: String,
+    /// The moves of the solution, both players', in USI notation.
+    pub solution: Vec<String>,
+}
+
+impl Puzzle {
+    /// The puzzles that come with the app.
+    pub fn builtin() -> Vec<Puzzle> {
+        Self::parse_all(PUZZLES)
+    }
+
+    /// Parses every puzzle in the text.
+    pub fn parse_all(text: &str) -> Vec<Puzzle> {
+        let mut puzzles = Vec::new();
+        let mut current: Option<Puzzle> = None;
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('#') { continue }
+            if line.is_empty() {
+                puzzles.extend(current.take());
+                continue;
+            }
+            let puzzle = current.get_or_insert_with(Puzzle::default);
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "name" => puzzle.name = value.trim().to_string(),
+                "sfen" => puzzle.sfen = value.trim().to_string(),
+                "solution" => puzzle.solution = value.split_whitespace().map(String::from).collect(),
+                _ => {},
+            }
+        }
+        puzzles.extend(current);
+        puzzles
+    }
+
+    pub fn to_text(&self) -> String {
+        format!("name {}\nsfen {}\nsolution {}\n", self.name, self.sfen, self.solution.join(" "))
+    }
+
+    /// The length of the mate in plies, counting both players' moves.
+    pub fn plies(&self) -> usize {
+        self.solution.len()
+    }
+
+    /// The length of the mate in the attacker's moves, as in "mate in 3".
+    pub fn moves(&self) -> usize {
+        self.plies().div_ceil(2)
+    }
+
+    pub fn start(&self) -> Result<Game, String> {
+        Game::from_sfen(&self.sfen)
+    }
+
+    /// Replays the solution and returns every position, starting with the puzzle's.
+    pub fn positions(&self) -> Result<Vec<Game>, String> {
+        let mut game = self.start()?;
+        let mut positions = vec![game];
+        for (index, usi) in self.solution.iter().enumerate() {
+            game = game.child_for_usi(usi).ok_or(format!("illegal move {} '{}'", index + 1, usi))?;
+            positions.push(game);
+        }
+        Ok(positions)
+    }
+
+    /// Checks the puzzle: a valid position, no shorter mate, and at each of the
+    /// attacker's turns the solution's move is the only one that mates in time, while
+    /// each of the defender's replies holds out longest.
+    pub fn verify(&self, max_nodes: usize) -> Result<(), String> {
+        let start = self.start()?;
+        start.validate()?;
+        let positions = self.positions()?;
+        let plies = self.plies();
+        if plies.is_multiple_of(2) {
+            return Err(format!("the solution has {} moves; the attacker should move last", plies));
+        }
+
+        // The quick check first.
+        match MateSolver::new(max_nodes).solve(&start) {
+            MateResult::Mate(_) => {},
+            MateResult::NoMate => return Err("there is no mate".to_string()),
+            MateResult::Unknown => return Err("the solver ran out of nodes".to_string()),
+        }
+        match mate_solver::mate_in(&start, plies) {
+            Some(length) if length == plies => {},
+            Some(length) => return Err(format!("there is a mate in {} plies", length)),
+            None => return Err(format!("there is no mate in {} plies", plies)),
+        }
+
+        for ply in (0..plies).step_by(2) {
+            let remaining = plies - ply;
+            let mating = mate_solver::mating_moves(&positions[ply], remaining);
+            let played = positions[ply + 1].position_key();
+            if !mating.iter().any(|check| check.position_key() == played) {
+                return Err(format!("move {} '{}' does not mate in time", ply + 1, self.solution[ply]));
+            }
+            if mating.len() > 1 {
+                let others: Vec<String> = mating.iter()
+                    .filter(|check| check.position_key() != played)
+                    .map(|check| positions[ply].usi_for_child(check))
+                    .collect();
+                return Err(format!("move {} also mates as {}", ply + 1, others.join(", ")));
+            }
+            if ply + 2 <= plies {
+                // Every reply mates within the remaining plies, so this one must take them all.
+                if mate_solver::mate_in(&positions[ply + 2], remaining - 2) != Some(remaining - 2) {
+                    return Err(format!("reply {} '{}' is not the longest defense", ply + 2, self.solution[ply + 1]));
+                }
+            }
+        }
+        if !mate_solver::evasions(&positions[plies]).is_empty() {
+            return Err("the solution does not end in mate".to_string());
+        }
+        Ok(())
+    }
+}
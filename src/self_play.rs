@@ -0,0 +1,138 @@
+// Self Play
+// Plays headless games between AIMinimax instances. The first few moves are random so
+// that games differ from each other; the rng is seeded, so a given seed always produces
+// the same games. With random_margin, the random moves are only chosen among good ones.
+
+use crate::ai::Think;
+use crate::ai_minimax::AIMinimax;
+use crate::ai_sender::AISender;
+use crate::evaluation::Evaluator;
+use crate::game::{Game, GameState};
+use crate::strength::Strength;
+
+pub struct SelfPlayGame {
+    /// Every position of the game, starting with the initial position.
+    pub positions: Vec<Game>,
+    /// WinPlayer0, WinPlayer1, or Draw if the game reached max_plies.
+    pub result: GameState,
+}
+
+pub struct SelfPlay {
+    /// Search depth for player 0 and player 1.
+    pub depths: [usize; 2],
+    /// If set, the player plays at this strength level instead of its depth.
+    pub strengths: [Option<Strength>; 2],
+    pub evaluator: Evaluator,
+    /// The number of random moves at the start of each game.
+    pub random_plies: usize,
+    /// If set, the random moves are chosen only among the moves that score within this
+    /// many pawns of the best, so that they are moves the AI might play.
+    pub random_margin: Option<f64>,
+    /// Games longer than this are scored as a draw.
+    pub max_plies: usize,
+    rng: fastrand::Rng,
+}
+
+impl SelfPlay {
+    pub fn new(seed: u64, depths: [usize; 2], evaluator: Evaluator) -> Self {
+        Self {
+            depths, evaluator,
+            strengths: [None, None],
+            random_plies: 4,
+            random_margin: None,
+            max_plies: 150,
+            rng: fastrand::Rng::with_seed(seed),
+        }
+    }
+
+    pub fn play_game(&mut self) -> SelfPlayGame {
+        let mut game = Game::new();
+        game.prepare();
+        let mut positions = vec![game];
+
+        while *game.update_state() == GameState::Ongoing {
+            let ply = positions.len() - 1;
+            if ply >= self.max_plies {
+                return SelfPlayGame { positions, result: GameState::Draw };
+            }
+
+            let next = if ply < self.random_plies {
+                self.random_move(&game)
+            } else if let Some(strength) = self.strengths[game.current_player] {
+                let mut ai = strength.minimax(game, self.evaluator.clone(), AISender::muted());
+                strength.think(&mut ai, &mut self.rng).best_node
+            } else {
+                let depth = self.depths[game.current_player];
+                let mut ai = AIMinimax::new(game, depth, self.evaluator.clone(), AISender::muted());
+                ai.think().best_node
+            };
+
+            match next {
+                Some(node) => {
+                    game = node;
+                    positions.push(game);
+                },
+                None => {
+                    // No moves at all. Count it as a loss for the player to move.
+                    let result = if game.current_player == 0 { GameState::WinPlayer1 } else { GameState::WinPlayer0 };
+                    return SelfPlayGame { positions, result };
+                },
+            }
+        }
+        SelfPlayGame { positions, result: game.state }
+    }
+
+    /// A random move, or with random_margin, a random one of the moves that score close
+    /// to the best at the player's depth.
+    fn random_move(&mut self, game: &Game) -> Option<Game> {
+        let mut candidates = match self.random_margin {
+            Some(margin) => {
+                let depth = self.depths[game.current_player].max(1);
+                let mut ai = AIMinimax::new(*game, depth, self.evaluator.clone(), AISender::muted());
+                let scores = ai.root_scores();
+                let best = scores.iter().map(|(_, score)| *score).fold(f64::MIN, f64::max);
+                scores.into_iter()
+                    .filter(|(_, score)| *score >= best - margin)
+                    .map(|(child, _)| child)
+                    .collect()
+            },
+            None => game.child_nodes(game.current_player),
+        };
+        if candidates.is_empty() { return None }
+        Some(candidates.swap_remove(self.rng.usize(..candidates.len())))
+    }
+}
+
+/// Returns true if the position is quiet: ongoing, the last move wasn't a capture and
+/// neither king can be captured. The static evaluation of quiet positions means
+/// something, so these are the positions worth training on.
+pub fn is_quiet(position: &Game) -> bool {
+    let mut position = *position;
+    *position.update_state() == GameState::Ongoing
+        && !position.last_move.is_some_and(|m| m.2)
+        && !position.is_in_check(0)
+        && !position.is_in_check(1)
+}
+
+/// The game result for player 0: 1 for a win, 0.5 for a draw, 0 for a loss.
+pub fn result_value(result: GameState) -> f64 {
+    match result {
+        GameState::WinPlayer0 => 1.0,
+        GameState::WinPlayer1 => 0.0,
+        _ => 0.5,
+    }
+}
+
+/// Formats a training position as written by the 'selfplay' tool: "<sfen>;<result>",
+/// where result is result_value() of the game.
+pub fn training_line(position: &Game, result: GameState) -> String {
+    format!("{};{}", position.to_sfen(), result_value(result))
+}
+
+/// The inverse of training_line().
+pub fn parse_training_line(line: &str) -> Result<(Game, f64), String> {
+    let (sfen, result) = line.split_once(';').ok_or(format!("missing ';' in '{}'", line))?;
+    let game = Game::from_sfen(sfen)?;
+    let result = result.trim().parse::<f64>().map_err(|e| format!("'{}': {}", result, e))?;
+    Ok((game, result))
+}
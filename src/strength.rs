@@ -0,0 +1,159 @@
+// Strength
+// Named difficulty levels for the AI. Rather than only lowering the search depth, the
+// weaker levels add noise to the scores of the moves at the root, sometimes play a
+// worse move on purpose and use a cruder evaluation. Deliberate mistakes are kept
+// within a margin of the best move, so even Beginner never simply gives away its king.
+// The 'levels' tool plays the levels against each other to check that each one
+// is clearly stronger than the one below it.
+
+use std::time::Duration;
+
+use crate::ai::{AIProgress, Think};
+use crate::ai_minimax::{AIMinimax, WIN_LOSS_VAL};
+use crate::ai_sender::AISender;
+use crate::evaluation::Evaluator;
+use crate::game::Game;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strength {
+    Beginner,
+    Novice,
+    Intermediate,
+    Advanced,
+    Expert,
+}
+
+pub struct StrengthSettings {
+    pub depth: usize,
+    /// Expert searches deeper while time allows. See AIMinimax.time_limit.
+    pub time_limit: Option<Duration>,
+    /// Standard deviation of the noise added to the root move scores, in pawns.
+    pub noise: f64,
+    /// The chance of deliberately playing a move other than the best.
+    pub blunder_chance: f64,
+    /// How much worse than the best move, in pawns, the noisy or deliberate choices
+    /// may be.
+    pub blunder_margin: f64,
+    /// Use EvalWeights::simplified().
+    pub simple_evaluation: bool,
+}
+
+impl Strength {
+    /// Weakest first.
+    pub const ALL: [Strength; 5] = [
+        Strength::Beginner, Strength::Novice, Strength::Intermediate, Strength::Advanced, Strength::Expert,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strength::Beginner => "Beginner",
+            Strength::Novice => "Novice",
+            Strength::Intermediate => "Intermediate",
+            Strength::Advanced => "Advanced",
+            Strength::Expert => "Expert",
+        }
+    }
+
+    /// The position in ALL, from 0.
+    pub fn level(&self) -> usize {
+        Strength::ALL.iter().position(|s| s == self).unwrap()
+    }
+
+    pub fn from_level(level: usize) -> Strength {
+        Strength::ALL[level.min(Strength::ALL.len() - 1)]
+    }
+
+    /// Calibrated with the 'levels' tool.
+    pub fn settings(&self) -> StrengthSettings {
+        match self {
+            Strength::Beginner => StrengthSettings {
+                depth: 2, time_limit: None,
+                noise: 3.0, blunder_chance: 0.4, blunder_margin: 8.0,
+                simple_evaluation: true,
+            },
+            Strength::Novice => StrengthSettings {
+                depth: 2, time_limit: None,
+                noise: 1.5, blunder_chance: 0.2, blunder_margin: 5.0,
+                simple_evaluation: true,
+            },
+            Strength::Intermediate => StrengthSettings {
+                depth: 3, time_limit: None,
+                noise: 0.8, blunder_chance: 0.1, blunder_margin: 3.0,
+                simple_evaluation: false,
+            },
+            Strength::Advanced => StrengthSettings {
+                depth: 4, time_limit: None,
+                noise: 0.3, blunder_chance: 0.03, blunder_margin: 1.5,
+                simple_evaluation: false,
+            },
+            Strength::Expert => StrengthSettings {
+                depth: 7, time_limit: Some(Duration::from_secs(3)),
+                noise: 0.0, blunder_chance: 0.0, blunder_margin: 0.0,
+                simple_evaluation: false,
+            },
+        }
+    }
+
+    /// Returns an AI searching at this strength, with evaluator simplified if needed.
+    pub fn minimax(&self, game: Game, evaluator: Evaluator, message_sender: AISender) -> AIMinimax {
+        let settings = self.settings();
+        let evaluator = if settings.simple_evaluation { evaluator.simplified() } else { evaluator };
+        let mut ai = AIMinimax::new(game, settings.depth, evaluator, message_sender);
+        ai.time_limit = settings.time_limit;
+        ai
+    }
+
+    /// Searches and picks a move the way this level plays. ai must come from minimax().
+    pub fn think(&self, ai: &mut AIMinimax, rng: &mut fastrand::Rng) -> AIProgress {
+        let settings = self.settings();
+        if settings.noise == 0.0 && settings.blunder_chance == 0.0 {
+            return ai.think();
+        }
+
+        let scores = ai.root_scores();
+        let mut progress = AIProgress::new();
+        progress.nodes = scores.len();
+        progress.percent_complete = 1.0;
+        progress.is_complete = true;
+        if let Some(index) = self.choose(&scores, rng) {
+            let (node, score) = scores[index];
+            progress.best_node = Some(node);
+            progress.score = score;
+            progress.pv = node.last_move.into_iter().collect();
+        }
+        progress
+    }
+
+    /// Picks one of the scored root moves. Only moves within blunder_margin of the best
+    /// are considered, and never a move that loses by force when another doesn't.
+    pub fn choose(&self, scores: &[(Game, f64)], rng: &mut fastrand::Rng) -> Option<usize> {
+        let settings = self.settings();
+        let best = scores.iter().map(|(_, score)| *score).fold(f64::MIN, f64::max);
+        let losing = -WIN_LOSS_VAL / 2.0;
+        let candidates: Vec<usize> = (0..scores.len())
+            .filter(|i| {
+                let score = scores[*i].1;
+                score >= best - settings.blunder_margin && (score > losing || best <= losing)
+            })
+            .collect();
+
+        let best_index = *candidates.iter().find(|i| scores[**i].1 == best)?;
+        if candidates.len() > 1 && rng.f64() < settings.blunder_chance {
+            let others: Vec<usize> = candidates.into_iter().filter(|i| *i != best_index).collect();
+            return Some(others[rng.usize(..others.len())]);
+        }
+
+        // Noisy scores, so close moves are chosen between at random.
+        candidates.into_iter()
+            .map(|i| (i, scores[i].1 + settings.noise * gaussian(rng)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+}
+
+/// A standard normal random number (Box-Muller).
+fn gaussian(rng: &mut fastrand::Rng) -> f64 {
+    let u1 = rng.f64().max(f64::MIN_POSITIVE);
+    let u2 = rng.f64();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
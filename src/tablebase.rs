@@ -0,0 +1,571 @@
+// Tablebase
+// Exact results for positions with few pieces, found by retrograde analysis. A table
+// covers one material set: both kings plus a few other pieces (e.g. "G" for K+G vs K),
+// where each piece may belong to either player and be on the board or in hand, with
+// either player to move. Since captured pieces change hands rather than leave the game,
+// every move from such a position stays in the same table (or captures a king).
+//
+// A normal game always has all ten other pieces somewhere, so tables only come into
+// play for positions that start with fewer pieces, such as set-up positions and puzzles.
+//
+// Each position is stored as one byte: its distance to the king capture in plies, odd if
+// the player to move wins and even if they lose, or 0 for a draw. Files store the bytes
+// as they are, except that runs (common among draws and impossible positions) are
+// stored as a RUN marker, the value and the run length.
+// Tables are built by the 'tablebase' tool.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Mutex;
+
+use crate::game::{Game, GameLocation, GameState, GRID_COUNT, NONE};
+use crate::piece::{Piece, PieceKind};
+
+/// The directory the app loads tables from at startup, if present.
+pub const TABLEBASE_DIR: &str = "tablebases";
+
+/// The most pieces besides the kings a table may have. Three would need over a gigabyte.
+pub const MAX_EXTRA_PIECES: usize = 2;
+
+const DRAW: u8 = 0;
+const MAX_DISTANCE: u8 = 250;
+/// Marks table entries that aren't positions, like two pieces on one square.
+const INVALID: u8 = 251;
+/// Starts a run in a table file.
+const RUN: u8 = 255;
+const MIN_RUN: usize = 4;
+
+const MAGIC: &[u8; 4] = b"MSTB";
+const VERSION: u32 = 1;
+
+/// A table result for the player to move. Distances are in plies, up to and including
+/// the king capture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TablebaseResult {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+impl TablebaseResult {
+    fn from_value(value: u8) -> Option<Self> {
+        match value {
+            DRAW => Some(TablebaseResult::Draw),
+            INVALID => None,
+            v if v % 2 == 1 => Some(TablebaseResult::Win(v)),
+            v => Some(TablebaseResult::Loss(v)),
+        }
+    }
+
+    /// Orders results for the player to move: faster wins first, slower losses last.
+    fn rank(&self) -> i32 {
+        match self {
+            TablebaseResult::Win(d) => 1000 - *d as i32,
+            TablebaseResult::Draw => 0,
+            TablebaseResult::Loss(d) => -1000 + *d as i32,
+        }
+    }
+}
+
+pub struct Tablebase {
+    /// The material name, e.g. "GP".
+    material: String,
+    /// The unpromoted kinds of the pieces besides the kings.
+    kinds: Vec<PieceKind>,
+    values: Vec<u8>,
+}
+
+impl Tablebase {
+    /// Parses a material name like "G" or "sp" (case is ignored) into piece kinds.
+    pub fn parse_material(material: &str) -> Result<Vec<PieceKind>, String> {
+        let mut kinds = Vec::new();
+        for c in material.chars() {
+            let kind = match c.to_ascii_uppercase() {
+                'G' => PieceKind::Gold,
+                'S' => PieceKind::Silver,
+                'R' => PieceKind::Rook,
+                'B' => PieceKind::Bishop,
+                'P' => PieceKind::Pawn,
+                _ => return Err(format!("unknown piece '{}' in '{}'", c, material)),
+            };
+            if kinds.iter().filter(|k| **k == kind).count() == 2 {
+                return Err(format!("there are only two of each piece, '{}'", material));
+            }
+            kinds.push(kind);
+        }
+        if kinds.len() > MAX_EXTRA_PIECES {
+            return Err(format!("at most {} pieces besides the kings", MAX_EXTRA_PIECES));
+        }
+        kinds.sort_by_key(|k| k.index());
+        Ok(kinds)
+    }
+
+    fn material_name(kinds: &[PieceKind]) -> String {
+        kinds.iter().map(|k| Piece::new(0, *k, 0).string_rep().to_string()).collect()
+    }
+
+    /// The material name of the game, or None if a king is missing.
+    fn material_of(game: &Game) -> Option<String> {
+        let mut kinds = Vec::new();
+        let mut kings = 0;
+        for piece in &game.pieces {
+            if piece.location == GameLocation::OutOfGame { continue }
+            match piece.kind {
+                PieceKind::King => kings += 1,
+                kind => kinds.push(Piece::new(0, kind, 0).demotion_kind().unwrap_or(kind)),
+            }
+        }
+        if kings != 2 { return None }
+        kinds.sort_by_key(|k| k.index());
+        Some(Self::material_name(&kinds))
+    }
+
+    pub fn material(&self) -> &str {
+        &self.material
+    }
+
+    /// The number of states of one piece: each player's board squares (twice for
+    /// pieces that promote) and hand.
+    fn state_count(kind: PieceKind) -> usize {
+        2 * Self::states_per_player(kind)
+    }
+
+    fn states_per_player(kind: PieceKind) -> usize {
+        let variants = if Piece::new(0, kind, 0).promotion_kind().is_some() { 2 } else { 1 };
+        GRID_COUNT * variants + 1
+    }
+
+    fn position_count(kinds: &[PieceKind]) -> usize {
+        kinds.iter().fold(2 * GRID_COUNT * GRID_COUNT, |count, kind| count * Self::state_count(*kind))
+    }
+
+    /// Returns the position for a table index, or None if the index isn't a position.
+    fn position(&self, mut index: usize) -> Option<Game> {
+        let mut states = vec![0; self.kinds.len()];
+        for (slot, kind) in self.kinds.iter().enumerate().rev() {
+            let count = Self::state_count(*kind);
+            states[slot] = index % count;
+            index /= count;
+        }
+        let king_1 = index % GRID_COUNT;
+        index /= GRID_COUNT;
+        let king_0 = index % GRID_COUNT;
+        let side = index / GRID_COUNT;
+        if king_0 == king_1 { return None }
+
+        let mut game = Game::new();
+        for (id, piece) in game.pieces.iter_mut().enumerate() {
+            piece.id = id;
+        }
+        let place = |game: &mut Game, mut piece: Piece, square: usize| -> bool {
+            if game.grid[square] != NONE { return false }
+            piece.location = GameLocation::Board;
+            piece.location_index = square;
+            game.pieces[piece.id] = piece;
+            game.grid[square] = piece.id;
+            true
+        };
+        place(&mut game, Piece::new(0, PieceKind::King, 0), king_0);
+        place(&mut game, Piece::new(1, PieceKind::King, 1), king_1);
+
+        let mut hand_counts = [0; 2];
+        for (slot, kind) in self.kinds.iter().enumerate() {
+            let per_player = Self::states_per_player(*kind);
+            let player = states[slot] / per_player;
+            let state = states[slot] % per_player;
+            let mut piece = Piece::new(2 + slot, *kind, player);
+
+            if state == per_player - 1 {
+                piece.location = GameLocation::Reserve;
+                piece.location_index = hand_counts[player];
+                game.reserves[player][hand_counts[player]] = piece.id;
+                game.pieces[piece.id] = piece;
+                hand_counts[player] += 1;
+            } else {
+                if state >= GRID_COUNT {
+                    piece.kind = piece.promotion_kind()?;
+                }
+                if !place(&mut game, piece, state % GRID_COUNT) { return None }
+            }
+        }
+        game.current_player = side;
+        Some(game)
+    }
+
+    /// Returns the table index of the game, or None if it has different material.
+    fn index_of(&self, game: &Game) -> Option<usize> {
+        let mut kings = [NONE; 2];
+        let mut used = [false; 12];
+        let mut states = Vec::with_capacity(self.kinds.len());
+
+        for piece in &game.pieces {
+            if piece.kind == PieceKind::King && piece.location == GameLocation::Board {
+                kings[piece.player] = piece.location_index;
+            }
+        }
+        if kings.contains(&NONE) { return None }
+
+        for kind in &self.kinds {
+            let piece = game.pieces.iter().find(|p| {
+                !used[p.id]
+                    && p.location != GameLocation::OutOfGame
+                    && p.kind != PieceKind::King
+                    && p.demotion_kind().unwrap_or(p.kind) == *kind
+            })?;
+            used[piece.id] = true;
+
+            let per_player = Self::states_per_player(*kind);
+            let state = match piece.location {
+                GameLocation::Reserve => per_player - 1,
+                _ if piece.kind != *kind => GRID_COUNT + piece.location_index,
+                _ => piece.location_index,
+            };
+            states.push(piece.player * per_player + state);
+        }
+        // Any pieces left over mean different material.
+        let count = game.pieces.iter()
+            .filter(|p| p.location != GameLocation::OutOfGame && p.kind != PieceKind::King)
+            .count();
+        if count != self.kinds.len() { return None }
+
+        let mut index = (game.current_player * GRID_COUNT + kings[0]) * GRID_COUNT + kings[1];
+        for (kind, state) in self.kinds.iter().zip(states) {
+            index = index * Self::state_count(*kind) + state;
+        }
+        Some(index)
+    }
+
+    /// Looks up the game, which must have this table's material.
+    pub fn probe(&self, game: &Game) -> Option<TablebaseResult> {
+        let index = self.index_of(game)?;
+        TablebaseResult::from_value(self.values[index])
+    }
+
+    /// Solves every position of the material set. report is called after each pass
+    /// with the pass number and the number of positions resolved in it.
+    pub fn generate(material: &str, mut report: impl FnMut(u8, usize)) -> Result<Self, String> {
+        let kinds = Self::parse_material(material)?;
+        let count = Self::position_count(&kinds);
+        let mut table = Self {
+            material: Self::material_name(&kinds),
+            kinds,
+            values: vec![DRAW; count],
+        };
+
+        let invalid: Vec<usize> = table.parallel_map(|table, index| {
+            match table.position(index) {
+                Some(_) => None,
+                None => Some(index),
+            }
+        });
+        for index in invalid {
+            table.values[index] = INVALID;
+        }
+
+        // Pass n finds the wins (odd n) or losses (even n) in exactly n plies. Once a
+        // pass finds nothing, no later pass can either.
+        for distance in 1..=MAX_DISTANCE {
+            let resolved = table.parallel_map(|table, index| {
+                if table.values[index] != DRAW { return None }
+                table.resolve(index, distance).then_some(index)
+            });
+            for index in &resolved {
+                table.values[*index] = distance;
+            }
+            report(distance, resolved.len());
+            if resolved.is_empty() { break }
+        }
+        Ok(table)
+    }
+
+    /// Returns true if the position at index is a win (odd distance) or loss (even
+    /// distance) in exactly distance plies, given all shorter results.
+    fn resolve(&self, index: usize, distance: u8) -> bool {
+        let game = match self.position(index) {
+            Some(game) => game,
+            None => return false,
+        };
+        let children = game.child_nodes(game.current_player);
+
+        // None if the move captures the king.
+        let child_value = |child: &Game| -> Option<u8> {
+            let mut child = *child;
+            if *child.update_state() != GameState::Ongoing { return None }
+            Some(self.index_of(&child).map_or(INVALID, |i| self.values[i]))
+        };
+
+        if distance % 2 == 1 {
+            // A win: some move takes the king, or leaves the opponent lost in distance - 1.
+            children.iter().any(|child| match child_value(child) {
+                None => distance == 1,
+                Some(value) => distance > 1 && value == distance - 1,
+            })
+        } else {
+            // A loss: every move leaves the opponent a win, the slowest in distance - 1.
+            !children.is_empty() && children.iter().all(|child| match child_value(child) {
+                None => false,
+                Some(value) => value != INVALID && value % 2 == 1 && value < distance,
+            })
+        }
+    }
+
+    /// Runs f over every index on all cores and collects the Some results.
+    fn parallel_map<F>(&self, f: F) -> Vec<usize>
+        where F: Fn(&Tablebase, usize) -> Option<usize> + Sync
+    {
+        let threads = std::thread::available_parallelism().map_or(4, |n| n.get());
+        let chunk = self.values.len().div_ceil(threads);
+        let results = Mutex::new(Vec::new());
+        std::thread::scope(|scope| {
+            for start in (0..self.values.len()).step_by(chunk) {
+                let end = (start + chunk).min(self.values.len());
+                let (f, results) = (&f, &results);
+                scope.spawn(move || {
+                    let found: Vec<usize> = (start..end).filter_map(|index| f(self, index)).collect();
+                    results.lock().unwrap().extend(found);
+                });
+            }
+        });
+        results.into_inner().unwrap()
+    }
+
+    /// A summary of the results: (wins, losses, draws, longest distance).
+    pub fn stats(&self) -> (usize, usize, usize, u8) {
+        let mut stats = (0, 0, 0, 0);
+        for value in &self.values {
+            match TablebaseResult::from_value(*value) {
+                Some(TablebaseResult::Win(d)) => { stats.0 += 1; stats.3 = stats.3.max(d); },
+                Some(TablebaseResult::Loss(d)) => { stats.1 += 1; stats.3 = stats.3.max(d); },
+                Some(TablebaseResult::Draw) => stats.2 += 1,
+                None => {},
+            }
+        }
+        stats
+    }
+
+    /// The file name for the table in the given directory.
+    pub fn path(dir: &str, material: &str) -> String {
+        format!("{}/{}.tb", dir, material)
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        if bytes.len() < 13 || &bytes[..4] != MAGIC {
+            return Err("not a tablebase file".to_string());
+        }
+        let u32_at = |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        if u32_at(4) != VERSION {
+            return Err(format!("unsupported version {}", u32_at(4)));
+        }
+        let name_length = bytes[8] as usize;
+        let name = std::str::from_utf8(bytes.get(9..9 + name_length).ok_or("file is too short")?)
+            .map_err(|e| e.to_string())?;
+        let kinds = Self::parse_material(name)?;
+        let count = Self::position_count(&kinds);
+
+        let values = Self::decode(&bytes[9 + name_length..], count)?;
+        Ok(Self { material: name.to_string(), kinds, values })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.push(self.material.len() as u8);
+        bytes.extend_from_slice(self.material.as_bytes());
+
+        bytes.extend(Self::encode(&self.values));
+        fs::write(path, bytes).map_err(|e| e.to_string())
+    }
+
+    /// Stores runs of MIN_RUN or more equal values as RUN, the value and the length.
+    fn encode(values: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut start = 0;
+        while start < values.len() {
+            let value = values[start];
+            let mut end = start + 1;
+            while end < values.len() && values[end] == value {
+                end += 1;
+            }
+            if end - start < MIN_RUN {
+                bytes.extend(&values[start..end]);
+            } else {
+                bytes.push(RUN);
+                bytes.push(value);
+                let mut length = end - start;
+                while length >= 0x80 {
+                    bytes.push((length & 0x7f) as u8 | 0x80);
+                    length >>= 7;
+                }
+                bytes.push(length as u8);
+            }
+            start = end;
+        }
+        bytes
+    }
+
+    /// Expands the bytes from encode(), which must hold count values.
+    fn decode(bytes: &[u8], count: usize) -> Result<Vec<u8>, String> {
+        let mut values = Vec::with_capacity(count);
+        let mut bytes = bytes.iter().copied();
+        while let Some(byte) = bytes.next() {
+            if byte != RUN {
+                values.push(byte);
+                continue;
+            }
+            let value = bytes.next().ok_or("file is truncated")?;
+            // The length is stored 7 bits at a time, low bits first.
+            let mut length = 0;
+            for shift in (0..64).step_by(7) {
+                let part = bytes.next().ok_or("file is truncated")?;
+                length |= ((part & 0x7f) as usize) << shift;
+                if part & 0x80 == 0 { break }
+            }
+            if values.len() + length > count { break }
+            values.resize(values.len() + length, value);
+        }
+        if values.len() != count {
+            return Err(format!("expected {} positions, found {}", count, values.len()));
+        }
+        Ok(values)
+    }
+}
+
+/// All the tables loaded at startup, looked up by material.
+#[derive(Default)]
+pub struct Tablebases {
+    tables: HashMap<String, Tablebase>,
+}
+
+impl Tablebases {
+    /// Loads every table in the directory. Returns None if there are none.
+    pub fn load_dir(dir: &str) -> Option<Self> {
+        let entries = fs::read_dir(dir).ok()?;
+        let mut tablebases = Self::default();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|e| e != "tb") { continue }
+            let path = path.to_string_lossy().to_string();
+            match Tablebase::load(&path) {
+                Ok(table) => tablebases.add(table),
+                Err(error) => println!("Could not load {}: {}", path, error),
+            }
+        }
+        if tablebases.tables.is_empty() { None } else { Some(tablebases) }
+    }
+
+    pub fn add(&mut self, table: Tablebase) {
+        self.tables.insert(table.material.clone(), table);
+    }
+
+    /// The result for the player to move, if there's a table for the game's material.
+    pub fn probe(&self, game: &Game) -> Option<TablebaseResult> {
+        // Cheap check first; normal games have far more pieces than any table.
+        let count = game.pieces.iter().filter(|p| p.location != GameLocation::OutOfGame).count();
+        if count > 2 + MAX_EXTRA_PIECES { return None }
+        self.tables.get(&Tablebase::material_of(game)?)?.probe(game)
+    }
+
+    /// Returns the best child node of the game and the game's result, if the game is
+    /// in a table: the fastest win, or else a draw, or else the slowest loss.
+    pub fn best_child(&self, game: &Game) -> Option<(Game, TablebaseResult)> {
+        let result = self.probe(game)?;
+        let mut best: Option<(Game, i32)> = None;
+        for child in game.child_nodes(game.current_player) {
+            let mut terminal = child;
+            let rank = if *terminal.update_state() != GameState::Ongoing {
+                i32::MAX
+            } else {
+                // The child's result is for the opponent, so flip it.
+                match self.probe(&child) {
+                    Some(child_result) => -child_result.rank(),
+                    None => continue,
+                }
+            };
+            if best.is_none_or(|(_, best_rank)| rank > best_rank) {
+                best = Some((child, rank));
+            }
+        }
+        best.map(|(child, _)| (child, result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(material: &str) -> Tablebase {
+        let kinds = Tablebase::parse_material(material).unwrap();
+        Tablebase { material: Tablebase::material_name(&kinds), kinds, values: Vec::new() }
+    }
+
+    fn assert_round_trips(table: &Tablebase, step: usize) {
+        let count = Tablebase::position_count(&table.kinds);
+        let mut positions = 0;
+        for index in (0..count).step_by(step) {
+            let Some(game) = table.position(index) else { continue };
+            assert_eq!(table.index_of(&game), Some(index), "{}", game.to_sfen());
+            positions += 1;
+        }
+        assert!(positions > 0);
+    }
+
+    #[test]
+    fn index_and_position_round_trip() {
+        assert_round_trips(&table("G"), 1);
+        // Promoting pieces, two of a kind, and pieces in either hand.
+        assert_round_trips(&table("PP"), 7);
+        assert_round_trips(&table("BS"), 13);
+    }
+
+    #[test]
+    fn index_of_reads_sfen_positions() {
+        let table = table("PR");
+        for sfen in ["k4/5/2+P2/5/4K b R 1", "k4/1+r3/5/5/4K w P 1", "k4/5/5/p4/R3K w - 1"] {
+            let game = Game::from_sfen(sfen).unwrap();
+            let index = table.index_of(&game).unwrap();
+            assert_eq!(table.position(index).unwrap().to_sfen(), sfen);
+        }
+        // Other material isn't in the table.
+        let game = Game::from_sfen("k4/5/2G2/5/4K b R 1").unwrap();
+        assert_eq!(table.index_of(&game), None);
+    }
+
+    #[test]
+    fn encode_and_decode_are_lossless() {
+        let mut values = vec![3, 3, 3, 0, 5];
+        values.extend([INVALID; MIN_RUN]);
+        values.extend([DRAW; 200]);
+        values.push(MAX_DISTANCE);
+        values.extend([7; 20_000]);
+        values.extend([DRAW, DRAW, 1]);
+        let bytes = Tablebase::encode(&values);
+        assert!(bytes.len() < 30);
+        assert_eq!(Tablebase::decode(&bytes, values.len()), Ok(values.clone()));
+        assert!(Tablebase::decode(&bytes, values.len() + 1).is_err());
+        assert!(Tablebase::decode(&bytes[..bytes.len() - 1], values.len()).is_err());
+        assert_eq!(Tablebase::decode(&[], 0), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn probes_known_mates() {
+        let table = Tablebase::generate("G", |_, _| {}).unwrap();
+        let probe = |sfen: &str| table.probe(&Game::from_sfen(sfen).unwrap());
+
+        // The gold takes the king.
+        assert_eq!(probe("2k2/2G2/5/5/2K2 b - 1"), Some(TablebaseResult::Win(1)));
+        // Dropping the gold in front of the king, guarded by ours, mates.
+        assert_eq!(probe("2k2/5/2K2/5/5 b G 1"), Some(TablebaseResult::Win(3)));
+        assert_eq!(probe("2k2/2G2/2K2/5/5 w - 1"), Some(TablebaseResult::Loss(2)));
+        // The same, with the sides swapped.
+        assert_eq!(probe("5/5/2k2/5/2K2 w g 1"), Some(TablebaseResult::Win(3)));
+
+        let mut tablebases = Tablebases::default();
+        tablebases.add(table);
+        let game = Game::from_sfen("2k2/5/2K2/5/5 b G 1").unwrap();
+        let (child, result) = tablebases.best_child(&game).unwrap();
+        assert_eq!(result, TablebaseResult::Win(3));
+        assert_eq!(child.to_sfen(), "2k2/2G2/2K2/5/5 w - 1");
+    }
+}
@@ -0,0 +1,322 @@
+// Theme
+// How the board and pieces look: a piece set and a colour scheme, each chosen in
+// Settings. The built-in ones are always there; more are found at startup in the themes
+// directory:
+//
+//   assets/themes/pieces/<name>/   A piece set of images named like the built-in ones
+//                                  (king.png, pawn_pro.png, ...), at twice their display
+//                                  size. A missing image is taken from Pictogram.
+//   assets/themes/colors/<name>.txt   A colour scheme, one "key r g b" line per colour,
+//                                  0 to 255. Keys are background, square, highlight,
+//                                  piece, selected and text; a missing key keeps the
+//                                  Classic colour, and "name" sets the name shown.
+//   assets/themes/kanji.ttf        A font with the kanji characters, such as Noto Sans
+//                                  CJK. The Kanji set is only offered when it loads.
+//
+// Letters and Kanji are drawn when chosen rather than loaded from images.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use macroquad::prelude::*;
+
+use crate::asset_loader::AssetLoader;
+use crate::piece::PieceKind::{self, *};
+use crate::view::set_layout_camera;
+
+pub const THEMES_DIR: &str = "assets/themes";
+const KANJI_FONT_FILE: &str = "kanji.ttf";
+
+/// The size of a drawn piece texture, as the Pictogram images.
+const PIECE_TEXTURE_SIZE: (f32, f32) = (160., 169.);
+const PIECE_FACE_COLOR: Color = Color::new(0.93, 0.9, 0.84, 1.0);
+const PIECE_EDGE_COLOR: Color = Color::new(0.25, 0.25, 0.25, 1.0);
+const PIECE_TEXT_COLOR: Color = Color::new(0.1, 0.1, 0.1, 1.0);
+const PIECE_PROMOTED_COLOR: Color = Color::new(0.75, 0.1, 0.1, 1.0);
+
+/// The texture name of the kind, as the built-in images and a piece set's files are named.
+pub fn texture_name(kind: PieceKind) -> &'static str {
+    match kind {
+        King => "king",
+        Gold => "gold",
+        Silver => "silver",
+        SilverPro => "silver_pro",
+        Rook => "rook",
+        RookPro => "rook_pro",
+        Bishop => "bishop",
+        BishopPro => "bishop_pro",
+        Pawn => "pawn",
+        PawnPro => "pawn_pro",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PieceStyle {
+    /// The built-in images: the piece's letter, with dots for its moves.
+    Pictogram,
+    /// The piece's letter, red with a '+' once promoted.
+    Letters,
+    /// The traditional characters, promoted ones in red.
+    Kanji,
+    /// Images from the directory.
+    Images(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+pub struct PieceSet {
+    pub name: String,
+    pub style: PieceStyle,
+}
+
+impl PieceSet {
+    /// The built-in sets, Kanji if its font loads, then any in the themes directory.
+    pub fn available() -> Vec<PieceSet> {
+        let mut sets = vec![
+            PieceSet { name: String::from("Pictogram"), style: PieceStyle::Pictogram },
+            PieceSet { name: String::from("Letters"), style: PieceStyle::Letters },
+        ];
+        if Self::load_kanji_font().is_ok() {
+            sets.push(PieceSet { name: String::from("Kanji"), style: PieceStyle::Kanji });
+        }
+        let Ok(entries) = fs::read_dir(Path::new(THEMES_DIR).join("pieces")) else { return sets };
+        let mut dirs: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect();
+        dirs.sort();
+        for dir in dirs {
+            let name = dir.file_name().unwrap_or_default().to_string_lossy().to_string();
+            sets.push(PieceSet { name, style: PieceStyle::Images(dir) });
+        }
+        sets
+    }
+
+    /// The texture of each kind, by PieceKind::index().
+    pub fn textures(&self) -> Vec<Texture2D> {
+        let textures = match &self.style {
+            PieceStyle::Pictogram => PieceKind::ALL.iter().map(|kind| AssetLoader::get_texture(texture_name(*kind))).collect(),
+            PieceStyle::Letters => Self::draw_all(&AssetLoader::get_font("Menlo Bold"), Self::letter),
+            PieceStyle::Kanji => match Self::load_kanji_font() {
+                Ok(font) => Self::draw_all(&font, Self::kanji),
+                Err(error) => {
+                    println!("Could not load the kanji font: {}", error);
+                    Self::draw_all(&AssetLoader::get_font("Menlo Bold"), Self::letter)
+                },
+            },
+            PieceStyle::Images(dir) => PieceKind::ALL.iter().map(|kind| Self::load_image(dir, *kind)).collect(),
+        };
+        // Drawing left the camera on a render target.
+        set_layout_camera();
+        textures
+    }
+
+    fn letter(kind: PieceKind) -> &'static str {
+        match kind {
+            King => "K",
+            Gold => "G",
+            Silver => "S",
+            SilverPro => "+S",
+            Rook => "R",
+            RookPro => "+R",
+            Bishop => "B",
+            BishopPro => "+B",
+            Pawn => "P",
+            PawnPro => "+P",
+        }
+    }
+
+    fn kanji(kind: PieceKind) -> &'static str {
+        match kind {
+            King => "王",
+            Gold => "金",
+            Silver => "銀",
+            SilverPro => "全",
+            Rook => "飛",
+            RookPro => "龍",
+            Bishop => "角",
+            BishopPro => "馬",
+            Pawn => "歩",
+            PawnPro => "と",
+        }
+    }
+
+    fn load_kanji_font() -> Result<Font, String> {
+        let path = Path::new(THEMES_DIR).join(KANJI_FONT_FILE);
+        let bytes = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        load_ttf_font_from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The kind's image from the directory, or the built-in one if it can't be read.
+    fn load_image(dir: &Path, kind: PieceKind) -> Texture2D {
+        let name = texture_name(kind);
+        let path = dir.join(format!("{}.png", name));
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(_) => return AssetLoader::get_texture(name),
+        };
+        match Image::from_file_with_format(&bytes, None) {
+            Ok(image) => Texture2D::from_image(&image),
+            Err(error) => {
+                println!("Could not load {}: {}", path.display(), error);
+                AssetLoader::get_texture(name)
+            },
+        }
+    }
+
+    fn draw_all(font: &Font, text_for: fn(PieceKind) -> &'static str) -> Vec<Texture2D> {
+        PieceKind::ALL.iter()
+            .map(|kind| {
+                let promoted = matches!(kind, SilverPro | RookPro | BishopPro | PawnPro);
+                Self::draw_piece(font, text_for(*kind), promoted)
+            })
+            .collect()
+    }
+
+    /// Draws a blank piece, pointed at the top, with the text on it.
+    fn draw_piece(font: &Font, text: &str, promoted: bool) -> Texture2D {
+        let (w, h) = PIECE_TEXTURE_SIZE;
+        let target = render_target(w as u32, h as u32);
+        target.texture.set_filter(FilterMode::Linear);
+        // As in view::draw_to_texture, a render target's camera isn't flipped.
+        set_camera(&Camera2D {
+            zoom: vec2(2. / w, 2. / h),
+            target: vec2(w / 2., h / 2.),
+            render_target: Some(target.clone()),
+            ..Default::default()
+        });
+        clear_background(BLANK);
+
+        let outline = [
+            vec2(w / 2., 4.),
+            vec2(w - 18., 38.),
+            vec2(w - 6., h - 4.),
+            vec2(6., h - 4.),
+            vec2(18., 38.),
+        ];
+        let center = vec2(w / 2., h / 2.);
+        for i in 0..outline.len() {
+            draw_triangle(center, outline[i], outline[(i + 1) % outline.len()], PIECE_FACE_COLOR);
+        }
+        for i in 0..outline.len() {
+            let (a, b) = (outline[i], outline[(i + 1) % outline.len()]);
+            draw_line(a.x, a.y, b.x, b.y, 4., PIECE_EDGE_COLOR);
+        }
+
+        let font_size = if text.chars().count() > 1 && text.is_ascii() { 64 } else { 84 };
+        let size = measure_text(text, Some(font), font_size, 1.0);
+        let params = TextParams {
+            font: Some(font),
+            font_size,
+            color: if promoted { PIECE_PROMOTED_COLOR } else { PIECE_TEXT_COLOR },
+            ..Default::default()
+        };
+        draw_text_ex(text, (w - size.width) / 2., h * 0.58 + size.offset_y / 2., params);
+        target.texture
+    }
+}
+
+/// The colours of the board, its pieces and text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorScheme {
+    pub name: String,
+    pub background: Color,
+    /// Tints the square image.
+    pub square: Color,
+    /// Tints the squares a selected piece can move to.
+    pub highlight: Color,
+    /// Tints the pieces.
+    pub piece: Color,
+    /// Tints a selected piece.
+    pub selected: Color,
+    pub text: Color,
+}
+
+impl ColorScheme {
+    pub fn classic() -> Self {
+        Self {
+            name: String::from("Classic"),
+            background: Color::from_rgba(40, 40, 40, 255),
+            square: WHITE,
+            highlight: LIGHTGRAY,
+            piece: WHITE,
+            selected: LIGHTGRAY,
+            text: WHITE,
+        }
+    }
+
+    /// The built-in schemes, then any in the themes directory.
+    pub fn available() -> Vec<ColorScheme> {
+        let mut schemes = vec![
+            Self::classic(),
+            Self {
+                name: String::from("Walnut"),
+                background: Color::from_rgba(48, 32, 22, 255),
+                square: Color::from_rgba(255, 205, 140, 255),
+                highlight: Color::from_rgba(190, 150, 100, 255),
+                piece: WHITE,
+                selected: Color::from_rgba(230, 200, 150, 255),
+                text: Color::from_rgba(240, 225, 200, 255),
+            },
+            // Dark squares under bright pieces, and yellow for what can be clicked.
+            Self {
+                name: String::from("High contrast"),
+                background: BLACK,
+                square: Color::from_rgba(110, 110, 110, 255),
+                highlight: Color::from_rgba(255, 230, 0, 255),
+                piece: WHITE,
+                selected: Color::from_rgba(255, 230, 0, 255),
+                text: WHITE,
+            },
+        ];
+        let Ok(entries) = fs::read_dir(Path::new(THEMES_DIR).join("colors")) else { return schemes };
+        let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "txt"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            match fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| Self::parse(&name, &text)) {
+                Ok(scheme) => schemes.push(scheme),
+                Err(error) => println!("Could not load {}: {}", path.display(), error),
+            }
+        }
+        schemes
+    }
+
+    /// Parses a scheme file, starting from the Classic colours.
+    pub fn parse(name: &str, text: &str) -> Result<ColorScheme, String> {
+        let mut scheme = Self { name: name.to_string(), ..Self::classic() };
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            if key == "name" {
+                scheme.name = value.trim().to_string();
+                continue;
+            }
+            let color = Self::parse_color(value).ok_or(format!("'{}' is not a colour", line))?;
+            match key {
+                "background" => scheme.background = color,
+                "square" => scheme.square = color,
+                "highlight" => scheme.highlight = color,
+                "piece" => scheme.piece = color,
+                "selected" => scheme.selected = color,
+                "text" => scheme.text = color,
+                _ => return Err(format!("unknown key '{}'", key)),
+            }
+        }
+        Ok(scheme)
+    }
+
+    fn parse_color(value: &str) -> Option<Color> {
+        let parts: Vec<u8> = value.split_whitespace().map(|part| part.parse().ok()).collect::<Option<_>>()?;
+        match parts[..] {
+            [r, g, b] => Some(Color::from_rgba(r, g, b, 255)),
+            _ => None,
+        }
+    }
+}
+
+/// The piece set and colour scheme chosen, as indices into their available lists.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ThemeChoice {
+    pub piece_set: usize,
+    pub color_scheme: usize,
+}
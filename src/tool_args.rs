@@ -0,0 +1,60 @@
+// Tool Args
+// Minimal "--name value" command-line parsing for the headless tools in src/bin.
+// A "--name" that isn't followed by a value is a flag.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub struct ToolArgs {
+    values: HashMap<String, Option<String>>,
+    /// Arguments that don't belong to a --name.
+    pub positional: Vec<String>,
+}
+
+impl ToolArgs {
+    /// Parses the process arguments, skipping the program name.
+    pub fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1).collect())
+    }
+
+    pub fn parse(args: Vec<String>) -> Self {
+        let mut values = HashMap::new();
+        let mut positional = Vec::new();
+        let mut iter = args.into_iter().peekable();
+
+        while let Some(arg) = iter.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = match iter.peek() {
+                    Some(next) if !next.starts_with("--") => iter.next(),
+                    _ => None,
+                };
+                values.insert(name.to_string(), value);
+            } else {
+                positional.push(arg);
+            }
+        }
+        Self { values, positional }
+    }
+
+    /// Returns true if --name was given, with or without a value.
+    pub fn has(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    /// Returns the value of --name, if it was given one.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.values.get(name).and_then(|v| v.as_deref())
+    }
+
+    /// Returns the parsed value of --name, or the default if it wasn't given.
+    /// Exits with a message if the value can't be parsed.
+    pub fn get<T: FromStr>(&self, name: &str, default: T) -> T {
+        match self.get_str(name) {
+            Some(text) => text.parse().unwrap_or_else(|_| {
+                eprintln!("Invalid value for --{}: {}", name, text);
+                std::process::exit(2);
+            }),
+            None => default,
+        }
+    }
+}
@@ -0,0 +1,296 @@
+// Tournament
+// Headless games between engine configurations for the 'tournament' tool: our own AIs
+// at various settings and external USI engines. Also the win/draw/loss tally and the
+// Elo estimate reported for each pairing.
+
+use std::time::Duration;
+
+use crate::ai::Think;
+use crate::ai_minimax::AIMinimax;
+use crate::ai_monte_carlo::AIMonteCarlo;
+use crate::ai_monte_carlo_tree::AIMonteCarloTree;
+use crate::ai_random::AIRandom;
+use crate::ai_sender::AISender;
+use crate::evaluation::{EvaluatorKind, Evaluators};
+use crate::game::{Game, GameState};
+use crate::game_record::GameRecord;
+use crate::strength::Strength;
+use crate::usi::{UsiBestMove, UsiEngine};
+
+/// An engine configuration, as given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineSpec {
+    Minimax { depth: usize, network: bool },
+    Level(Strength),
+    /// Milliseconds per move.
+    MonteCarloTree { millis: u64 },
+    /// Random playouts per move.
+    MonteCarlo { rounds: usize },
+    Random,
+    Usi { command: String },
+}
+
+impl EngineSpec {
+    /// Parses "minimax:DEPTH", "minimax:DEPTH:network", "level:NAME", "mcts:MILLIS",
+    /// "montecarlo:ROUNDS", "random" or "usi:COMMAND".
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let (kind, rest) = text.split_once(':').unwrap_or((text, ""));
+        let number = |value: &str| value.parse::<u64>().map_err(|_| format!("bad number in '{}'", text));
+
+        match kind {
+            "minimax" => {
+                let (depth, option) = rest.split_once(':').unwrap_or((rest, ""));
+                let network = match option {
+                    "" => false,
+                    "network" => true,
+                    _ => return Err(format!("unknown minimax option in '{}'", text)),
+                };
+                Ok(EngineSpec::Minimax { depth: number(depth)?.max(1) as usize, network })
+            },
+            "level" => Strength::ALL.iter()
+                .find(|s| s.name().eq_ignore_ascii_case(rest))
+                .map(|s| EngineSpec::Level(*s))
+                .ok_or(format!("unknown level '{}'", rest)),
+            "mcts" => Ok(EngineSpec::MonteCarloTree { millis: number(rest)? }),
+            "montecarlo" => Ok(EngineSpec::MonteCarlo { rounds: number(rest)? as usize }),
+            "random" => Ok(EngineSpec::Random),
+            "usi" if !rest.is_empty() => Ok(EngineSpec::Usi { command: rest.to_string() }),
+            _ => Err(format!("unknown engine '{}'", text)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            EngineSpec::Minimax { depth, network: false } => format!("minimax:{}", depth),
+            EngineSpec::Minimax { depth, network: true } => format!("minimax:{}:network", depth),
+            EngineSpec::Level(strength) => format!("level:{}", strength.name()),
+            EngineSpec::MonteCarloTree { millis } => format!("mcts:{}", millis),
+            EngineSpec::MonteCarlo { rounds } => format!("montecarlo:{}", rounds),
+            EngineSpec::Random => "random".to_string(),
+            EngineSpec::Usi { command } => format!("usi:{}", command),
+        }
+    }
+}
+
+/// Settings shared by every game of a tournament.
+#[derive(Clone)]
+pub struct TournamentSettings {
+    /// Longer games are scored as draws.
+    pub max_plies: usize,
+    /// Options sent to every USI engine with setoption.
+    pub usi_options: Vec<(String, String)>,
+    /// The limits sent with USI "go", e.g. "movetime 500".
+    pub usi_go: String,
+    /// A USI engine that takes longer than this to move loses.
+    pub usi_timeout: Duration,
+}
+
+impl Default for TournamentSettings {
+    fn default() -> Self {
+        Self {
+            max_plies: 150,
+            usi_options: Vec::new(),
+            usi_go: "movetime 500".to_string(),
+            usi_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// One side of a game: an engine ready to move.
+pub struct Engine {
+    spec: EngineSpec,
+    evaluators: Evaluators,
+    usi: Option<UsiEngine>,
+    rng: fastrand::Rng,
+}
+
+impl Engine {
+    /// Creates the engine, starting the process for a USI engine.
+    pub fn new(spec: &EngineSpec, evaluators: &Evaluators, settings: &TournamentSettings, seed: u64) -> Result<Self, String> {
+        let usi = match spec {
+            EngineSpec::Usi { command } => Some(UsiEngine::start(command, &settings.usi_options)?),
+            _ => None,
+        };
+        Ok(Self {
+            spec: spec.clone(),
+            evaluators: evaluators.clone(),
+            usi,
+            rng: fastrand::Rng::with_seed(seed),
+        })
+    }
+
+    pub fn new_game(&mut self) -> Result<(), String> {
+        match &mut self.usi {
+            Some(usi) => usi.new_game(),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the engine's move in the game, whose moves so far are in record, or
+    /// None if it resigns.
+    pub fn choose(&mut self, record: &GameRecord, game: &Game, settings: &TournamentSettings) -> Result<Option<Game>, String> {
+        let sender = AISender::muted();
+        let progress = match &self.spec {
+            EngineSpec::Minimax { depth, network } => {
+                let kind = if *network { EvaluatorKind::Network } else { EvaluatorKind::Handcrafted };
+                AIMinimax::new(*game, *depth, self.evaluators.evaluator(kind), sender).think()
+            },
+            EngineSpec::Level(strength) => {
+                let kind = if self.evaluators.has_network() { EvaluatorKind::Network } else { EvaluatorKind::Handcrafted };
+                let mut ai = strength.minimax(*game, self.evaluators.evaluator(kind), sender);
+                strength.think(&mut ai, &mut self.rng)
+            },
+            EngineSpec::MonteCarloTree { millis } => {
+                AIMonteCarloTree::new(*game, Duration::from_millis(*millis), sender).think()
+            },
+            EngineSpec::MonteCarlo { rounds } => AIMonteCarlo::new(*game, *rounds, sender).think(),
+            EngineSpec::Random => AIRandom::new(*game, sender).think(),
+            EngineSpec::Usi { .. } => {
+                let usi = self.usi.as_mut().unwrap();
+                usi.set_position(&record.start, &record.moves)?;
+                return match usi.go(&settings.usi_go, settings.usi_timeout)? {
+                    UsiBestMove::Move(usi_move) => match game.child_for_usi(&usi_move) {
+                        Some(child) => Ok(Some(child)),
+                        None => Err(format!("{} played illegal move {}", usi.name, usi_move)),
+                    },
+                    UsiBestMove::Resign => Ok(None),
+                    // Mini Shogi has no impasse rule to claim a win by.
+                    UsiBestMove::Win => Err(format!("{} claimed a win", usi.name)),
+                };
+            },
+        };
+        Ok(progress.best_node)
+    }
+}
+
+/// A finished tournament game.
+pub struct MatchGame {
+    pub record: GameRecord,
+    pub result: GameState,
+    /// Set if an engine lost by failing, e.g. an illegal move or timeout.
+    pub error: Option<String>,
+}
+
+/// Plays a game from the opening, engines[0] as player 0.
+pub fn play_game(mut engines: [&mut Engine; 2], opening: &GameRecord, settings: &TournamentSettings) -> MatchGame {
+    let mut record = opening.clone();
+    let positions = match opening.positions() {
+        Ok(positions) => positions,
+        Err(error) => return MatchGame { record, result: GameState::Draw, error: Some(error) },
+    };
+    let mut game = *positions.last().unwrap();
+    let loss_for = |player: usize| if player == 0 { GameState::WinPlayer1 } else { GameState::WinPlayer0 };
+
+    for (player, engine) in engines.iter_mut().enumerate() {
+        if let Err(error) = engine.new_game() {
+            return MatchGame { record, result: loss_for(player), error: Some(error) };
+        }
+    }
+
+    let mut plies = positions.len() - 1;
+    while *game.update_state() == GameState::Ongoing {
+        if plies >= settings.max_plies {
+            record.result = Some(GameState::Draw);
+            return MatchGame { record, result: GameState::Draw, error: None };
+        }
+        let player = game.current_player;
+        match engines[player].choose(&record, &game, settings) {
+            Ok(Some(child)) => {
+                record.moves.push(game.usi_for_child(&child));
+                game = child;
+                plies += 1;
+            },
+            // Resigned, or no moves.
+            Ok(None) => {
+                record.result = Some(loss_for(player));
+                return MatchGame { record, result: loss_for(player), error: None };
+            },
+            Err(error) => {
+                record.result = Some(loss_for(player));
+                return MatchGame { record, result: loss_for(player), error: Some(error) };
+            },
+        }
+    }
+    record.result = Some(game.state);
+    MatchGame { record, result: game.state, error: None }
+}
+
+/// Makes opening records by playing random moves from the start position. The same
+/// seed always gives the same openings.
+pub fn random_openings(count: usize, plies: usize, seed: u64) -> Vec<GameRecord> {
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let mut start = Game::new();
+    start.prepare();
+
+    let mut openings = Vec::with_capacity(count);
+    while openings.len() < count {
+        let mut positions = vec![start];
+        let mut game = start;
+        for _ in 0..plies {
+            let mut children = game.child_nodes(game.current_player);
+            if children.is_empty() { break }
+            game = children.swap_remove(rng.usize(..children.len()));
+            positions.push(game);
+        }
+        // Skip openings that already lost a king, or hang one.
+        let mut last = game;
+        if *last.update_state() != GameState::Ongoing || last.is_in_check(1 - last.current_player) { continue }
+        openings.push(GameRecord::from_positions(&positions, None));
+    }
+    openings
+}
+
+/// Wins, draws and losses for one side of a pairing.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Score {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Score {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// 1 per win and 0.5 per draw.
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 * 0.5
+    }
+
+    pub fn add(&mut self, other: &Score) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+
+    /// The opposing side's score.
+    pub fn reversed(&self) -> Score {
+        Score { wins: self.losses, draws: self.draws, losses: self.wins }
+    }
+
+    /// The Elo difference implied by the score, and the half width of its 95%
+    /// confidence interval. None without games. A perfect score is clamped, since
+    /// its Elo difference would be infinite.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let games = self.games() as f64;
+        if games == 0. { return None }
+
+        let score = self.points() / games;
+        let deviation = ((self.wins as f64 * (1. - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2)) / games).sqrt();
+        let margin = 1.96 * deviation / games.sqrt();
+
+        let low = elo_for_score(score - margin);
+        let high = elo_for_score(score + margin);
+        Some((elo_for_score(score), (high - low) / 2.))
+    }
+}
+
+/// The Elo difference for an expected score between 0 and 1.
+pub fn elo_for_score(score: f64) -> f64 {
+    let score = score.clamp(0.001, 0.999);
+    -400. * (1. / score - 1.).log10()
+}
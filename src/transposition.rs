@@ -0,0 +1,167 @@
+// Transposition Table
+// Remembers search results by position key, so a position reached again, through another
+// move order, in the next iteration of iterative deepening or on a later turn, needn't
+// be searched again. Its best move is also searched first, which makes alpha-beta cut
+// off sooner.
+//
+// Entries are two atomics, with the key stored xor the data, so the table can be shared
+// between threads without locks: a torn write just reads as a miss.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::game::Move;
+
+/// The size of the tables used by the app, in megabytes.
+pub const DEFAULT_TABLE_MEGABYTES: usize = 16;
+
+/// What a stored score says about the true score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    Exact,
+    /// The true score is at least this (the search failed high).
+    Lower,
+    /// The true score is at most this (the search failed low).
+    Upper,
+}
+
+impl Bound {
+    /// The bound for the negated score.
+    pub fn flipped(&self) -> Bound {
+        match self {
+            Bound::Exact => Bound::Exact,
+            Bound::Lower => Bound::Upper,
+            Bound::Upper => Bound::Lower,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableEntry {
+    /// The depth searched below the position.
+    pub depth: u8,
+    pub bound: Bound,
+    /// For the player to move in the position.
+    pub score: f32,
+    pub best_move: Option<Move>,
+}
+
+pub struct TranspositionTable {
+    /// (key ^ data, data) pairs.
+    entries: Vec<(AtomicU64, AtomicU64)>,
+    mask: usize,
+}
+
+impl TranspositionTable {
+    /// Creates a table of about the given size, rounded down to a power of two entries.
+    pub fn new(megabytes: usize) -> Self {
+        let wanted = (megabytes.max(1) << 20) / std::mem::size_of::<(AtomicU64, AtomicU64)>();
+        let count = 1usize << (usize::BITS - 1 - wanted.leading_zeros());
+        Self {
+            entries: (0..count).map(|_| (AtomicU64::new(0), AtomicU64::new(0))).collect(),
+            mask: count - 1,
+        }
+    }
+
+    pub fn clear(&self) {
+        for (check, data) in &self.entries {
+            check.store(0, Ordering::Relaxed);
+            data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TableEntry> {
+        let (check, data) = &self.entries[key as usize & self.mask];
+        let data = data.load(Ordering::Relaxed);
+        if data == 0 || check.load(Ordering::Relaxed) ^ data != key { return None }
+        Some(Self::unpack(data))
+    }
+
+    /// Stores the entry, unless the slot holds a deeper search of the same position.
+    pub fn store(&self, key: u64, entry: TableEntry) {
+        let (check, data) = &self.entries[key as usize & self.mask];
+        let old = data.load(Ordering::Relaxed);
+        if old != 0 && check.load(Ordering::Relaxed) ^ old == key && Self::unpack(old).depth > entry.depth {
+            return;
+        }
+        let new = Self::pack(&entry);
+        check.store(key ^ new, Ordering::Relaxed);
+        data.store(new, Ordering::Relaxed);
+    }
+
+    /// Bits: score 0-31, depth 32-39, bound 40-41, move 42-52. Never 0, since the bound
+    /// is stored plus one.
+    fn pack(entry: &TableEntry) -> u64 {
+        let bound = match entry.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        let the_move = match entry.best_move {
+            // Piece ids are under 16 and location indexes under 32.
+            Some(Move(id, to_index, capture)) => 1 | (id as u64) << 1 | (to_index as u64) << 5 | (capture as u64) << 10,
+            None => 0,
+        };
+        entry.score.to_bits() as u64 | (entry.depth as u64) << 32 | bound << 40 | the_move << 42
+    }
+
+    fn unpack(data: u64) -> TableEntry {
+        let bound = match (data >> 40) & 3 {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let the_move = (data >> 42) & 0x7ff;
+        let best_move = if the_move & 1 == 0 { None } else {
+            Some(Move(((the_move >> 1) & 0xf) as usize, ((the_move >> 5) & 0x1f) as usize, the_move >> 10 & 1 == 1))
+        };
+        TableEntry {
+            depth: (data >> 32) as u8,
+            bound,
+            score: f32::from_bits(data as u32),
+            best_move,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_and_unpack_round_trip() {
+        let moves = [None, Some(Move(0, 0, false)), Some(Move(11, 24, true)), Some(Move(7, 13, false))];
+        let scores = [0., -0.5, 3.25, -1234.5, f32::MAX, -f32::MAX, f32::INFINITY, f32::NEG_INFINITY];
+        for bound in [Bound::Exact, Bound::Lower, Bound::Upper] {
+            for depth in [0, 1, 37, u8::MAX] {
+                for score in scores {
+                    for best_move in moves {
+                        let entry = TableEntry { depth, bound, score, best_move };
+                        let data = TranspositionTable::pack(&entry);
+                        assert_ne!(data, 0);
+                        assert_eq!(TranspositionTable::unpack(data), entry);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn store_keeps_the_deeper_search() {
+        let table = TranspositionTable::new(1);
+        let key = 0x1234_5678_9abc_def0;
+        let deep = TableEntry { depth: u8::MAX, bound: Bound::Lower, score: -2.5, best_move: Some(Move(3, 9, true)) };
+        let shallow = TableEntry { depth: 2, bound: Bound::Exact, score: 1., best_move: None };
+
+        assert_eq!(table.probe(key), None);
+        table.store(key, deep);
+        table.store(key, shallow);
+        assert_eq!(table.probe(key), Some(deep));
+        // Another key in the same slot replaces it.
+        let other = key ^ (1 << 63);
+        table.store(other, shallow);
+        assert_eq!(table.probe(other), Some(shallow));
+        assert_eq!(table.probe(key), None);
+        table.clear();
+        assert_eq!(table.probe(other), None);
+    }
+}
@@ -0,0 +1,131 @@
+// Tuner
+// Texel-style tuning of the evaluation weights. Quiet positions from self-play games are
+// labeled with the game result (1, 0.5 or 0 for player 0). The evaluation, squashed by a
+// sigmoid, is treated as a predicted result and the weights are fitted by minimizing the
+// mean squared error with gradient descent (Adam). Nothing is random, so the same
+// positions always give the same weights.
+
+use crate::evaluation::{EvalWeights, WEIGHT_COUNT};
+use crate::self_play::{is_quiet, result_value, SelfPlayGame};
+
+/// A labeled position, stored as its non-zero features.
+pub struct TrainingPosition {
+    /// (weight index, value) pairs, from player 0's point of view.
+    pub features: Vec<(usize, f64)>,
+    /// The game result for player 0.
+    pub result: f64,
+}
+
+pub struct Tuner {
+    pub positions: Vec<TrainingPosition>,
+    /// Scales evaluation units to the sigmoid. Set by fit_scale().
+    pub scale: f64,
+    weights: Vec<f64>,
+}
+
+impl Tuner {
+    pub fn new(start_weights: &EvalWeights) -> Self {
+        Self {
+            positions: Vec::new(),
+            scale: 1.0,
+            weights: start_weights.to_vec(),
+        }
+    }
+
+    /// Adds the quiet positions of a game, skipping the first skip_plies positions.
+    pub fn add_game(&mut self, game: &SelfPlayGame, skip_plies: usize) {
+        let result = result_value(game.result);
+        for position in game.positions.iter().skip(skip_plies) {
+            if !is_quiet(position) { continue }
+
+            let features = EvalWeights::features(position, 0)
+                .into_iter()
+                .enumerate()
+                .filter(|(_, v)| *v != 0.)
+                .collect();
+            self.positions.push(TrainingPosition { features, result });
+        }
+    }
+
+    pub fn weights(&self) -> EvalWeights {
+        EvalWeights::from_slice(&self.weights)
+    }
+
+    fn sigmoid(x: f64) -> f64 {
+        1.0 / (1.0 + (-x).exp())
+    }
+
+    fn evaluate(weights: &[f64], position: &TrainingPosition) -> f64 {
+        position.features.iter().map(|(i, v)| weights[*i] * v).sum()
+    }
+
+    /// The mean squared error between predicted and actual results.
+    pub fn error(&self, scale: f64) -> f64 {
+        if self.positions.is_empty() { return 0. }
+        let total: f64 = self.positions.iter()
+            .map(|p| {
+                let predicted = Self::sigmoid(scale * Self::evaluate(&self.weights, p));
+                (p.result - predicted).powi(2)
+            })
+            .sum();
+        total / self.positions.len() as f64
+    }
+
+    /// Finds the sigmoid scale that best fits the current weights, by a coarse scan
+    /// followed by refinement. This is done once, before tuning the weights.
+    pub fn fit_scale(&mut self) -> f64 {
+        let mut best = self.scale;
+        let mut step = 0.1;
+        for _ in 0..4 {
+            let start = (best - step * 10.).max(step);
+            let mut best_error = f64::MAX;
+            for i in 0..=20 {
+                let scale = start + step * i as f64;
+                let error = self.error(scale);
+                if error < best_error {
+                    best_error = error;
+                    best = scale;
+                }
+            }
+            step /= 10.;
+        }
+        self.scale = best;
+        best
+    }
+
+    /// Runs the given number of full-batch gradient descent steps. Calls report with the
+    /// iteration number and error every report_every iterations.
+    pub fn tune(&mut self, iterations: usize, learning_rate: f64, report_every: usize, mut report: impl FnMut(usize, f64)) {
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+        const EPSILON: f64 = 1e-8;
+
+        let mut m = vec![0.; WEIGHT_COUNT];
+        let mut v = vec![0.; WEIGHT_COUNT];
+        let count = self.positions.len().max(1) as f64;
+
+        for iteration in 1..=iterations {
+            let mut gradient = vec![0.; WEIGHT_COUNT];
+            for position in &self.positions {
+                let predicted = Self::sigmoid(self.scale * Self::evaluate(&self.weights, position));
+                // d/dw of (result - predicted)^2
+                let common = -2. * (position.result - predicted) * predicted * (1. - predicted) * self.scale;
+                for (i, value) in &position.features {
+                    gradient[*i] += common * value / count;
+                }
+            }
+
+            for i in 0..WEIGHT_COUNT {
+                m[i] = BETA1 * m[i] + (1. - BETA1) * gradient[i];
+                v[i] = BETA2 * v[i] + (1. - BETA2) * gradient[i] * gradient[i];
+                let m_hat = m[i] / (1. - BETA1.powi(iteration as i32));
+                let v_hat = v[i] / (1. - BETA2.powi(iteration as i32));
+                self.weights[i] -= learning_rate * m_hat / (v_hat.sqrt() + EPSILON);
+            }
+
+            if report_every > 0 && (iteration % report_every == 0 || iteration == iterations) {
+                report(iteration, self.error(self.scale));
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+// Tutorial
+// Lessons that teach how the pieces move, one step at a time. Lessons are plain text
+// like puzzles, one "key value" line per field and a blank line between lessons:
+//
+//   title The rook
+//   sfen 5/5/2R2/5/5 b - 1
+//   show 3c
+//   text The rook moves any number of empty squares up, down, left or right.
+//   step 3c5c | Slide the rook all the way to the left edge.
+//   wrong The rook slides in straight lines only.
+//   done The rook is the strongest piece that hasn't promoted.
+//
+// 'show' names the square of the piece whose moves are highlighted at the start, or
+// "P*" for a pawn in hand. Each 'step' lists the moves it accepts in USI notation, then
+// what to ask for; a move like "P*" accepts any drop of the piece. 'text', 'wrong' and
+// 'done' may take several lines, which are joined. The lessons are assets/tutorial.txt,
+// compiled in.
+
+use crate::game::{Game, GameLocation, NONE};
+
+const LESSONS: &str = include_str!("../assets/tutorial.txt");
+const DEFAULT_WRONG: &str = "That isn't the move asked for. Try again.";
+
+#[derive(Debug, Clone, Default)]
+pub struct Step {
+    /// The moves that complete the step, in USI notation.
+    pub moves: Vec<String>,
+    pub prompt: String,
+}
+
+impl Step {
+    pub fn accepts(&self, usi: &str) -> bool {
+        self.moves.iter().any(|the_move| {
+            the_move == usi || (the_move.ends_with('*') && usi.starts_with(the_move.as_str()))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Lesson {
+    pub title: String,
+    /// SFEN of the position, with the learner to move.
+    pub sfen: String,
+    /// The square, or "P*" style drop, of the piece shown first.
+    pub show: Option<String>,
+    pub text: String,
+    pub steps: Vec<Step>,
+    /// The feedback for a move that isn't asked for.
+    pub wrong: String,
+    /// Said once every step is made.
+    pub done: String,
+}
+
+impl Lesson {
+    /// The lessons that come with the app.
+    pub fn builtin() -> Vec<Lesson> {
+        Self::parse_all(LESSONS)
+    }
+
+    /// Parses every lesson in the text.
+    pub fn parse_all(text: &str) -> Vec<Lesson> {
+        let mut lessons = Vec::new();
+        let mut current: Option<Lesson> = None;
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with('#') { continue }
+            if line.is_empty() {
+                lessons.extend(current.take());
+                continue;
+            }
+            let lesson = current.get_or_insert_with(Lesson::default);
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match key {
+                "title" => lesson.title = value.to_string(),
+                "sfen" => lesson.sfen = value.to_string(),
+                "show" => lesson.show = Some(value.to_string()),
+                "text" => Self::join(&mut lesson.text, value),
+                "wrong" => Self::join(&mut lesson.wrong, value),
+                "done" => Self::join(&mut lesson.done, value),
+                "step" => {
+                    let (moves, prompt) = value.split_once('|').unwrap_or((value, ""));
+                    lesson.steps.push(Step {
+                        moves: moves.split_whitespace().map(String::from).collect(),
+                        prompt: prompt.trim().to_string(),
+                    });
+                },
+                _ => {},
+            }
+        }
+        lessons.extend(current);
+        lessons
+    }
+
+    fn join(text: &mut String, line: &str) {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        text.push_str(line);
+    }
+
+    pub fn start(&self) -> Result<Game, String> {
+        Game::from_sfen(&self.sfen)
+    }
+
+    pub fn wrong_text(&self) -> &str {
+        if self.wrong.is_empty() { DEFAULT_WRONG } else { &self.wrong }
+    }
+
+    /// The id of the piece whose moves are shown first: the one on the 'show' square, or
+    /// one of the kind in the hand of the player to move.
+    pub fn shown_piece(&self, game: &Game) -> Option<usize> {
+        let show = self.show.as_deref()?;
+        if let Some(letter) = show.strip_suffix('*') {
+            return game.pieces.iter()
+                .find(|piece| piece.location == GameLocation::Reserve && piece.player == game.current_player
+                    && piece.string_rep() == letter)
+                .map(|piece| piece.id);
+        }
+        let index = Game::index_for_usi_square(show)?;
+        Some(game.grid[index]).filter(|id| *id != NONE)
+    }
+}
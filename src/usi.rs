@@ -0,0 +1,430 @@
+// USI
+// The Universal Shogi Interface, the text protocol shogi engines speak over stdin and
+// stdout. UsiEngine runs an external engine (for example Fairy-Stockfish with
+// "UCI_Variant" set to "minishogi") so it can play against our AIs. UsiServer is the
+// other side: it answers the protocol with our Minimax AI, for the 'usi_engine' tool.
+//
+// Squares and moves use the same notation as Game::usi_square() and
+// Game::usi_for_child(), and positions are sent as SFEN plus the moves since.
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::ai::{AIProgress, Think};
+use crate::ai_minimax::{AIMinimax, WIN_LOSS_VAL};
+use crate::ai_sender::AISender;
+use crate::clock::move_budget;
+use crate::evaluation::{EvaluatorKind, Evaluators};
+use crate::game::{Game, GameState, Move};
+use crate::opening_book::{OpeningBook, OPENING_BOOK_FILE};
+use crate::tablebase::{Tablebases, TABLEBASE_DIR};
+use crate::transposition::{TranspositionTable, DEFAULT_TABLE_MEGABYTES};
+
+/// How long to wait for an engine to start up or get ready.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What an engine answered to "go".
+#[derive(Debug, Clone, PartialEq)]
+pub enum UsiBestMove {
+    Move(String),
+    Resign,
+    /// The engine claims a win (for instance by the impasse rule).
+    Win,
+}
+
+pub struct UsiEngine {
+    /// The name the engine reported, or its command if it didn't.
+    pub name: String,
+    process: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    /// Print everything sent and received.
+    pub debug: bool,
+}
+
+impl UsiEngine {
+    /// Starts the engine, which is a command line like "fairy-stockfish" or
+    /// "/path/engine --flag", and sets its options.
+    pub fn start(command: &str, options: &[(String, String)]) -> Result<Self, String> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("empty engine command")?;
+        let mut process = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("{}: {}", program, e))?;
+
+        let stdin = process.stdin.take().ok_or("no engine stdin")?;
+        let stdout = process.stdout.take().ok_or("no engine stdout")?;
+
+        // Read on a thread so waits can time out.
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() { break }
+            }
+        });
+
+        let mut engine = Self {
+            name: command.to_string(),
+            process, stdin, lines,
+            debug: false,
+        };
+        engine.send("usi")?;
+        loop {
+            let line = engine.read_line(STARTUP_TIMEOUT)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            }
+            if line.trim() == "usiok" { break }
+        }
+        for (name, value) in options {
+            engine.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        engine.wait_ready()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, line: &str) -> Result<(), String> {
+        if self.debug {
+            println!("> {}", line);
+        }
+        writeln!(self.stdin, "{}", line).and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("{}: {}", self.name, e))
+    }
+
+    fn read_line(&mut self, timeout: Duration) -> Result<String, String> {
+        let line = self.lines.recv_timeout(timeout)
+            .map_err(|_| format!("{}: no response", self.name))?;
+        if self.debug {
+            println!("< {}", line);
+        }
+        Ok(line)
+    }
+
+    pub fn wait_ready(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        while self.read_line(STARTUP_TIMEOUT)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    /// Tells the engine a new game is starting.
+    pub fn new_game(&mut self) -> Result<(), String> {
+        self.wait_ready()?;
+        self.send("usinewgame")
+    }
+
+    /// Sends the position, starting from the SFEN and followed by the USI moves.
+    pub fn set_position(&mut self, start_sfen: &str, moves: &[String]) -> Result<(), String> {
+        let mut command = format!("position sfen {}", start_sfen);
+        if !moves.is_empty() {
+            command.push_str(" moves ");
+            command.push_str(&moves.join(" "));
+        }
+        self.send(&command)
+    }
+
+    /// Sends "go" with the given limits, such as "movetime 1000" or "depth 5", and
+    /// waits up to timeout for the best move. Info lines don't extend the wait.
+    pub fn go(&mut self, limits: &str, timeout: Duration) -> Result<UsiBestMove, String> {
+        self.send(format!("go {}", limits).trim())?;
+        let start = Instant::now();
+        loop {
+            let line = self.read_line(timeout.saturating_sub(start.elapsed()))?;
+            let mut words = line.split_whitespace();
+            if words.next() != Some("bestmove") { continue }
+            return match words.next() {
+                Some("resign") => Ok(UsiBestMove::Resign),
+                Some("win") => Ok(UsiBestMove::Win),
+                Some(usi) => Ok(UsiBestMove::Move(usi.to_string())),
+                None => Err(format!("{}: empty bestmove", self.name)),
+            };
+        }
+    }
+}
+
+impl Drop for UsiEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        // Give the engine a moment to exit on its own.
+        for _ in 0..10 {
+            if let Ok(Some(_)) = self.process.try_wait() { return }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+/// The deepest UsiServer searches, unless set lower with the Depth option.
+const SERVER_MAX_DEPTH: usize = 30;
+
+/// A USI engine backed by AIMinimax. Searches run on their own thread, so "stop" and
+/// "ponderhit" are handled while one is going.
+pub struct UsiServer {
+    game: Game,
+    evaluators: Evaluators,
+    book: Option<Arc<OpeningBook>>,
+    tablebases: Option<Arc<Tablebases>>,
+    transposition_table: Arc<TranspositionTable>,
+    search: Option<ServerSearch>,
+
+    // Options
+    /// USI_Ponder: also give a move to ponder on with bestmove.
+    ponder: bool,
+    own_book: bool,
+    max_depth: usize,
+    /// For "go" without a time limit.
+    default_move_time: Duration,
+}
+
+struct ServerSearch {
+    stop: Arc<AtomicBool>,
+    pondering: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl Default for UsiServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsiServer {
+    pub fn new() -> Self {
+        let book = OpeningBook::load_or_empty(OPENING_BOOK_FILE);
+        Self {
+            game: Self::start_position(),
+            evaluators: Evaluators::load(),
+            book: if book.is_empty() { None } else { Some(Arc::new(book)) },
+            tablebases: Tablebases::load_dir(TABLEBASE_DIR).map(Arc::new),
+            transposition_table: Arc::new(TranspositionTable::new(DEFAULT_TABLE_MEGABYTES)),
+            search: None,
+            ponder: false,
+            own_book: true,
+            max_depth: SERVER_MAX_DEPTH,
+            default_move_time: Duration::from_millis(1000),
+        }
+    }
+
+    fn start_position() -> Game {
+        let mut game = Game::new();
+        game.prepare();
+        game
+    }
+
+    /// Answers commands from stdin until "quit" or the end of input.
+    pub fn run(&mut self) {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if !self.command(&line) { break }
+        }
+        self.stop();
+    }
+
+    /// Handles one command line. Returns false on "quit".
+    pub fn command(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("usi") => {
+                println!("id name Mini Shogi");
+                println!("id author the Mini Shogi authors");
+                println!("option name USI_Ponder type check default false");
+                println!("option name USI_Hash type spin default {} min 1 max 4096", DEFAULT_TABLE_MEGABYTES);
+                println!("option name OwnBook type check default true");
+                println!("option name Depth type spin default {} min 1 max {}", SERVER_MAX_DEPTH, SERVER_MAX_DEPTH);
+                println!("option name MoveTime type spin default 1000 min 10 max 600000");
+                println!("usiok");
+            },
+            Some("isready") => println!("readyok"),
+            Some("setoption") => self.set_option(&words.collect::<Vec<_>>()),
+            Some("usinewgame") => {
+                self.stop();
+                self.transposition_table.clear();
+            },
+            Some("position") => {
+                self.stop();
+                if let Err(error) = self.set_position(&words.collect::<Vec<_>>()) {
+                    println!("info string {}", error);
+                }
+            },
+            Some("go") => {
+                self.stop();
+                self.go(&words.collect::<Vec<_>>());
+            },
+            Some("ponderhit") => {
+                if let Some(search) = &self.search {
+                    search.pondering.store(false, Ordering::Relaxed);
+                }
+            },
+            Some("stop") => self.stop(),
+            Some("quit") => return false,
+            // "gameover" and anything unknown need no answer.
+            _ => {},
+        }
+        true
+    }
+
+    /// "setoption name NAME value VALUE"
+    fn set_option(&mut self, words: &[&str]) {
+        let value_at = words.iter().position(|w| *w == "value");
+        let name = words.get(1..value_at.unwrap_or(words.len())).unwrap_or_default().join(" ");
+        let value = value_at.map(|i| words[i + 1..].join(" ")).unwrap_or_default();
+        let number = value.parse::<u64>().ok();
+        match name.as_str() {
+            "USI_Ponder" => self.ponder = value == "true",
+            "USI_Hash" => if let Some(megabytes) = number {
+                self.transposition_table = Arc::new(TranspositionTable::new(megabytes as usize));
+            },
+            "OwnBook" => self.own_book = value == "true",
+            "Depth" => if let Some(depth) = number {
+                self.max_depth = (depth as usize).clamp(1, SERVER_MAX_DEPTH);
+            },
+            "MoveTime" => if let Some(ms) = number {
+                self.default_move_time = Duration::from_millis(ms);
+            },
+            _ => println!("info string unknown option {}", name),
+        }
+    }
+
+    /// "position startpos|sfen SFEN [moves MOVE...]"
+    fn set_position(&mut self, words: &[&str]) -> Result<(), String> {
+        let moves_at = words.iter().position(|w| *w == "moves").unwrap_or(words.len());
+        let mut game = match words.first() {
+            Some(&"startpos") => Self::start_position(),
+            Some(&"sfen") => Game::from_sfen(&words[1..moves_at].join(" "))?,
+            _ => return Err("bad position command".to_string()),
+        };
+        for usi in words.iter().skip(moves_at + 1) {
+            game = game.child_for_usi(usi).ok_or(format!("illegal move {}", usi))?;
+        }
+        self.game = game;
+        Ok(())
+    }
+
+    /// "go [ponder] [infinite] [movetime MS] [depth N] [btime MS] [wtime MS] [binc MS]
+    /// [winc MS] [byoyomi MS]". With the clock times, the time manager decides how long
+    /// to think; without any limit, the MoveTime option is used.
+    fn go(&mut self, words: &[&str]) {
+        let mut ponder = false;
+        let mut infinite = false;
+        let mut move_time = None;
+        let mut depth = self.max_depth;
+        // Per player: the time left and the increment.
+        let mut time = [None; 2];
+        let mut increment = [Duration::ZERO; 2];
+        let mut byoyomi = Duration::ZERO;
+        let mut i = 0;
+        while i < words.len() {
+            let number = words.get(i + 1).and_then(|w| w.parse::<u64>().ok());
+            match (words[i], number) {
+                ("ponder", _) => ponder = true,
+                ("infinite", _) => infinite = true,
+                ("movetime", Some(ms)) => { move_time = Some(Duration::from_millis(ms)); i += 1 },
+                ("depth", Some(n)) => { depth = (n as usize).clamp(1, SERVER_MAX_DEPTH); i += 1 },
+                ("btime", Some(ms)) => { time[0] = Some(Duration::from_millis(ms)); i += 1 },
+                ("wtime", Some(ms)) => { time[1] = Some(Duration::from_millis(ms)); i += 1 },
+                ("binc", Some(ms)) => { increment[0] = Duration::from_millis(ms); i += 1 },
+                ("winc", Some(ms)) => { increment[1] = Duration::from_millis(ms); i += 1 },
+                ("byoyomi", Some(ms)) => { byoyomi = Duration::from_millis(ms); i += 1 },
+                _ => {},
+            }
+            i += 1;
+        }
+        let p = self.game.current_player;
+        let move_time = move_time
+            .or(time[p].map(|main| move_budget(main, increment[p], byoyomi)))
+            .unwrap_or(self.default_move_time);
+
+        // These variables are captured by the thread.
+        let stop = Arc::new(AtomicBool::new(false));
+        let pondering = Arc::new(AtomicBool::new(ponder));
+        let (stop_clone, pondering_clone) = (stop.clone(), pondering.clone());
+        let game = self.game;
+        let kind = if self.evaluators.has_network() { EvaluatorKind::Network } else { EvaluatorKind::Handcrafted };
+        let mut ai = AIMinimax::new(game, depth, self.evaluators.evaluator(kind), AISender::muted());
+        ai.tablebases = self.tablebases.clone();
+        ai.transposition_table = Some(self.transposition_table.clone());
+        ai.stop_signal = Some(stop.clone());
+        ai.pondering = Some(pondering.clone());
+        ai.time_limit = Some(if infinite { Duration::MAX } else { move_time });
+        let book = if self.own_book { self.book.clone() } else { None };
+        let give_ponder_move = self.ponder;
+
+        let thread = std::thread::spawn(move || {
+            let book_node = book.and_then(|book| book.choose(&game, &mut fastrand::Rng::new()));
+            let progress = match book_node {
+                Some(node) => {
+                    let mut progress = AIProgress::new();
+                    progress.pv = node.last_move.into_iter().collect();
+                    progress.best_node = Some(node);
+                    progress
+                },
+                None => ai.think(),
+            };
+            // The protocol allows bestmove only once pondering or an infinite search is
+            // over.
+            while (pondering_clone.load(Ordering::Relaxed) || infinite) && !stop_clone.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Self::report(&game, &progress, give_ponder_move);
+        });
+        self.search = Some(ServerSearch { stop, pondering, thread });
+    }
+
+    /// Stops any search, which then reports its best move.
+    fn stop(&mut self) {
+        let Some(search) = self.search.take() else { return };
+        search.stop.store(true, Ordering::Relaxed);
+        let _ = search.thread.join();
+    }
+
+    fn report(game: &Game, progress: &AIProgress, give_ponder_move: bool) {
+        let pv = Self::usi_moves(game, &progress.pv);
+        let score = if progress.score.abs() >= WIN_LOSS_VAL / 2.0 {
+            let plies = (WIN_LOSS_VAL - progress.score.abs()).round() as i64;
+            format!("mate {}", if progress.score > 0. { plies } else { -plies })
+        } else {
+            format!("cp {}", (progress.score * 100.).round() as i64)
+        };
+        println!("info depth {} score {} nodes {} time {} pv {}",
+            progress.depth, score, progress.nodes, progress.duration.as_millis(), pv.join(" "));
+
+        // The search always finishes a move, but resign only when there is none to play.
+        let mut position = *game;
+        let best_node = progress.best_node.or_else(|| match *position.update_state() {
+            GameState::Ongoing => position.child_nodes(position.current_player).first().copied(),
+            _ => None,
+        });
+        match best_node {
+            Some(node) => {
+                let best = game.usi_for_child(&node);
+                match pv.get(1).filter(|_| give_ponder_move && pv[0] == best) {
+                    Some(ponder) => println!("bestmove {} ponder {}", best, ponder),
+                    None => println!("bestmove {}", best),
+                }
+            },
+            None => println!("bestmove resign"),
+        }
+    }
+
+    /// The moves in USI notation, as far as they are legal from the game.
+    fn usi_moves(game: &Game, moves: &[Move]) -> Vec<String> {
+        let mut node = *game;
+        let mut usi_moves = Vec::new();
+        for the_move in moves {
+            let Some(child) = node.child_for_move(*the_move) else { break };
+            usi_moves.push(node.usi_for_child(&child));
+            node = child;
+        }
+        usi_moves
+    }
+}
@@ -160,6 +160,10 @@ impl Button {
                     },
                 }
             },
+            // Buttons aren't draggable.
+            Event::DragStarted(_) | Event::Dragged(_) | Event::DragEnded(_) => None,
+            // Nor do they answer the right button.
+            Event::RightMousePressed | Event::RightMouseReleased => None,
         }
     }
 
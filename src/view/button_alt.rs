@@ -119,6 +119,10 @@ impl ButtonAlt {
                     },
                 }
             },
+            // Buttons aren't draggable.
+            Event::DragStarted(_) | Event::Dragged(_) | Event::DragEnded(_) => None,
+            // Nor do they answer the right button.
+            Event::RightMousePressed | Event::RightMouseReleased => None,
         }
     }
 
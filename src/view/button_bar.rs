@@ -71,6 +71,10 @@ impl ButtonBar {
         id
     }
 
+    pub fn button_for_id(&mut self, id: usize) -> Option<&mut Button> {
+        self.buttons.iter_mut().find(|b| b.id == Some(id))
+    }
+
     pub fn select_only(&mut self, id: usize) {
         for button in &mut self.buttons {
             button.set_selected(id == button.id.unwrap());
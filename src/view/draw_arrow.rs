@@ -0,0 +1,45 @@
+// DrawArrow
+
+use macroquad::prelude::*;
+
+/// A straight arrow with a triangular head, drawn between two points given at draw time.
+pub struct DrawArrow {
+    pub visible: bool,
+    pub color: Color,
+    pub thickness: f32, // of the shaft
+    pub head_size: f32, // the length and width of the head
+}
+
+impl DrawArrow {
+    pub fn new(color: Color, thickness: f32, head_size: f32) -> Self {
+        Self {
+            visible: true,
+            color,
+            thickness,
+            head_size,
+        }
+    }
+
+    /// Draws the arrow from 'from' to the tip of its head at 'to'. An arrow shorter than
+    /// its head is drawn as the head alone.
+    pub fn draw(&self, from: (f32, f32), to: (f32, f32), color: Option<Color>) {
+        if !self.visible { return }
+
+        let from = Vec2::new(from.0, from.1);
+        let tip = Vec2::new(to.0, to.1);
+        let length = from.distance(tip);
+        if length == 0. { return }
+
+        let direction = (tip - from) / length;
+        let normal = direction.perp() * self.head_size / 2.;
+        let head_length = self.head_size.min(length);
+        let base = tip - direction * head_length;
+        let color = color.unwrap_or(self.color);
+
+        // The shaft stops at the head, so translucent colors don't overlap.
+        if length > head_length {
+            draw_line(from.x, from.y, base.x, base.y, self.thickness, color);
+        }
+        draw_triangle(tip, base + normal, base - normal, color);
+    }
+}
@@ -3,6 +3,7 @@
 use macroquad::prelude::*;
 
 use crate::asset_loader::AssetLoader;
+use crate::view::layout_scale;
 use crate::view::transform::Transform;
 
 pub struct DrawText {
@@ -76,10 +77,13 @@ impl DrawText {
 
         let color = color.unwrap_or(self.color);
 
+        // Render the glyphs at the size they appear in the window, so scaled text stays sharp.
+        let scale = layout_scale();
+        let font_size = (self.font_size as f32 * scale).round().max(1.) as u16;
         let params = TextParams {
             font,
-            font_size: self.font_size,
-            font_scale: self.font_scale,
+            font_size,
+            font_scale: self.font_scale * self.font_size as f32 / font_size as f32,
             font_scale_aspect: 1.0,
             rotation: 0.0,
             color,
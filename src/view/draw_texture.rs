@@ -34,7 +34,6 @@ impl DrawTexture {
         sprite
     }
 
-    #[allow(dead_code)]
     pub fn set_texture(&mut self, texture: Texture2D) {
         self.texture = texture.clone();
         self.size = (texture.width() / TEXTURE_SCALE, texture.height() / TEXTURE_SCALE);
@@ -2,24 +2,44 @@
 
 use macroquad::prelude::*;
 
+use crate::view::layout_mouse_position;
 use crate::view::transform::Transform;
 use crate::view::draw_texture::DrawTexture;
 
+/// How far the mouse must move with the left button down, in logical pixels, before a
+/// press becomes a drag. Shorter moves are still clicks.
+const DRAG_THRESHOLD: f32 = 4.0;
+
 pub enum Event {
     MouseEntered,
     MouseExited,
     LeftMousePressed,
     LeftMouseReleased,
-    // Right
-    // DragStarted
+    RightMousePressed,
+    RightMouseReleased,
+    /// The mouse position where the left button went down.
+    DragStarted((f32, f32)),
+    /// Sent every frame of a drag, with the mouse position.
+    Dragged((f32, f32)),
+    /// The left button was let go, at the mouse position. No LeftMouseReleased follows.
+    DragEnded((f32, f32)),
 }
 
 pub struct Eventable {
     pub enabled: bool,
     pub mouse_over: bool,
     pub left_mouse_down: bool,
-    // right_
-    // dragging: bool, drag_start_pos, drag_pos_now
+    pub right_mouse_down: bool,
+    /// Whether a press may become a drag. Off by default, so buttons only click.
+    pub draggable: bool,
+    pub dragging: bool,
+    drag_start_pos: Option<(f32, f32)>, // set while the left button is down after a press here
+}
+
+impl Default for Eventable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Eventable {
@@ -28,11 +48,15 @@ impl Eventable {
             enabled: true,
             mouse_over: false,
             left_mouse_down: false,
+            right_mouse_down: false,
+            draggable: false,
+            dragging: false,
+            drag_start_pos: None,
         }
     }
 
     /// Test whether the point lies in the texture rectangle, considering rotation.
-    /// Note: Macroquad's mouse_position() gives the physical location of the mouse.
+    /// Note: the point is in layout coordinates, as from layout_mouse_position().
     pub fn contains_point(&self, point: (f32, f32), transform: &Transform, draw: &DrawTexture) -> bool {
         let (x, y, rot) = transform.combined_x_y_rot();
 
@@ -56,7 +80,16 @@ impl Eventable {
     pub fn process_events(&mut self, transform: &Transform, draw: &DrawTexture) -> Option<Event> {
         if !self.enabled { return None }
 
-        let mouse_over = self.contains_point(mouse_position(), transform, draw);
+        let mouse_over = self.contains_point(layout_mouse_position(), transform, draw);
+
+        if self.draggable {
+            if mouse_over && is_mouse_button_pressed(MouseButton::Left) {
+                self.drag_start_pos = Some(layout_mouse_position());
+            }
+            if let Some(event) = self.process_drag() {
+                return Some(event);
+            }
+        }
 
         if mouse_over && !self.mouse_over {
             self.mouse_over = true;
@@ -79,6 +112,40 @@ impl Eventable {
         if mouse_over && is_mouse_button_released(MouseButton::Left) {
             return Some(Event::LeftMouseReleased);
         }
+
+        let right_mouse_down = is_mouse_button_down(MouseButton::Right);
+
+        if mouse_over && right_mouse_down && !self.right_mouse_down {
+            self.right_mouse_down = true;
+            return Some(Event::RightMousePressed);
+        }
+        self.right_mouse_down = right_mouse_down && self.right_mouse_down;
+
+        if mouse_over && is_mouse_button_released(MouseButton::Right) {
+            return Some(Event::RightMouseReleased);
+        }
         None
     }
+
+    /// Follows a press made here: starts a drag once the mouse has moved far enough,
+    /// reports it while it goes on, and ends it when the button is let go.
+    fn process_drag(&mut self) -> Option<Event> {
+        let start = self.drag_start_pos?;
+        let position = layout_mouse_position();
+        if !is_mouse_button_down(MouseButton::Left) {
+            self.drag_start_pos = None;
+            if self.dragging {
+                self.dragging = false;
+                return Some(Event::DragEnded(position));
+            }
+            return None; // a click
+        }
+        if !self.dragging {
+            let distance = ((position.0 - start.0).powi(2) + (position.1 - start.1).powi(2)).sqrt();
+            if distance < DRAG_THRESHOLD { return None }
+            self.dragging = true;
+            return Some(Event::DragStarted(start));
+        }
+        Some(Event::Dragged(position))
+    }
 }
\ No newline at end of file
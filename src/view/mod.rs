@@ -4,10 +4,12 @@ pub mod animators;
 //pub mod button_alt;
 pub mod button;
 pub mod button_bar;
+pub mod draw_arrow;
 pub mod draw_text;
 pub mod draw_texture;
 pub mod eventable;
 pub mod image;
+pub mod score_graph;
 pub mod slider;
 pub mod sprite;
 pub mod label;
@@ -15,10 +17,14 @@ pub mod transform;
 
 // mods for this app
 pub mod view_about;
+pub mod view_editor;
 pub mod view_game;
 pub mod view_intro;
+pub mod view_puzzle;
 pub mod view_rules;
 pub mod view_settings;
+pub mod view_spectator;
+pub mod view_tutorial;
 
 use macroquad::prelude::*;
 
@@ -28,6 +34,10 @@ use macroquad::prelude::*;
 /// "_2x" as a reminder.
 const IMAGE_ASSETS_SCALE: f32 = 2.0;
 
+/// The size, in logical pixels, of the area every view is laid out in. The window shows
+/// it scaled to fit and centered, keeping its aspect ratio.
+pub const LAYOUT_SIZE: (f32, f32) = (800., 800.);
+
 // Utility methods related to high-resolution image display.
 
 // Returns the number of physical pixels per logical pixel.
@@ -35,6 +45,44 @@ pub fn dpi_scale() -> f32 {
     miniquad::window::dpi_scale()
 }
 
+// Utility methods that fit the layout to the window.
+
+/// Returns the number of window pixels per layout pixel.
+pub fn layout_scale() -> f32 {
+    f32::min(screen_width() / LAYOUT_SIZE.0, screen_height() / LAYOUT_SIZE.1)
+}
+
+/// Returns the window rectangle, in layout coordinates. It is larger than the layout
+/// along the side the window has room to spare.
+fn layout_window_rect() -> Rect {
+    let scale = layout_scale();
+    let (w, h) = (screen_width() / scale, screen_height() / scale);
+    Rect::new((LAYOUT_SIZE.0 - w) / 2., (LAYOUT_SIZE.1 - h) / 2., w, h)
+}
+
+/// Draws in layout coordinates from here on. Call each frame before drawing, since the
+/// window may have been resized.
+pub fn set_layout_camera() {
+    // A minimized window has no size to fit to.
+    if layout_scale() <= 0. { return }
+    set_camera(&Camera2D::from_display_rect(layout_window_rect()));
+}
+
+/// Returns the mouse position in layout coordinates.
+pub fn layout_mouse_position() -> (f32, f32) {
+    let scale = layout_scale();
+    let (x, y) = mouse_position();
+    if scale <= 0. { return (x, y) }
+    let rect = layout_window_rect();
+    (rect.x + x / scale, rect.y + y / scale)
+}
+
+/// Whether Control, or Command on a Mac, is held, making a key a shortcut.
+pub fn command_key_down() -> bool {
+    is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl)
+        || is_key_down(KeyCode::LeftSuper) || is_key_down(KeyCode::RightSuper)
+}
+
 #[allow(dead_code)]
 /// Returns the scaling factor that should be used for textures and images,
 /// given the dpi scale and the asset scale.
@@ -69,8 +117,8 @@ pub fn draw_to_texture(draw_fn: fn(u32, u32), phys_width: u32, phys_height: u32)
 
     draw_fn(phys_width, phys_height);
 
-    // All done. Restore default camera.
-    set_default_camera();
+    // All done. Restore the layout camera.
+    set_layout_camera();
     render_target.texture
 }
 
@@ -0,0 +1,112 @@
+// Score Graph
+// A line chart of scores over a game, one point per ply. Scores beyond the range are
+// drawn at the edge, so a single decisive score doesn't flatten the rest of the line.
+// Clicking the graph selects the nearest ply.
+
+use macroquad::prelude::*;
+
+use crate::view::layout_mouse_position;
+use crate::view::transform::Transform;
+
+pub struct ScoreGraph {
+    /// Use to set position of top-left corner.
+    pub transform: Transform,
+    pub width: f32,
+    pub height: f32,
+    /// The score drawn at the top edge; its negative is at the bottom.
+    pub range: f64,
+    /// The x axis is at least this many plies long, so early points aren't spread out.
+    pub min_plies: usize,
+    pub line_thickness: f32,
+    pub color: Color,
+    pub axis_color: Color,
+    pub background_color: Color,
+    pub selected_color: Color,
+    pub is_visible: bool,
+    /// The ply marked on the graph.
+    pub selected: Option<usize>,
+
+    // Private
+    scores: Vec<Option<f64>>, // by ply; None where unknown
+}
+
+impl ScoreGraph {
+    /// Creates a new ScoreGraph with the given position and size in logical pixels.
+    pub fn new(position: (f32, f32), width: f32, height: f32) -> Self {
+        Self {
+            transform: Transform::new(position, 0.0),
+            width,
+            height,
+            range: 10.0,
+            min_plies: 40,
+            line_thickness: 2.0,
+            color: WHITE,
+            axis_color: GRAY,
+            background_color: Color::from_rgba(30, 30, 30, 255),
+            selected_color: YELLOW,
+            is_visible: true,
+            selected: None,
+            scores: Vec::new(),
+        }
+    }
+
+    /// Sets the scores, by ply, for the player shown at the top.
+    pub fn set_scores(&mut self, scores: &[Option<f64>]) {
+        self.scores = scores.to_vec();
+    }
+
+    pub fn has_scores(&self) -> bool {
+        self.scores.iter().any(|score| score.is_some())
+    }
+
+    /// The position of a point, relative to the top-left corner.
+    fn point(&self, ply: usize, score: f64) -> (f32, f32) {
+        let x = self.ply_x(ply);
+        let ratio = (score.clamp(-self.range, self.range) / self.range) as f32;
+        let y = self.height / 2.0 * (1.0 - ratio);
+        (x, y)
+    }
+
+    /// The x offset of the ply, from the left edge.
+    fn ply_x(&self, ply: usize) -> f32 {
+        let plies = (self.scores.len().max(self.min_plies) - 1).max(1);
+        self.width * ply as f32 / plies as f32
+    }
+
+    /// Returns the ply nearest a click inside the graph.
+    pub fn process_events(&self) -> Option<usize> {
+        if !self.is_visible || self.scores.is_empty() { return None; }
+        if !is_mouse_button_released(MouseButton::Left) { return None; }
+
+        let (x, y, _) = self.transform.combined_x_y_rot();
+        let (mouse_x, mouse_y) = layout_mouse_position();
+        if mouse_x < x || mouse_x > x + self.width || mouse_y < y || mouse_y > y + self.height { return None; }
+        (0..self.scores.len()).min_by(|a, b| {
+            let distance = |ply: &usize| (self.ply_x(*ply) - (mouse_x - x)).abs();
+            distance(a).total_cmp(&distance(b))
+        })
+    }
+
+    pub fn draw(&self) {
+        if !self.is_visible { return; }
+
+        let (x, y, _) = self.transform.combined_x_y_rot();
+        draw_rectangle(x, y, self.width, self.height, self.background_color);
+        draw_line(x, y + self.height / 2.0, x + self.width, y + self.height / 2.0, 1.0, self.axis_color);
+        if let Some(ply) = self.selected {
+            let ply_x = x + self.ply_x(ply);
+            draw_line(ply_x, y, ply_x, y + self.height, 1.0, self.selected_color);
+        }
+
+        // Connect the known points, bridging any unknown ones.
+        let points: Vec<(f32, f32)> = self.scores.iter().enumerate()
+            .filter_map(|(ply, score)| score.map(|score| self.point(ply, score)))
+            .collect();
+        for pair in points.windows(2) {
+            draw_line(x + pair[0].0, y + pair[0].1, x + pair[1].0, y + pair[1].1, self.line_thickness, self.color);
+        }
+        if let Some(last) = points.last() {
+            draw_circle(x + last.0, y + last.1, self.line_thickness * 1.5, self.color);
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 use macroquad::prelude::*;
 
+use crate::view::layout_mouse_position;
 use crate::view::transform::Transform;
 
 #[derive(Debug)]
@@ -124,7 +125,7 @@ impl Slider {
         let mut event = None;
         if !self.is_visible { return event; };
         
-        let mouse_pos = mouse_position();
+        let mouse_pos = layout_mouse_position();
         let old_value = self.value;
         let mut value_changed = false;
 
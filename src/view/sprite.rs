@@ -30,6 +30,7 @@ pub struct Sprite {
     pub alt_texture: Option<Texture2D>,
     pub use_alt_texture: bool,
 
+    pub color: Option<Color>, // tints the texture, none for its own colors
     pub alt_color: Option<Color>,
     pub use_alt_color: bool,
 }
@@ -46,6 +47,7 @@ impl Sprite {
             mover: None,
             alt_texture: None,
             use_alt_texture: false,
+            color: None,
             alt_color: None,
             use_alt_color: false,
         }
@@ -72,7 +74,6 @@ impl Sprite {
         self.eventable.contains_point(point, &self.transform, &self.drawable)
     }
 
-    #[allow(dead_code)]
     pub fn process_events(&mut self) -> Option<Event> {
         self.eventable.process_events(&self.transform, &self.drawable)
     }
@@ -81,7 +82,7 @@ impl Sprite {
         if self.use_alt_color {
             self.drawable.draw(&self.transform, self.alt_color);
         } else {
-            self.drawable.draw(&self.transform, None);
+            self.drawable.draw(&self.transform, self.color);
         }
         
     }
@@ -1,6 +1,6 @@
 /// Transform
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub struct Transform {
     pub position: (f32, f32),
     pub rotation: f32,
@@ -18,16 +18,6 @@ impl Transform {
         }
     }
 
-    #[allow(dead_code)]
-    pub fn default() -> Self {
-        Self {
-            position: (0.0, 0.0),
-            rotation: 0.0,
-            parent_position: (0.0, 0.0),
-            parent_rotation: 0.0,
-         }
-    }
-
     pub fn set_parent(&mut self, parent: Transform) {
         self.parent_position = parent.position;
         self.parent_rotation = parent.rotation;
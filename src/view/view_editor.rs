@@ -0,0 +1,182 @@
+// ViewEditor
+// The palette for setting up a position: a tool for each piece kind and one to erase,
+// whose pieces the tool places, the player to move, and buttons to clear the board,
+// reset it, and leave the editor.
+
+use std::sync::mpsc::Sender;
+
+use crate::piece::PieceKind::{self, *};
+use crate::view::button::{Button, ButtonEvent, ButtonMode};
+
+// Widget IDs
+const ERASE_ID: usize = 10; // after the piece kinds, which use PieceKind::index()
+const OWNER_ID: usize = 11;
+const SIDE_TO_MOVE_ID: usize = 12;
+const CLEAR_ID: usize = 13;
+const START_POSITION_ID: usize = 14;
+const PLAY_ID: usize = 15;
+const ANALYZE_ID: usize = 16;
+const CANCEL_ID: usize = 17;
+
+/// Below the board, clear of player 0's reserve.
+const PALETTE_CORNER: (f32, f32) = (145., 655.);
+const BUTTON_STEP: (f32, f32) = (84., 28.);
+
+/// The tools in palette order, by row.
+const TOOL_ROWS: [&[Option<PieceKind>]; 2] = [
+    &[Some(King), Some(Gold), Some(Silver), Some(Rook), Some(Bishop), Some(Pawn)],
+    &[Some(SilverPro), Some(RookPro), Some(BishopPro), Some(PawnPro), None],
+];
+
+pub enum ViewEditorMessage {
+    /// The kind placed by clicks, or None to erase.
+    ToolSelected(Option<PieceKind>),
+    OwnerChanged(usize), // the player
+    SideToMoveChanged(usize), // the player
+    Clear,
+    StartPosition,
+    Play,
+    Analyze,
+    Cancel,
+}
+
+pub struct ViewEditor {
+    /// Sends messages to controller.
+    tx: Sender<ViewEditorMessage>,
+
+    tool_buttons: Vec<(Option<PieceKind>, Button)>,
+    owner_button: Button,
+    side_to_move_button: Button,
+    buttons: Vec<Button>, // push buttons: clear, start position, play, analyze, cancel
+}
+
+impl ViewEditor {
+    pub fn new(tx: Sender<ViewEditorMessage>) -> Self {
+        let position = |column: usize, row: usize| {
+            (PALETTE_CORNER.0 + BUTTON_STEP.0 * column as f32, PALETTE_CORNER.1 + BUTTON_STEP.1 * row as f32)
+        };
+
+        let mut tool_buttons = Vec::new();
+        for (row, tools) in TOOL_ROWS.iter().enumerate() {
+            for (column, tool) in tools.iter().enumerate() {
+                let (text, id) = match tool {
+                    Some(kind) => (Self::kind_name(*kind), kind.index()),
+                    None => ("Erase", ERASE_ID),
+                };
+                let mut button = Button::new(position(column, row), 1, text, Some(id));
+                button.mode = ButtonMode::Toggle;
+                tool_buttons.push((*tool, button));
+            }
+        }
+
+        let mut owner_button = Button::new(position(5, 1), 1, "", Some(OWNER_ID));
+        owner_button.mode = ButtonMode::Toggle;
+        let mut side_to_move_button = Button::new(position(0, 2), 1, "", Some(SIDE_TO_MOVE_ID));
+        side_to_move_button.mode = ButtonMode::Toggle;
+
+        let buttons = [("Clear", CLEAR_ID), ("Start", START_POSITION_ID), ("Play", PLAY_ID), ("Analyze", ANALYZE_ID), ("Cancel", CANCEL_ID)]
+            .iter()
+            .enumerate()
+            .map(|(column, (text, id))| Button::new(position(column + 1, 2), 1, text, Some(*id)))
+            .collect();
+
+        let mut view = Self {
+            tx,
+            tool_buttons,
+            owner_button,
+            side_to_move_button,
+            buttons,
+        };
+        view.set_tool(Some(Pawn));
+        view.set_owner(0);
+        view.set_side_to_move(0);
+        view
+    }
+
+    fn kind_name(kind: PieceKind) -> &'static str {
+        match kind {
+            King => "King",
+            Gold => "Gold",
+            Silver => "Silver",
+            SilverPro => "Silver+",
+            Rook => "Rook",
+            RookPro => "Rook+",
+            Bishop => "Bishop",
+            BishopPro => "Bishop+",
+            Pawn => "Pawn",
+            PawnPro => "Pawn+",
+        }
+    }
+
+    /// Highlights the tool's button only.
+    pub fn set_tool(&mut self, tool: Option<PieceKind>) {
+        for (button_tool, button) in &mut self.tool_buttons {
+            button.set_selected(*button_tool == tool);
+        }
+    }
+
+    /// Shows whose pieces the tools place.
+    pub fn set_owner(&mut self, player: usize) {
+        self.owner_button.set_selected(player == 1);
+        self.owner_button.text_drawable.text = format!("Player {}", player + 1);
+    }
+
+    pub fn set_side_to_move(&mut self, player: usize) {
+        self.side_to_move_button.set_selected(player == 1);
+        self.side_to_move_button.text_drawable.text = format!("P{} moves", player + 1);
+    }
+
+    pub fn process_events(&mut self) {
+        let mut selected_tool = None;
+        for (tool, button) in &mut self.tool_buttons {
+            if button.process_events().is_some() {
+                selected_tool = Some(*tool);
+            }
+        }
+        if let Some(tool) = selected_tool {
+            // Radio behavior: a second click on the selected tool keeps it.
+            self.set_tool(tool);
+            self.send(ViewEditorMessage::ToolSelected(tool));
+        }
+        if let Some(ButtonEvent::Toggled(_id)) = self.owner_button.process_events() {
+            let player = self.owner_button.selected() as usize;
+            self.set_owner(player);
+            self.send(ViewEditorMessage::OwnerChanged(player));
+        }
+        if let Some(ButtonEvent::Toggled(_id)) = self.side_to_move_button.process_events() {
+            let player = self.side_to_move_button.selected() as usize;
+            self.set_side_to_move(player);
+            self.send(ViewEditorMessage::SideToMoveChanged(player));
+        }
+
+        let mut pushed = None;
+        for button in &mut self.buttons {
+            if let Some(ButtonEvent::Pushed(id)) = button.process_events() {
+                pushed = id;
+            }
+        }
+        match pushed {
+            Some(CLEAR_ID) => self.send(ViewEditorMessage::Clear),
+            Some(START_POSITION_ID) => self.send(ViewEditorMessage::StartPosition),
+            Some(PLAY_ID) => self.send(ViewEditorMessage::Play),
+            Some(ANALYZE_ID) => self.send(ViewEditorMessage::Analyze),
+            Some(CANCEL_ID) => self.send(ViewEditorMessage::Cancel),
+            _ => {},
+        }
+    }
+
+    fn send(&self, message: ViewEditorMessage) {
+        self.tx.send(message).expect("Editor message send error.");
+    }
+
+    pub fn draw(&mut self) {
+        for (_, button) in &mut self.tool_buttons {
+            button.draw();
+        }
+        self.owner_button.draw();
+        self.side_to_move_button.draw();
+        for button in &mut self.buttons {
+            button.draw();
+        }
+    }
+}
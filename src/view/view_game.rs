@@ -9,15 +9,20 @@ use macroquad::audio::{Sound, play_sound_once, play_sound, PlaySoundParams};
 use macroquad::prelude::*;
 
 use crate::asset_loader::AssetLoader;
-use crate::game::{Game, NONE};
+use crate::game::{EndReason, Game, GameLocation, NONE};
+use crate::game_analysis::Judgement;
 use crate::controller::AppState;
 use crate::controller::AppState::*;
 use crate::piece::Piece;
 use crate::piece::PieceKind::{self, *};
+use crate::theme::{self, ColorScheme, PieceSet};
+use crate::view::draw_arrow::DrawArrow;
+use crate::view::eventable::Event;
 use crate::view::label::Label;
+use crate::view::{command_key_down, layout_mouse_position};
+use crate::view::score_graph::ScoreGraph;
 use crate::view::sprite::Sprite;
 
-const BACKGROUND_COLOR: (u8, u8, u8) = (40, 40, 40);
 const BOARD_CORNER: (f32, f32) = (165.0, 165.0);
 const SQUARE_SIZE: f32 = 90.0; // matches the square.png size
 const SQUARE_GAP: f32 = 5.0;
@@ -26,30 +31,144 @@ const PROMO_LINE_BOTTOM: (f32, f32) = (405., 547.);
 const RESERVE_0_CENTER: (f32, f32) = (710., 680.);
 const RESERVE_1_CENTER: (f32, f32) = (90., 120.);
 const RESERVE_BOX_OFFSET: f32 = 20.;
-const RESERVE_PIECE_OFFSET: f32 = 12.;
+const RESERVE_BOX_COUNT: usize = 6;
+/// The kind each reserve box holds, from the one nearest the player's side of the board.
+/// Anything else, such as a captured king, goes in the last box.
+const RESERVE_BOX_KINDS: [PieceKind; RESERVE_BOX_COUNT - 1] = [Pawn, Bishop, Rook, Silver, Gold];
+/// The count badge of a reserve box holding several pieces, at its top-right corner.
+const COUNT_BADGE_OFFSET: (f32, f32) = (34., -34.);
+const COUNT_BADGE_RADIUS: f32 = 11.;
+const COUNT_BADGE_COLOR: Color = Color::new(0.75, 0.1, 0.1, 1.0);
+const HAND_TOOLTIP_GAP: f32 = 8.;
+const HAND_TOOLTIP_COLOR: Color = Color::new(0.1, 0.1, 0.1, 0.85);
 const TEXT_STATUS_CENTER: (f32, f32) = (400., 120.0);
+const CLOCK_CENTERS: [(f32, f32); 2] = [(710., 745.), (90., 52.)]; // by the bottom and top reserves
+const CLOCK_LOW_COLOR: Color = RED;
 const AI_PROGRESS_CORNER: (f32, f32) = (20., 778.);
+const ANALYSIS_CORNER: (f32, f32) = (170., 668.);
+const ANALYSIS_LINE_HEIGHT: f32 = 20.;
+const ANALYSIS_SELECTED_COLOR: Color = YELLOW;
+const SCORE_GRAPH_CORNER: (f32, f32) = (170., 655.); // below the board, where analysis lines go
+const SCORE_GRAPH_SIZE: (f32, f32) = (470., 50.);
+const MOVE_LIST_CORNER: (f32, f32) = (170., 718.); // below the score graph
+const MOVE_LIST_CELL_SIZE: (f32, f32) = (78., 14.);
+const MOVE_LIST_COLUMNS: usize = 6;
+const MOVE_LIST_ROWS: usize = 4;
+const MOVE_LIST_SELECTED_COLOR: Color = SKYBLUE;
+const INACCURACY_COLOR: Color = GOLD;
+const MISTAKE_COLOR: Color = ORANGE;
+const BLUNDER_COLOR: Color = RED;
 const MOVE_DURATION: f32 = 0.25;
+const LAST_MOVE_COLOR: Color = Color::new(0.95, 0.85, 0.3, 0.35);
+const CHECK_GLOW_COLOR: Color = Color::new(1.0, 0.1, 0.1, 0.5);
+const CHECK_GLOW_RADIUS: f32 = 46.;
+const HANGING_COLOR: Color = ORANGE;
+const HANGING_RADIUS: f32 = 42.;
+const ANNOTATION_COLOR: Color = Color::new(0.1, 0.6, 0.25, 0.8);
+const ANNOTATION_CIRCLE_RADIUS: f32 = 40.;
+const PV_ARROW_COLOR: Color = Color::new(0.2, 0.45, 0.9, 0.75);
+const PV_ARROW_COUNT: usize = 3; // the most engine moves drawn
+const PV_NUMBER_RADIUS: f32 = 10.;
+const ARROW_THICKNESS: f32 = 10.;
+const ARROW_HEAD_SIZE: f32 = 28.;
+const CURSOR_COLOR: Color = SKYBLUE;
+const CURSOR_THICKNESS: f32 = 4.;
+const TYPED_MOVE_CORNER: (f32, f32) = (20., 694.); // above the Undo and Flip buttons
+const TYPED_MOVE_MAX_LEN: usize = 5; // as in "2b3c+"
+
+/// The marks drawn over the board, each turned on or off in settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Overlays {
+    /// The squares of the last move.
+    pub last_move: bool,
+    /// A glow under a king in check.
+    pub check: bool,
+    /// Rings around undefended pieces under attack.
+    pub hanging: bool,
+}
+
+impl Default for Overlays {
+    fn default() -> Self {
+        Self { last_move: true, check: true, hanging: false }
+    }
+}
 
 pub enum ViewGameMessage {
     PieceSelected(usize), // the piece id
     SquareSelected(usize), // the location index
-    ReserveSelected(usize), // the player
+    PieceDragStarted(usize), // the piece id
+    PieceDropped(usize, Option<usize>), // the piece id and the location index of the square under it, if any
+    ReserveSelected(usize, Option<usize>), // the player, and the top piece id in the box clicked
+    MoveTyped(String), // in USI notation, as typed
+    AnalysisLineSelected(usize), // the rank, from 0
+    PlySelected(usize), // the ply clicked on the score graph, or after the move clicked in the move list
     ShouldExit,
 }
 
+/// A mark drawn on the board with the right mouse button.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Annotation {
+    Arrow(usize, usize), // from and to location indices
+    Circle(usize), // the location index
+}
+
+/// What the keyboard cursor is on.
+#[derive(Clone, Copy)]
+enum CursorTarget {
+    Square(usize), // the location index
+    Reserve(usize, usize), // the player and reserve box index
+}
+
+/// A piece being dragged with the mouse.
+#[derive(Clone, Copy)]
+struct DraggedPiece {
+    id: usize,
+    origin: (f32, f32), // where it was when the drag started
+    offset: (f32, f32), // from the mouse
+}
+
 pub struct ViewGame {
     tx: Sender<ViewGameMessage>, // sends event messages to controller
     columns: usize,
     rows: usize,
+    flipped: bool, // player 1 at the bottom
+    piece_textures: Vec<Texture2D>, // the piece set's, by PieceKind::index(); empty for the built-in images
+    colors: ColorScheme,
+    shown_game: Game, // the position the pieces show
     squares: HashMap<usize, Sprite>, // key: location index
     promotion_lines: Vec<Sprite>,
     reserve_boxes: Vec<HashMap<usize, Sprite>>, // *************** why is this a hash map?
+    reserve_stacks: Vec<Vec<Vec<usize>>>, // the piece ids in each player's reserve boxes, top last
+    count_badges: Vec<Vec<Label>>, // by player and reserve box
+    hand_tooltip: Label, // how many pieces a hovered reserve box holds
     pieces: Vec<Sprite>, // a vec so it can be sorted by z_order
     pub selected_piece: Option<usize>,
     pub move_indices: Vec<usize>, // all the spots the currently selected piece can move to
+    hint: Option<(usize, usize)>, // the suggested piece id and location index
+    dragged_piece: Option<DraggedPiece>,
+    overlays: Overlays,
+    last_move: Option<(Option<usize>, usize)>, // the from square, none for a drop, and the to square
+    kings_in_check: Vec<usize>, // piece ids, for the check overlay
+    hanging_pieces: Vec<usize>, // piece ids, for the hanging overlay
+    annotations: Vec<Annotation>,
+    annotation_start: Option<usize>, // the square where the right button went down
+    pv_arrows: Vec<(Option<usize>, usize)>, // the engine's moves, in order; a drop has no from square
+    pv_numbers: Vec<Label>, // one per pv arrow
+    arrow: DrawArrow,
+    cursor: Option<(i32, i32)>, // the keyboard cursor's column and row on screen; the reserves are columns -1 and 'columns'
+    typed_move: String,
+    typed_move_label: Label,
     status_text: Label,
+    end_reason: Option<EndReason>, // shown with the result when not a king capture
+    clock_texts: Vec<Label>, // by player
     ai_progress_text: Label,
+    analysis_lines: Vec<Label>, // the ranked moves of an analysis
+    score_graph: ScoreGraph,
+    move_list: Vec<(String, Option<Judgement>)>, // each move played, with any review judgement
+    move_list_labels: Vec<Label>, // one per cell, filled from the top row shown
+    move_list_top_row: usize, // the first row shown, for scrolling
+    move_list_selected: Option<usize>, // the move whose position is shown from history
+    move_list_visible: bool,
     piece_move: Sound,
     piece_capture: Sound,
 }
@@ -58,12 +177,37 @@ impl ViewGame {
     pub async fn new(tx: Sender<ViewGameMessage>, columns: usize, rows: usize) -> Self {
         Self {
             tx, columns, rows,
+            flipped: false,
+            piece_textures: Vec::new(),
+            colors: ColorScheme::classic(),
+            shown_game: Game::new(),
             squares: HashMap::new(),
             promotion_lines: Vec::new(),
             reserve_boxes: vec!(HashMap::new(), HashMap::new()),
+            reserve_stacks: vec![vec![Vec::new(); RESERVE_BOX_COUNT]; 2],
+            count_badges: (0..2)
+                .map(|_| (0..RESERVE_BOX_COUNT).map(|_| Label::new((0., 0.), true, "", 12, Some("Menlo Bold"))).collect())
+                .collect(),
+            hand_tooltip: Label::new((0., 0.), false, "", 12, Some("Menlo")),
             pieces: Vec::new(),
             selected_piece: None,
             move_indices: Vec::new(),
+            hint: None,
+            dragged_piece: None,
+            overlays: Overlays::default(),
+            last_move: None,
+            kings_in_check: Vec::new(),
+            hanging_pieces: Vec::new(),
+            annotations: Vec::new(),
+            annotation_start: None,
+            pv_arrows: Vec::new(),
+            pv_numbers: (1..=PV_ARROW_COUNT)
+                .map(|number| Label::new((0., 0.), true, &number.to_string(), 12, Some("Menlo Bold")))
+                .collect(),
+            arrow: DrawArrow::new(ANNOTATION_COLOR, ARROW_THICKNESS, ARROW_HEAD_SIZE),
+            cursor: None,
+            typed_move: String::new(),
+            typed_move_label: Label::new(TYPED_MOVE_CORNER, false, "", 14, Some("Menlo")),
             status_text: Label::new(
                 TEXT_STATUS_CENTER,
                 true,
@@ -71,6 +215,10 @@ impl ViewGame {
                 18,
                 Some("Menlo"),
             ),
+            end_reason: None,
+            clock_texts: CLOCK_CENTERS.iter()
+                .map(|center| Label::new(*center, true, "", 18, Some("Menlo Bold")))
+                .collect(),
             ai_progress_text: Label::new(
                 AI_PROGRESS_CORNER,
                 false,
@@ -78,6 +226,20 @@ impl ViewGame {
                 12,
                 Some("Menlo"),
             ),
+            analysis_lines: Vec::new(),
+            score_graph: ScoreGraph::new(SCORE_GRAPH_CORNER, SCORE_GRAPH_SIZE.0, SCORE_GRAPH_SIZE.1),
+            move_list: Vec::new(),
+            move_list_labels: (0..MOVE_LIST_ROWS * MOVE_LIST_COLUMNS)
+                .map(|cell| {
+                    let column = (cell % MOVE_LIST_COLUMNS) as f32;
+                    let row = (cell / MOVE_LIST_COLUMNS) as f32;
+                    let position = (MOVE_LIST_CORNER.0 + MOVE_LIST_CELL_SIZE.0 * column, MOVE_LIST_CORNER.1 + MOVE_LIST_CELL_SIZE.1 * row);
+                    Label::new(position, false, "", 12, Some("Menlo"))
+                })
+                .collect(),
+            move_list_top_row: 0,
+            move_list_selected: None,
+            move_list_visible: true,
             piece_move: AssetLoader::get_sound("piece_move").await,
             piece_capture: AssetLoader::get_sound("piece_capture").await,
         }
@@ -85,10 +247,25 @@ impl ViewGame {
 
     pub fn reset_game(&mut self, game: &Game) {
         self.pieces.clear();
+        self.last_move = None;
+        self.annotations.clear();
+        self.pv_arrows.clear();
+        self.update_threats(game);
 
-         // Add the game's pieces to the view.
+         // Add the game's pieces to the view. A set-up position may not use them all.
          for piece in game.pieces {
-            self.add_piece(&piece);
+            match piece.location {
+                GameLocation::Board => self.add_piece(&piece),
+                GameLocation::Reserve => {
+                    // Placed with the rest of its kind below.
+                    let position = self.reserve_boxes[piece.player][&0].transform.position;
+                    self.add_piece_at(&piece, position);
+                },
+                GameLocation::OutOfGame => {},
+            }
+        }
+        for player in 0..2 {
+            self.update_reserve_pieces(game, player, false);
         }
     }
 
@@ -100,7 +277,8 @@ impl ViewGame {
                 let index = Game::column_row_to_index(c, r);
                 let position = self.center_position_for(index);
                 let mut square = Sprite::new(position, texture.clone(), None);
-                square.alt_color = Some(LIGHTGRAY);
+                square.color = Some(self.colors.square);
+                square.alt_color = Some(self.colors.highlight);
                 self.squares.insert(index, square);
             }
         }
@@ -114,59 +292,146 @@ impl ViewGame {
 
         // Reserves
         texture = AssetLoader::get_texture("reserve");
-        for i in 0..6 {
-            // Reserve, player 0
-            let mut pos_x = RESERVE_0_CENTER.0;
-            let mut pos_y = RESERVE_0_CENTER.1 - i as f32 * (SQUARE_SIZE + RESERVE_BOX_OFFSET); 
-            let mut reserve = Sprite::new((pos_x, pos_y), texture.clone(), None);
-            self.reserve_boxes[0].insert(i, reserve);
-            // Reserve, player 1
-            pos_x = RESERVE_1_CENTER.0;
-            pos_y = RESERVE_1_CENTER.1 + i as f32 * (SQUARE_SIZE + RESERVE_BOX_OFFSET);
-            reserve = Sprite::new((pos_x, pos_y), texture.clone(), None);
-            self.reserve_boxes[1].insert(i, reserve);
+        for player in 0..2 {
+            for i in 0..RESERVE_BOX_COUNT {
+                let reserve = Sprite::new((0., 0.), texture.clone(), None);
+                self.reserve_boxes[player].insert(i, reserve);
+            }
         }
+        self.layout_sides();
 
         // ButtonBar
 
     }
 
+    /// Places each player's reserve boxes, count badges and clock on their side of the
+    /// board: player 0 at the bottom, unless flipped.
+    fn layout_sides(&mut self) {
+        for player in 0..2 {
+            let bottom = self.side_for(player) == 0;
+            for i in 0..RESERVE_BOX_COUNT {
+                let step = i as f32 * (SQUARE_SIZE + RESERVE_BOX_OFFSET);
+                let position = if bottom {
+                    (RESERVE_0_CENTER.0, RESERVE_0_CENTER.1 - step)
+                } else {
+                    (RESERVE_1_CENTER.0, RESERVE_1_CENTER.1 + step)
+                };
+                if let Some(reserve) = self.reserve_boxes[player].get_mut(&i) {
+                    reserve.transform.position = position;
+                }
+                self.count_badges[player][i].transform.position = (position.0 + COUNT_BADGE_OFFSET.0, position.1 + COUNT_BADGE_OFFSET.1);
+            }
+            self.clock_texts[player].transform.position = CLOCK_CENTERS[self.side_for(player)];
+        }
+    }
+
+    /// The side of the board the player sits at: 0 for the bottom, 1 for the top.
+    fn side_for(&self, player: usize) -> usize {
+        if self.flipped { 1 - player } else { player }
+    }
+
+    /// The rotation of the player's pieces, which face away from the player's side.
+    fn rotation_for(&self, player: usize) -> f32 {
+        if self.side_for(player) == 1 { std::f32::consts::PI } else { 0. }
+    }
+
+    pub fn is_flipped(&self) -> bool {
+        self.flipped
+    }
+
+    /// Turns the board around, putting player 1 at the bottom if flipped, and places the
+    /// pieces shown again.
+    pub fn set_flipped(&mut self, flipped: bool) {
+        if flipped == self.flipped { return }
+        self.flipped = flipped;
+        let game = self.shown_game;
+        let positions: Vec<(usize, (f32, f32))> = self.squares.keys()
+            .map(|index| (*index, self.center_position_for(*index)))
+            .collect();
+        for (index, position) in positions {
+            if let Some(square) = self.squares.get_mut(&index) {
+                square.transform.position = position;
+            }
+        }
+        self.layout_sides();
+        for piece in game.pieces {
+            if piece.location != GameLocation::Board { continue }
+            let position = self.center_position_for(piece.location_index);
+            let rotation = self.rotation_for(piece.player);
+            if let Some(sprite) = self.piece_for_id(piece.id) {
+                sprite.transform.position = position;
+                sprite.transform.rotation = rotation;
+            }
+        }
+        for player in 0..2 {
+            self.update_reserve_pieces(&game, player, false);
+        }
+        self.set_pv_arrows(self.pv_arrows.clone());
+    }
+
     fn texture_for(&self, piece_kind: PieceKind) -> Texture2D {
-        match piece_kind {
-            King => AssetLoader::get_texture("king"),
-            Gold => AssetLoader::get_texture("gold"),
-            Silver => AssetLoader::get_texture("silver"),
-            SilverPro => AssetLoader::get_texture("silver_pro"),
-            Rook => AssetLoader::get_texture("rook"),
-            RookPro => AssetLoader::get_texture("rook_pro"),
-            Bishop => AssetLoader::get_texture("bishop"),
-            BishopPro => AssetLoader::get_texture("bishop_pro"),
-            Pawn => AssetLoader::get_texture("pawn"),
-            PawnPro => AssetLoader::get_texture("pawn_pro"),
+        match self.piece_textures.get(piece_kind.index()) {
+            Some(texture) => texture.clone(),
+            None => AssetLoader::get_texture(theme::texture_name(piece_kind)),
         }
     }
 
+    /// Draws the pieces from the set from now on, the ones shown included.
+    pub fn set_piece_set(&mut self, piece_set: &PieceSet) {
+        self.piece_textures = piece_set.textures();
+        let ids: Vec<usize> = self.pieces.iter().filter_map(|piece| piece.id).collect();
+        for id in ids {
+            let kind = self.shown_game.piece_for(id).kind;
+            self.update_piece_kind(id, kind);
+        }
+    }
+
+    pub fn set_color_scheme(&mut self, colors: ColorScheme) {
+        self.colors = colors;
+        for square in self.squares.values_mut() {
+            square.color = Some(self.colors.square);
+            square.alt_color = Some(self.colors.highlight);
+        }
+        for piece in &mut self.pieces {
+            piece.color = Some(self.colors.piece);
+            piece.alt_color = Some(self.colors.selected);
+        }
+        self.status_text.set_color(self.colors.text);
+        self.ai_progress_text.set_color(self.colors.text);
+        self.typed_move_label.set_color(self.colors.text);
+        self.update_move_list_labels();
+    }
+
     fn piece_for_id(&mut self, id: usize) -> Option<&mut Sprite> {
         self.pieces.iter_mut().find(|p| p.id == Some(id))
     }
 
     pub fn add_piece(&mut self, piece: &Piece) {
-        let texture = self.texture_for(piece.kind);
         let position = self.center_position_for(piece.location_index);
+        self.add_piece_at(piece, position);
+    }
+
+    fn add_piece_at(&mut self, piece: &Piece, position: (f32, f32)) {
+        let texture = self.texture_for(piece.kind);
         let mut sprite = Sprite::new(position, texture, Some(piece.id));
-        if piece.player == 1 {
-            sprite.transform.rotation = std::f32::consts::PI;
-        }
-        sprite.alt_color = Some(LIGHTGRAY);
-        self.pieces.insert(piece.id, sprite);
+        sprite.transform.rotation = self.rotation_for(piece.player);
+        sprite.color = Some(self.colors.piece);
+        sprite.alt_color = Some(self.colors.selected);
+        sprite.eventable.draggable = true;
+        self.pieces.push(sprite);
     }
 
     fn corner_position_for(&self, index: usize) -> (f32, f32) {
         let (x0, y0) = Game::index_to_column_row(index);
-        // We want row 0 at the bottom of the board, not the top, so flip the row.
-        let flip_r = self.rows - y0 - 1;
-        let x = BOARD_CORNER.0 + SQUARE_GAP + (SQUARE_SIZE + SQUARE_GAP) * x0 as f32;
-        let y = BOARD_CORNER.1 + SQUARE_GAP + (SQUARE_SIZE + SQUARE_GAP) * flip_r as f32;
+        // We want row 0 at the bottom of the board, not the top, so flip the row. A
+        // flipped board is turned half around, which mirrors the columns instead.
+        let (column, row) = if self.flipped {
+            (self.columns - x0 - 1, y0)
+        } else {
+            (x0, self.rows - y0 - 1)
+        };
+        let x = BOARD_CORNER.0 + SQUARE_GAP + (SQUARE_SIZE + SQUARE_GAP) * column as f32;
+        let y = BOARD_CORNER.1 + SQUARE_GAP + (SQUARE_SIZE + SQUARE_GAP) * row as f32;
         (x, y)
     }
 
@@ -190,21 +455,17 @@ impl ViewGame {
         }
     }
 
-    fn move_piece_to_reserve(&mut self, player: usize, id: usize, reserve_index: usize, count_index: usize) {
+    /// Moves the piece to its spot in the player's reserve, sliding it there if animate.
+    /// Pieces of a kind stack exactly; the box's count badge tells how many there are.
+    fn move_piece_to_reserve(&mut self, player: usize, id: usize, reserve_index: usize, animate: bool) {
         let reserve_pos = self.reserve_boxes[player].get(&reserve_index).unwrap().transform.position;
+        let theta = self.rotation_for(player);
         if let Some(piece) = self.piece_for_id(id) {
-                let mut end_position = reserve_pos;
-                if player == 0 {
-                    end_position.1 -= RESERVE_PIECE_OFFSET * count_index as f32;
-                } else {
-                    end_position.1 += RESERVE_PIECE_OFFSET * count_index as f32;
-                }
-                let mut theta: f32 = 0.0;
-                if player == 1 {
-                    theta = std::f32::consts::PI
-                }
+                let end_position = reserve_pos;
                 piece.transform.rotation = theta;
-                if end_position != piece.transform.position {
+                if !animate {
+                    piece.transform.position = end_position;
+                } else if end_position != piece.transform.position {
                     piece.move_to(end_position, Duration::from_secs_f32(MOVE_DURATION));
                     play_sound_once(&self.piece_capture);
                 }
@@ -212,7 +473,7 @@ impl ViewGame {
     }
 
     /// Position the reserve pieces for the player, grouping by PieceKind.
-    fn update_reserve_pieces(&mut self, game: &Game, player: usize) {
+    fn update_reserve_pieces(&mut self, game: &Game, player: usize, animate: bool) {
         // First, get all the piece ids and group them into a vec and store them by kind.
         let mut reserve_hash = HashMap::<PieceKind, Vec<usize>>::new();
         
@@ -229,28 +490,28 @@ impl ViewGame {
         }
 
         // Now, move the pieces into the appropriate spot.
+        for stack in &mut self.reserve_stacks[player] {
+            stack.clear();
+        }
         for (kind, id_vec) in reserve_hash {
-            // Could match PieceKind here to specific reserve index. Pawns = 0, etc
-            let reserve_index = match kind {
-                Gold => 4,
-                Silver => 3,
-                Rook => 2,
-                Bishop => 1,
-                Pawn => 0,
-                _ => 5,
-            };
+            let reserve_index = RESERVE_BOX_KINDS.iter().position(|box_kind| *box_kind == kind).unwrap_or(RESERVE_BOX_COUNT - 1);
             for (count_index, id) in id_vec.iter().enumerate() {
                 if let Some(piece) = self.piece_for_id(*id) {
                     piece.drawable.z_order = count_index; // position on top of previous pieces
                 }
-                self.move_piece_to_reserve(player, *id, reserve_index, count_index);
+                self.move_piece_to_reserve(player, *id, reserve_index, animate);
             }
+            self.reserve_stacks[player][reserve_index] = id_vec;
+        }
+        for (reserve_index, badge) in self.count_badges[player].iter_mut().enumerate() {
+            badge.set_text(self.reserve_stacks[player][reserve_index].len().to_string());
         }
         // Sort by z_order so the overlap is correct.
         self.pieces.sort_by(|a, b| a.drawable.z_order.cmp(&b.drawable.z_order));
     }
 
     pub fn update_with_game(&mut self, game: &Game) {
+        self.update_threats(game);
         // Board move
         for (index, id) in game.grid.iter().enumerate() {
             if *id == NONE { continue }
@@ -260,32 +521,163 @@ impl ViewGame {
         }
         // Reserves
         for player in 0..2 {
-            self.update_reserve_pieces(game, player);
+            self.update_reserve_pieces(game, player, true);
+        }
+    }
+
+    /// Finds the kings in check and the hanging pieces of the position shown.
+    fn update_threats(&mut self, game: &Game) {
+        self.shown_game = *game;
+        self.kings_in_check = (0..2)
+            .filter(|player| game.is_in_check(*player))
+            .filter_map(|player| game.pieces.iter().find(|piece| piece.player == player && piece.kind == King && piece.location == GameLocation::Board))
+            .map(|piece| piece.id)
+            .collect();
+        self.hanging_pieces = (0..2).flat_map(|player| game.hanging_pieces(player)).collect();
+    }
+
+    pub fn set_overlays(&mut self, overlays: Overlays) {
+        self.overlays = overlays;
+    }
+
+    /// Marks the squares of the move that led to the position shown, if any.
+    pub fn set_last_move(&mut self, squares: Option<(Option<usize>, usize)>) {
+        self.last_move = squares;
+    }
+
+    /// Shows the first moves of the engine's principal variation as numbered arrows, or
+    /// none if empty. A drop is marked on its square.
+    pub fn set_pv_arrows(&mut self, squares: Vec<(Option<usize>, usize)>) {
+        self.pv_arrows = squares.into_iter().take(PV_ARROW_COUNT).collect();
+        for (index, (from, to)) in self.pv_arrows.clone().into_iter().enumerate() {
+            let to_center = self.center_position_for(to);
+            let position = match from {
+                Some(from) => {
+                    let from_center = self.center_position_for(from);
+                    ((from_center.0 + to_center.0) / 2., (from_center.1 + to_center.1) / 2.)
+                },
+                None => to_center,
+            };
+            self.pv_numbers[index].transform.position = position;
+        }
+    }
+
+    /// Adds an arrow, or a circle if from and to are the same square, or removes it if
+    /// it is already drawn.
+    fn toggle_annotation(&mut self, from: usize, to: usize) {
+        let annotation = if from == to { Annotation::Circle(to) } else { Annotation::Arrow(from, to) };
+        if let Some(position) = self.annotations.iter().position(|a| *a == annotation) {
+            self.annotations.remove(position);
+        } else {
+            self.annotations.push(annotation);
+        }
+    }
+
+    /// Right-drags from square to square draw arrows, and right-clicks draw circles. A
+    /// left press on the board wipes them.
+    fn process_annotations(&mut self) {
+        let mut pressed = None;
+        let mut released = None;
+        for (index, square) in &mut self.squares {
+            match square.process_events() {
+                Some(Event::RightMousePressed) => pressed = Some(*index),
+                Some(Event::RightMouseReleased) => released = Some(*index),
+                _ => {},
+            }
+        }
+        if pressed.is_some() {
+            self.annotation_start = pressed;
+        }
+        if is_mouse_button_released(MouseButton::Right) {
+            if let (Some(from), Some(to)) = (self.annotation_start.take(), released) {
+                self.toggle_annotation(from, to);
+            }
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let mouse_pos = layout_mouse_position();
+            if self.squares.values().any(|square| square.contains_point(mouse_pos)) {
+                self.annotations.clear();
+            }
         }
     }
 
     fn update_piece_kind(&mut self, id: usize, new_kind: PieceKind) {
         let texture = self.texture_for(new_kind);
         if let Some(sprite) = self.piece_for_id(id) {
-            sprite.drawable.texture = texture;
+            sprite.drawable.set_texture(texture);
         }
     }
 
     pub fn process_events(&mut self) {
-        // Key presses.
+        // Key presses. Escape puts away the cursor and any typing before it quits.
         if is_key_released(KeyCode::Escape) {
-            self.tx.send(ViewGameMessage::ShouldExit).expect("ViewGame message send error.");
+            if self.cursor.is_none() && self.typed_move.is_empty() {
+                self.tx.send(ViewGameMessage::ShouldExit).expect("ViewGame message send error.");
+            }
+            self.cursor = None;
+            self.typed_move.clear();
+        }
+        self.process_keys();
+
+        self.process_annotations();
+
+        // A drag ends with the button let go, which isn't also a click.
+        if self.process_drags() {
+            return;
         }
 
         // Mouse position and buttons.
-        let mouse_pos = mouse_position();
+        let mouse_pos = layout_mouse_position();
         let left_button = is_mouse_button_released(MouseButton::Left);
 
         let mut clicked_handled = false;
 
-        // Detect piece hits first.
-        for piece in &self.pieces {
-            if left_button && piece.contains_point(mouse_pos) {
+        // Analysis lines
+        for (rank, line) in self.analysis_lines.iter().enumerate() {
+            let (x, y) = line.transform.position;
+            let half_height = ANALYSIS_LINE_HEIGHT / 2.;
+            let hit = !line.draw_text.text.is_empty() && mouse_pos.0 >= x && mouse_pos.0 <= x + line.width()
+                && mouse_pos.1 >= y - half_height && mouse_pos.1 < y + half_height;
+            if left_button && hit {
+                self.tx.send(ViewGameMessage::AnalysisLineSelected(rank)).expect("ViewGame message send error.");
+                clicked_handled = true;
+            }
+        }
+
+        // Score graph
+        if let Some(ply) = self.score_graph.process_events() {
+            self.tx.send(ViewGameMessage::PlySelected(ply)).expect("ViewGame message send error.");
+            clicked_handled = true;
+        }
+
+        // Move list
+        if self.move_list_visible {
+            let (x, y) = MOVE_LIST_CORNER;
+            let top = y - MOVE_LIST_CELL_SIZE.1 / 2.;
+            let over_list = mouse_pos.0 >= x && mouse_pos.0 < x + MOVE_LIST_CELL_SIZE.0 * MOVE_LIST_COLUMNS as f32
+                && mouse_pos.1 >= top && mouse_pos.1 < top + MOVE_LIST_CELL_SIZE.1 * MOVE_LIST_ROWS as f32;
+            if over_list {
+                let (_, wheel) = mouse_wheel();
+                if wheel > 0. {
+                    self.scroll_move_list(self.move_list_top_row.saturating_sub(1));
+                } else if wheel < 0. {
+                    self.scroll_move_list(self.move_list_top_row + 1);
+                }
+                let column = ((mouse_pos.0 - x) / MOVE_LIST_CELL_SIZE.0) as usize;
+                let row = ((mouse_pos.1 - top) / MOVE_LIST_CELL_SIZE.1) as usize;
+                let index = (self.move_list_top_row + row) * MOVE_LIST_COLUMNS + column;
+                if left_button && index < self.move_list.len() {
+                    // The position after the move.
+                    self.tx.send(ViewGameMessage::PlySelected(index + 1)).expect("ViewGame message send error.");
+                    clicked_handled = true;
+                }
+            }
+        }
+
+        // Detect piece hits first. Pieces in reserve overlap, so only the top one counts.
+        if left_button {
+            if let Some(piece) = self.pieces.iter().rev().find(|piece| piece.contains_point(mouse_pos)) {
                 self.tx.send(ViewGameMessage::PieceSelected(piece.id.unwrap())).expect("ViewGame message send error.");
                 clicked_handled = true;
             }
@@ -304,15 +696,146 @@ impl ViewGame {
         if !clicked_handled {
             // Reserves
             for i in 0..2 {
-                for reserve in self.reserve_boxes[i].values() {
+                for (reserve_index, reserve) in &self.reserve_boxes[i] {
                     if left_button && reserve.contains_point(mouse_pos) {
-                        self.tx.send(ViewGameMessage::ReserveSelected(i)).expect("ViewGame message send error.");
+                        let top_piece = self.reserve_stacks[i][*reserve_index].last().copied();
+                        self.tx.send(ViewGameMessage::ReserveSelected(i, top_piece)).expect("ViewGame message send error.");
                     }
                 }
             }
         }
     }
 
+    /// Typing a move in USI notation and pressing Enter makes it. Otherwise the arrow keys
+    /// move a cursor over the squares and reserves, and Enter clicks what it is on.
+    fn process_keys(&mut self) {
+        while let Some(c) = get_char_pressed() {
+            if command_key_down() || self.typed_move.len() == TYPED_MOVE_MAX_LEN { continue }
+            match c {
+                // A move begins with a square, or the letter of a piece to drop.
+                'p' | 's' | 'g' | 'b' | 'r' | 'P' | 'S' | 'G' | 'B' | 'R' if self.typed_move.is_empty() => {
+                    self.typed_move.push(c.to_ascii_uppercase());
+                },
+                '1'..='5' | 'a'..='e' | '*' | '+' => self.typed_move.push(c),
+                _ => {},
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.typed_move.pop();
+        }
+
+        if is_key_pressed(KeyCode::Enter) || is_key_pressed(KeyCode::KpEnter) {
+            if !self.typed_move.is_empty() {
+                let usi = std::mem::take(&mut self.typed_move);
+                self.tx.send(ViewGameMessage::MoveTyped(usi)).expect("ViewGame message send error.");
+            } else if self.cursor.is_some() {
+                self.click_cursor();
+            }
+        }
+
+        let step = [
+            (KeyCode::Left, (-1, 0)),
+            (KeyCode::Right, (1, 0)),
+            (KeyCode::Up, (0, -1)),
+            (KeyCode::Down, (0, 1)),
+        ].into_iter().find(|(key, _)| is_key_pressed(*key)).map(|(_, step)| step);
+        if let Some((dx, dy)) = step {
+            let (column, row) = match self.cursor {
+                // It starts in the middle of the board.
+                None => ((self.columns / 2) as i32, (self.rows / 2) as i32),
+                Some((column, row)) => {
+                    let column = (column + dx).clamp(-1, self.columns as i32);
+                    let in_reserve = column < 0 || column == self.columns as i32;
+                    let rows = if in_reserve { RESERVE_BOX_COUNT } else { self.rows };
+                    (column, (row + dy).clamp(0, rows as i32 - 1))
+                },
+            };
+            self.cursor = Some((column, row));
+        }
+    }
+
+    /// The square or reserve box under the cursor.
+    fn cursor_target(&self) -> Option<CursorTarget> {
+        let (column, row) = self.cursor?;
+        let (column, row) = (column as usize, row as usize);
+        if column < self.columns {
+            let (x, y) = if self.flipped { (self.columns - column - 1, row) } else { (column, self.rows - row - 1) };
+            return Some(CursorTarget::Square(Game::column_row_to_index(x, y)));
+        }
+        // The reserve left of the board is the top player's, filled downward; the one on
+        // the right is the bottom player's, filled upward.
+        let side = if column == self.columns { 0 } else { 1 };
+        let player = if self.flipped { 1 - side } else { side };
+        let reserve_index = if side == 0 { RESERVE_BOX_COUNT - row - 1 } else { row };
+        Some(CursorTarget::Reserve(player, reserve_index))
+    }
+
+    /// Sends what a click on the cursor's square or reserve box would.
+    fn click_cursor(&mut self) {
+        let message = match self.cursor_target() {
+            Some(CursorTarget::Square(index)) => match self.shown_game.grid[index] {
+                NONE => ViewGameMessage::SquareSelected(index),
+                id => ViewGameMessage::PieceSelected(id),
+            },
+            Some(CursorTarget::Reserve(player, reserve_index)) => {
+                let top_piece = self.reserve_stacks[player][reserve_index].last().copied();
+                ViewGameMessage::ReserveSelected(player, top_piece)
+            },
+            None => return,
+        };
+        self.tx.send(message).expect("ViewGame message send error.");
+    }
+
+    /// Reports drags of pieces, topmost first, and moves the dragged piece with the
+    /// mouse once the controller has selected it. A dropped piece slides back to where
+    /// it was; if the drop makes a move, the move takes it on from there. Returns true if
+    /// a drag ended.
+    fn process_drags(&mut self) -> bool {
+        let mut events = Vec::new();
+        for piece in self.pieces.iter_mut().rev() {
+            if let Some(event) = piece.process_events() {
+                events.push((piece.id.unwrap(), event));
+            }
+        }
+
+        let mut ended = false;
+        for (id, event) in events {
+            match event {
+                Event::DragStarted(start) if self.dragged_piece.is_none() => {
+                    let Some(piece) = self.piece_for_id(id) else { continue };
+                    let origin = piece.transform.position;
+                    let offset = (origin.0 - start.0, origin.1 - start.1);
+                    self.dragged_piece = Some(DraggedPiece { id, origin, offset });
+                    self.tx.send(ViewGameMessage::PieceDragStarted(id)).expect("ViewGame message send error.");
+                },
+                Event::Dragged(position) => {
+                    let Some(dragged) = self.dragged_piece else { continue };
+                    if dragged.id != id || self.selected_piece != Some(id) { continue }
+                    if let Some(piece) = self.piece_for_id(id) {
+                        piece.transform.position = (position.0 + dragged.offset.0, position.1 + dragged.offset.1);
+                    }
+                },
+                Event::DragEnded(position) => {
+                    ended = true;
+                    let Some(dragged) = self.dragged_piece else { continue };
+                    if dragged.id != id { continue }
+                    self.dragged_piece = None;
+                    if let Some(piece) = self.piece_for_id(id) {
+                        if piece.transform.position != dragged.origin {
+                            piece.move_to(dragged.origin, Duration::from_secs_f32(MOVE_DURATION));
+                        }
+                    }
+                    let index = self.squares.iter()
+                        .find(|(_, square)| square.contains_point(position))
+                        .map(|(index, _)| *index);
+                    self.tx.send(ViewGameMessage::PieceDropped(id, index)).expect("ViewGame message send error.");
+                },
+                _ => {},
+            }
+        }
+        ended
+    }
+
     pub fn update(&mut self, time_delta: Duration) -> bool {
         let mut update_active = false;
         for piece in &mut self.pieces {
@@ -325,8 +848,7 @@ impl ViewGame {
     }
 
     pub fn draw_board(&mut self) {
-        clear_background(Color::from_rgba(
-            BACKGROUND_COLOR.0, BACKGROUND_COLOR.1, BACKGROUND_COLOR.2, 255));
+        clear_background(self.colors.background);
         // Squares
         for square in &mut self.squares.values_mut() {
             square.draw();
@@ -343,28 +865,262 @@ impl ViewGame {
         //     }
         // }
         
-        // Pieces
+        // Overlays under the pieces
+        if self.overlays.last_move {
+            if let Some((from, to)) = self.last_move {
+                for index in from.into_iter().chain([to]) {
+                    let (x, y) = self.corner_position_for(index);
+                    draw_rectangle(x, y, SQUARE_SIZE, SQUARE_SIZE, LAST_MOVE_COLOR);
+                }
+            }
+        }
+        if self.overlays.check {
+            for id in self.kings_in_check.clone() {
+                if let Some(king) = self.piece_for_id(id) {
+                    let (x, y) = king.transform.position;
+                    draw_circle(x, y, CHECK_GLOW_RADIUS, CHECK_GLOW_COLOR);
+                }
+            }
+        }
+
+        // Pieces, with a dragged one on top
+        let dragged_id = self.dragged_piece.map(|dragged| dragged.id);
         for piece in &mut self.pieces {
-            piece.draw();
+            if piece.id != dragged_id {
+                piece.draw();
+            }
+        }
+        if self.overlays.hanging {
+            for id in self.hanging_pieces.clone() {
+                if let Some(piece) = self.piece_for_id(id) {
+                    let (x, y) = piece.transform.position;
+                    draw_circle_lines(x, y, HANGING_RADIUS, 3., HANGING_COLOR);
+                }
+            }
+        }
+        // Engine moves, then the user's marks
+        for (index, (from, to)) in self.pv_arrows.clone().into_iter().enumerate() {
+            let to_center = self.center_position_for(to);
+            if let Some(from) = from {
+                self.arrow.draw(self.center_position_for(from), to_center, Some(PV_ARROW_COLOR));
+            }
+            let number = &mut self.pv_numbers[index];
+            let (x, y) = number.transform.position;
+            draw_circle(x, y, PV_NUMBER_RADIUS, PV_ARROW_COLOR);
+            number.draw();
+        }
+        for annotation in &self.annotations {
+            match *annotation {
+                Annotation::Arrow(from, to) => {
+                    self.arrow.draw(self.center_position_for(from), self.center_position_for(to), None);
+                },
+                Annotation::Circle(index) => {
+                    let (x, y) = self.center_position_for(index);
+                    draw_circle_lines(x, y, ANNOTATION_CIRCLE_RADIUS, ARROW_THICKNESS / 2., ANNOTATION_COLOR);
+                },
+            }
+        }
+        // Count badges of reserve boxes with several pieces
+        for player in 0..2 {
+            for (reserve_index, badge) in self.count_badges[player].iter_mut().enumerate() {
+                if self.reserve_stacks[player][reserve_index].len() < 2 { continue }
+                let (x, y) = badge.transform.position;
+                draw_circle(x, y, COUNT_BADGE_RADIUS, COUNT_BADGE_COLOR);
+                badge.draw();
+            }
+        }
+        // Keyboard cursor
+        match self.cursor_target() {
+            Some(CursorTarget::Square(index)) => {
+                let (x, y) = self.corner_position_for(index);
+                draw_rectangle_lines(x, y, SQUARE_SIZE, SQUARE_SIZE, CURSOR_THICKNESS, CURSOR_COLOR);
+            },
+            Some(CursorTarget::Reserve(player, reserve_index)) => {
+                let reserve = &self.reserve_boxes[player][&reserve_index];
+                let (x, y) = reserve.transform.position;
+                let (w, h) = reserve.drawable.size;
+                draw_rectangle_lines(x - w / 2., y - h / 2., w, h, CURSOR_THICKNESS, CURSOR_COLOR);
+            },
+            None => {},
+        }
+        if let Some(id) = dragged_id {
+            if let Some(piece) = self.piece_for_id(id) {
+                piece.draw();
+            }
+        }
+    }
+
+    /// Names the kind in the reserve box under the mouse and how many are in hand,
+    /// beside the box, toward the board.
+    fn draw_hand_tooltip(&mut self) {
+        let mouse_pos = layout_mouse_position();
+        for player in 0..2 {
+            for (reserve_index, reserve) in &self.reserve_boxes[player] {
+                let stack = &self.reserve_stacks[player][*reserve_index];
+                if stack.is_empty() || !reserve.contains_point(mouse_pos) { continue }
+                let kind = match RESERVE_BOX_KINDS.get(*reserve_index) {
+                    Some(Pawn) => "Pawn",
+                    Some(Bishop) => "Bishop",
+                    Some(Rook) => "Rook",
+                    Some(Silver) => "Silver",
+                    Some(Gold) => "Gold",
+                    _ => "King",
+                };
+                let plural = if stack.len() == 1 { "" } else { "s" };
+                self.hand_tooltip.set_text(format!("{} {}{} in hand", stack.len(), kind, plural));
+
+                let (x, y) = reserve.transform.position;
+                let half_width = reserve.drawable.size.0 / 2.;
+                let width = self.hand_tooltip.width();
+                let left = if self.side_for(player) == 0 { x - half_width - HAND_TOOLTIP_GAP - width } else { x + half_width + HAND_TOOLTIP_GAP };
+                self.hand_tooltip.transform.position = (left, y);
+                draw_rectangle(left - 4., y - 10., width + 8., 20., HAND_TOOLTIP_COLOR);
+                self.hand_tooltip.draw();
+                return;
+            }
         }
     }
 
     pub fn draw_ui(&mut self, state: &AppState, other_text: &str) {
         // Status text
+        let how = match self.end_reason {
+            Some(EndReason::Time) => " on time",
+            Some(EndReason::Resignation) => " by resignation",
+            Some(EndReason::Checkmate) => " by checkmate",
+            _ => "",
+        };
         let text = match state {
-            HumanTurn => "Make move.",
-            AIThinking => "AI thinking...",
-            Player0Won => "Player 1 wins!",
-            Player1Won => "Player 2 wins!",
-            Draw => "The game is a draw.",
-            _ => {""},
+            HumanTurn => "Make move.".to_owned(),
+            AIThinking => "AI thinking...".to_owned(),
+            Analysis => "Analysis. Click a line to preview it.".to_owned(),
+            Editing => "Set up a position.".to_owned(),
+            Tutorial => "Follow the lesson below the board.".to_owned(),
+            Player0Won => format!("Player 1 wins{}!", how),
+            Player1Won => format!("Player 2 wins{}!", how),
+            Draw => match self.end_reason {
+                Some(EndReason::DrawAgreed) => "Draw agreed.",
+                Some(EndReason::Aborted) => "Game aborted.",
+                _ => "The game is a draw.",
+            }.to_owned(),
+            _ => String::new(),
         };
 
-        self.status_text.set_text(text.to_owned());
+        self.status_text.set_text(text);
         self.status_text.draw();
 
+        for label in &mut self.clock_texts {
+            label.draw();
+        }
+
         self.ai_progress_text.set_text(other_text.to_owned());
         self.ai_progress_text.draw();
+        if !self.typed_move.is_empty() {
+            self.typed_move_label.set_text(format!("move: {}", self.typed_move));
+            self.typed_move_label.draw();
+        }
+
+        for line in &mut self.analysis_lines {
+            line.draw();
+        }
+        // The analysis lines take the place of the graph and the move list.
+        // The editor's palette and the tutorial's text take the place of both.
+        let shows_game = !matches!(state, Analysis | Editing | Tutorial);
+        self.score_graph.is_visible = shows_game && self.score_graph.has_scores();
+        self.score_graph.draw();
+        self.move_list_visible = shows_game;
+        if self.move_list_visible {
+            for label in &mut self.move_list_labels {
+                label.draw();
+            }
+        }
+
+        self.draw_hand_tooltip();
+    }
+
+    /// Shows the scores, by ply, for player 0 in the score graph.
+    pub fn set_scores(&mut self, scores: &[Option<f64>]) {
+        self.score_graph.set_scores(scores);
+    }
+
+    /// Marks the ply of the position shown from history on the score graph, and the
+    /// move that led to it in the move list.
+    pub fn set_history_selection(&mut self, ply: Option<usize>) {
+        self.score_graph.selected = ply;
+        self.move_list_selected = ply.and_then(|ply| ply.checked_sub(1));
+        if let Some(index) = self.move_list_selected {
+            let row = index / MOVE_LIST_COLUMNS;
+            if row < self.move_list_top_row {
+                self.scroll_move_list(row);
+            } else if row >= self.move_list_top_row + MOVE_LIST_ROWS {
+                self.scroll_move_list(row + 1 - MOVE_LIST_ROWS);
+            }
+        }
+        self.update_move_list_labels();
+    }
+
+    /// Shows the moves played, as text such as "12.Sx13", each with any judgement from a
+    /// review. The list follows the latest move unless scrolled back.
+    pub fn set_move_list(&mut self, entries: Vec<(String, Option<Judgement>)>) {
+        let following = self.move_list_top_row >= self.last_move_list_row();
+        self.move_list = entries;
+        let last_row = self.last_move_list_row();
+        if following || self.move_list_top_row > last_row {
+            self.move_list_top_row = last_row;
+        }
+        self.update_move_list_labels();
+    }
+
+    /// The top row shown when scrolled to the end.
+    fn last_move_list_row(&self) -> usize {
+        self.move_list.len().div_ceil(MOVE_LIST_COLUMNS).saturating_sub(MOVE_LIST_ROWS)
+    }
+
+    fn scroll_move_list(&mut self, top_row: usize) {
+        self.move_list_top_row = top_row.min(self.last_move_list_row());
+        self.update_move_list_labels();
+    }
+
+    fn update_move_list_labels(&mut self) {
+        let first = self.move_list_top_row * MOVE_LIST_COLUMNS;
+        for (cell, label) in self.move_list_labels.iter_mut().enumerate() {
+            let index = first + cell;
+            let (text, judgement) = self.move_list.get(index).cloned().unwrap_or_default();
+            label.set_text(text);
+            label.set_color(match judgement {
+                _ if self.move_list_selected == Some(index) => MOVE_LIST_SELECTED_COLOR,
+                Some(Judgement::Inaccuracy) => INACCURACY_COLOR,
+                Some(Judgement::Mistake) => MISTAKE_COLOR,
+                Some(Judgement::Blunder) => BLUNDER_COLOR,
+                None => self.colors.text,
+            });
+        }
+    }
+
+    /// Shows the player's remaining time, in red when it is low. Empty hides it.
+    pub fn set_clock_text(&mut self, player: usize, text: String, low: bool) {
+        let label = &mut self.clock_texts[player];
+        label.set_text(text);
+        label.set_color(if low { CLOCK_LOW_COLOR } else { self.colors.text });
+    }
+
+    /// How the game ended, for the status text. None while it goes on.
+    pub fn set_end_reason(&mut self, reason: Option<EndReason>) {
+        self.end_reason = reason;
+    }
+
+    /// Shows the analysis lines, best first, with the selected one highlighted. An
+    /// empty vec hides them.
+    pub fn set_analysis_lines(&mut self, lines: Vec<String>, selected: Option<usize>) {
+        // Labels are kept for reuse, since each loads its font.
+        while self.analysis_lines.len() < lines.len() {
+            let rank = self.analysis_lines.len();
+            let position = (ANALYSIS_CORNER.0, ANALYSIS_CORNER.1 + ANALYSIS_LINE_HEIGHT * rank as f32);
+            self.analysis_lines.push(Label::new(position, false, "", 12, Some("Menlo")));
+        }
+        for (rank, label) in self.analysis_lines.iter_mut().enumerate() {
+            label.set_text(lines.get(rank).cloned().unwrap_or_default());
+            label.set_color(if selected == Some(rank) { ANALYSIS_SELECTED_COLOR } else { self.colors.text });
+        }
     }
 
     pub fn selected_piece_id(&self) -> Option<usize> {
@@ -404,6 +1160,33 @@ impl ViewGame {
         self.move_indices.contains(&index)
     }
 
+    /// Highlights a suggested move: the piece and the square it should go to.
+    pub fn show_hint(&mut self, id: usize, to_index: usize) {
+        self.clear_hint();
+        if let Some(piece) = self.piece_for_id(id) {
+            piece.use_alt_color = true;
+        }
+        if let Some(square) = self.squares.get_mut(&to_index) {
+            square.use_alt_color = true;
+        }
+        self.hint = Some((id, to_index));
+    }
+
+    /// Removes the hint highlights, keeping those of any selected piece.
+    pub fn clear_hint(&mut self) {
+        let Some((id, to_index)) = self.hint.take() else { return };
+        if self.selected_piece != Some(id) {
+            if let Some(piece) = self.piece_for_id(id) {
+                piece.use_alt_color = false;
+            }
+        }
+        if !self.move_indices.contains(&to_index) {
+            if let Some(square) = self.squares.get_mut(&to_index) {
+                square.use_alt_color = false;
+            }
+        }
+    }
+
     /// Does what is says on the tin.
     pub fn unhighlight_all_squares(&mut self) {
         for square in &mut self.squares.values_mut() {
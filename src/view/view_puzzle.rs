@@ -0,0 +1,82 @@
+// ViewPuzzle
+// Controls for the mate puzzles: move between puzzles, try one again, show its
+// solution, or go back to normal play, with the puzzle's name above the board.
+
+use std::sync::mpsc::Sender;
+
+use crate::view::button::{Button, ButtonEvent};
+use crate::view::label::Label;
+
+// Widget IDs
+const PREVIOUS_ID: usize = 0;
+const NEXT_ID: usize = 1;
+const RETRY_ID: usize = 2;
+const SOLUTION_ID: usize = 3;
+const EXIT_ID: usize = 4;
+
+pub enum ViewPuzzleMessage {
+    Previous,
+    Next,
+    Retry,
+    ShowSolution,
+    Exit,
+}
+
+pub struct ViewPuzzle {
+    /// Sends messages to controller.
+    tx: Sender<ViewPuzzleMessage>,
+
+    buttons: Vec<Button>,
+    title_label: Label,
+}
+
+impl ViewPuzzle {
+    pub fn new(tx: Sender<ViewPuzzleMessage>) -> Self {
+        let buttons = vec![
+            Button::new((165., 33.), 0, "Prev", Some(PREVIOUS_ID)),
+            Button::new((229., 33.), 0, "Next", Some(NEXT_ID)),
+            Button::new((293., 33.), 0, "Retry", Some(RETRY_ID)),
+            Button::new((357., 33.), 1, "Solution", Some(SOLUTION_ID)),
+            Button::new((441., 33.), 0, "Exit", Some(EXIT_ID)),
+        ];
+
+        Self {
+            tx,
+            buttons,
+            title_label: Label::new((400., 80.), true, "", 14, Some("Menlo")),
+        }
+    }
+
+    /// Names the puzzle being solved.
+    pub fn set_title(&mut self, text: String) {
+        self.title_label.set_text(text);
+    }
+
+    pub fn process_events(&mut self) {
+        let mut pushed = None;
+        for button in &mut self.buttons {
+            if let Some(ButtonEvent::Pushed(id)) = button.process_events() {
+                pushed = id;
+            }
+        }
+        match pushed {
+            Some(PREVIOUS_ID) => self.send(ViewPuzzleMessage::Previous),
+            Some(NEXT_ID) => self.send(ViewPuzzleMessage::Next),
+            Some(RETRY_ID) => self.send(ViewPuzzleMessage::Retry),
+            Some(SOLUTION_ID) => self.send(ViewPuzzleMessage::ShowSolution),
+            Some(EXIT_ID) => self.send(ViewPuzzleMessage::Exit),
+            _ => {},
+        }
+    }
+
+    fn send(&self, message: ViewPuzzleMessage) {
+        self.tx.send(message).expect("Puzzle message send error.");
+    }
+
+    pub fn draw(&mut self) {
+        for button in &mut self.buttons {
+            button.draw();
+        }
+        self.title_label.draw();
+    }
+}
@@ -11,12 +11,18 @@ use crate::view::image::Image;
 
 pub enum ViewRulesMessage {
     ShouldClose,
+    /// Close and start the mate puzzles.
+    Puzzles,
+    /// Close and start the tutorial.
+    Tutorial,
 }
 
 pub struct ViewRules {
     tx: Sender<ViewRulesMessage>, 
     image: Image,
     okay_button: Button,
+    puzzles_button: Button,
+    tutorial_button: Button,
 }
 
 impl ViewRules {
@@ -27,6 +33,8 @@ impl ViewRules {
             tx,
             image: Image::new((0., 0.), texture, false, None),
             okay_button: Button::new((370., 760.), 0, "Okay", None),
+            puzzles_button: Button::new((280., 760.), 1, "Puzzles", None),
+            tutorial_button: Button::new((190., 760.), 1, "Tutorial", None),
         }
     }
 
@@ -40,6 +48,12 @@ impl ViewRules {
         if event_opt == Some(ButtonEvent::Pushed(None)) {
             self.send_close_message();
         }
+        if self.puzzles_button.process_events() == Some(ButtonEvent::Pushed(None)) {
+            self.tx.send(ViewRulesMessage::Puzzles).expect("Rules message send error.");
+        }
+        if self.tutorial_button.process_events() == Some(ButtonEvent::Pushed(None)) {
+            self.tx.send(ViewRulesMessage::Tutorial).expect("Rules message send error.");
+        }
     }
 
     fn send_close_message(&self) {
@@ -48,5 +62,8 @@ impl ViewRules {
 
     pub fn draw(&mut self) {
         self.image.draw();
-        self.okay_button.draw();    }
+        self.okay_button.draw();
+        self.puzzles_button.draw();
+        self.tutorial_button.draw();
+    }
 }
\ No newline at end of file
@@ -4,9 +4,12 @@
 use std::sync::mpsc::Sender;
 
 use crate::asset_loader::AssetLoader;
+use crate::clock::TimeControl;
 use crate::controller::Player;
 use crate::controller::PlayerKind::*;
-use crate::view::button::Button;
+use crate::strength::Strength;
+use crate::theme::ThemeChoice;
+use crate::view::button::{Button, ButtonEvent, ButtonMode};
 use crate::view::button_bar::ButtonBar;
 use crate::view::button_bar::ButtonBarOrientation;
 use crate::view::image::Image;
@@ -14,13 +17,22 @@ use crate::view::label::Label;
 use crate::view::slider::Slider;
 use crate::view::slider::SliderEvent;
 use crate::view::transform::Transform;
+use crate::view::view_game::Overlays;
 
 // Widget IDs
 const HUMAN_ID: usize = 0;
 const AI_ID: usize = 1;
+const BOOK_ID: usize = 2;
+const PONDER_ID: usize = 3;
+const CLOCK_ID: usize = 4;
+const LAST_MOVE_ID: usize = 5;
+const CHECK_ID: usize = 6;
+const HANGING_ID: usize = 7;
+const PIECES_ID: usize = 8;
+const COLORS_ID: usize = 9;
 
 pub enum ViewSettingsMessage {
-    ShouldStart(Vec<Player>),
+    ShouldStart(Vec<Player>, TimeControl, Overlays, ThemeChoice),
 }
 
 pub struct ViewSettings {
@@ -30,16 +42,34 @@ pub struct ViewSettings {
     transform: Transform, // the position of this Settings panel
     background_image: Image,
     okay_button: Button,
+    clock_button: Button, // steps through the time controls
+    clock_label: Label,
+    last_move_button: Button, // overlay toggles
+    check_button: Button,
+    hanging_button: Button,
+    pieces_button: Button, // steps through the piece sets
+    pieces_label: Label,
+    colors_button: Button, // steps through the colour schemes
+    colors_label: Label,
 
     button_bar_0: ButtonBar,
     slider_0: Slider,
     slider_0_label: Label,
+    book_button_0: Button,
+    ponder_button_0: Button,
 
     button_bar_1: ButtonBar,
     slider_1: Slider,
     slider_1_label: Label,
+    book_button_1: Button,
+    ponder_button_1: Button,
 
     players: Vec<Player>,
+    time_control: TimeControl,
+    overlays: Overlays,
+    theme: ThemeChoice,
+    piece_set_names: Vec<String>,
+    color_scheme_names: Vec<String>,
 }
 
 impl ViewSettings {
@@ -53,24 +83,51 @@ impl ViewSettings {
             // All the following positions are relative to 'transform'.
 
             background_image: Image::new((0., 0.), texture, false, None),
-            okay_button: Button::new((170., 310.), 0, "Okay", None),
+            okay_button: Button::new((330., 300.), 0, "Okay", None),
+            clock_button: Button::new((20., 300.), 0, "Clock", Some(CLOCK_ID)),
+            clock_label: Label::new((90., 312.), false, "", 14, Some("Menlo")),
+            last_move_button: Button::new((20., 330.), 1, "Last move", Some(LAST_MOVE_ID)),
+            check_button: Button::new((104., 330.), 0, "Check", Some(CHECK_ID)),
+            hanging_button: Button::new((168., 330.), 1, "Hanging", Some(HANGING_ID)),
+            pieces_button: Button::new((20., 150.), 0, "Pieces", Some(PIECES_ID)),
+            pieces_label: Label::new((88., 162.), false, "", 14, Some("Menlo")),
+            colors_button: Button::new((200., 150.), 0, "Colors", Some(COLORS_ID)),
+            colors_label: Label::new((268., 162.), false, "", 14, Some("Menlo")),
 
             // Top player (1)
             button_bar_1: ButtonBar::new((179., 45.), ButtonBarOrientation::Horizontal, 25.),
             slider_1: Slider::new((100., 100.), 200., 1., 1., 1., 0),
             slider_1_label: Label::new((200., 125.), true, "slider 1", 14, Some("Menlo")),
+            book_button_1: Button::new((320., 88.), 0, "Book", Some(BOOK_ID)),
+            ponder_button_1: Button::new((320., 116.), 0, "Ponder", Some(PONDER_ID)),
 
             // Botton player (0)
             button_bar_0: ButtonBar::new((179., 191.), ButtonBarOrientation::Horizontal, 25.),
             slider_0: Slider::new((100., 245.), 200., 1., 1., 1., 1),
             slider_0_label: Label::new((200., 270.), true, "slider 0", 14, Some("Menlo")),
+            book_button_0: Button::new((320., 233.), 0, "Book", Some(BOOK_ID)),
+            ponder_button_0: Button::new((320., 261.), 0, "Ponder", Some(PONDER_ID)),
           
             players: Vec::new(),
+            time_control: TimeControl::Unlimited,
+            overlays: Overlays::default(),
+            theme: ThemeChoice::default(),
+            piece_set_names: Vec::new(),
+            color_scheme_names: Vec::new(),
         }
     }
 
-    pub fn prepare(&mut self, players: Vec<Player>) {        
+    /// The names of the piece sets and colour schemes to choose from, in order.
+    pub fn set_theme_names(&mut self, piece_sets: Vec<String>, color_schemes: Vec<String>) {
+        self.piece_set_names = piece_sets;
+        self.color_scheme_names = color_schemes;
+    }
+
+    pub fn prepare(&mut self, players: Vec<Player>, time_control: TimeControl, overlays: Overlays, theme: ThemeChoice) {        
         self.players = players;
+        self.time_control = time_control;
+        self.overlays = overlays;
+        self.theme = theme;
 
         // Player 0
         let mut button = Button::new((0., 0.), 1, "Human", Some(HUMAN_ID));
@@ -86,6 +143,30 @@ impl ViewSettings {
         button = Button::new((0., 0.), 1, "AI", Some(AI_ID));
         self.button_bar_1.add_button(button);
 
+        // Opening book and pondering toggles
+        self.book_button_0.mode = ButtonMode::Toggle;
+        self.book_button_1.mode = ButtonMode::Toggle;
+        self.ponder_button_0.mode = ButtonMode::Toggle;
+        self.ponder_button_1.mode = ButtonMode::Toggle;
+
+        // Theme, the next piece set or colour scheme each click
+        if self.pieces_button.process_events().is_some() && !self.piece_set_names.is_empty() {
+            self.theme.piece_set = (self.theme.piece_set + 1) % self.piece_set_names.len();
+        }
+        if self.colors_button.process_events().is_some() && !self.color_scheme_names.is_empty() {
+            self.theme.color_scheme = (self.theme.color_scheme + 1) % self.color_scheme_names.len();
+        }
+
+        // Overlay toggles
+        for (button, on) in [
+            (&mut self.last_move_button, overlays.last_move),
+            (&mut self.check_button, overlays.check),
+            (&mut self.hanging_button, overlays.hanging),
+        ] {
+            button.mode = ButtonMode::Toggle;
+            button.set_selected(on);
+        }
+
         self.set_player_controls(0);
         self.set_player_controls(1);
     }
@@ -110,18 +191,25 @@ impl ViewSettings {
 
             match self.players[0].kind {
                 Human => {
-                    self.slider_0.is_visible = false;
-                    self.slider_0_label.draw_text.visible = false;
+                    // The slider sets the hint strength.
+                    self.book_button_0.texture_drawable.visible = false;
+                    self.ponder_button_0.texture_drawable.visible = false;
+                    self.slider_0.value = self.players[0].hint_strength.level() as f32;
                 },
                 AI => {
-                    self.slider_0.is_visible = true;
-                    self.slider_0_label.draw_text.visible = true;
-                    self.slider_0.value = self.players[0].search_depth as f32;
-                    self.slider_0.max_value = 9.;
-                    self.slider_0.tick_divisions = 7;
-                    self.slider_0.snap_to_tick = true;
+                    self.book_button_0.texture_drawable.visible = true;
+                    self.book_button_0.set_selected(self.players[0].use_book);
+                    self.ponder_button_0.texture_drawable.visible = true;
+                    self.ponder_button_0.set_selected(self.players[0].ponder);
+                    self.slider_0.value = self.players[0].strength.level() as f32;
                 },
             }
+            self.slider_0.is_visible = true;
+            self.slider_0_label.draw_text.visible = true;
+            self.slider_0.min_value = 0.;
+            self.slider_0.max_value = (Strength::ALL.len() - 1) as f32;
+            self.slider_0.tick_divisions = Strength::ALL.len() - 2;
+            self.slider_0.snap_to_tick = true;
         }
 
         if player_id == 1 {
@@ -133,27 +221,52 @@ impl ViewSettings {
     
             match self.players[1].kind {
                 Human => {
-                    self.slider_1.is_visible = false;
-                    self.slider_1_label.draw_text.visible = false;
+                    // The slider sets the hint strength.
+                    self.book_button_1.texture_drawable.visible = false;
+                    self.ponder_button_1.texture_drawable.visible = false;
+                    self.slider_1.value = self.players[1].hint_strength.level() as f32;
                 },
                 AI => {
-                    self.slider_1.is_visible = true;
-                    self.slider_1_label.draw_text.visible = true;
-                    self.slider_1.value = self.players[1].search_depth as f32;
-                    self.slider_1.max_value = 9.;
-                    self.slider_1.tick_divisions = 7;
-                    self.slider_1.snap_to_tick = true;
+                    self.book_button_1.texture_drawable.visible = true;
+                    self.book_button_1.set_selected(self.players[1].use_book);
+                    self.ponder_button_1.texture_drawable.visible = true;
+                    self.ponder_button_1.set_selected(self.players[1].ponder);
+                    self.slider_1.value = self.players[1].strength.level() as f32;
                 },
             }
+            self.slider_1.is_visible = true;
+            self.slider_1_label.draw_text.visible = true;
+            self.slider_1.min_value = 0.;
+            self.slider_1.max_value = (Strength::ALL.len() - 1) as f32;
+            self.slider_1.tick_divisions = Strength::ALL.len() - 2;
+            self.slider_1.snap_to_tick = true;
         }
     }
 
     pub fn process_events(&mut self) {
         if self.okay_button.process_events().is_some() {
-            self.tx.send(ViewSettingsMessage::ShouldStart(self.players.clone()))
+            self.tx.send(ViewSettingsMessage::ShouldStart(self.players.clone(), self.time_control, self.overlays, self.theme))
             .expect("Intro message send error.");
         }
 
+        // Time control, the next preset each click
+        if self.clock_button.process_events().is_some() {
+            let presets = &TimeControl::PRESETS;
+            let index = presets.iter().position(|tc| *tc == self.time_control).map_or(0, |i| (i + 1) % presets.len());
+            self.time_control = presets[index];
+        }
+
+        // Overlay toggles
+        if let Some(ButtonEvent::Toggled(_id)) = self.last_move_button.process_events() {
+            self.overlays.last_move = self.last_move_button.selected();
+        }
+        if let Some(ButtonEvent::Toggled(_id)) = self.check_button.process_events() {
+            self.overlays.check = self.check_button.selected();
+        }
+        if let Some(ButtonEvent::Toggled(_id)) = self.hanging_button.process_events() {
+            self.overlays.hanging = self.hanging_button.selected();
+        }
+
         // ButtonBar 0
         if let Some(button_id) = self.button_bar_0.process_events() {
             self.button_bar_0.select_only(button_id);
@@ -168,13 +281,29 @@ impl ViewSettings {
             self.set_player_controls(1);
         }
                 
+        // Book and ponder toggles
+        if let Some(ButtonEvent::Toggled(_id)) = self.book_button_0.process_events() {
+            self.players[0].use_book = self.book_button_0.selected();
+        }
+        if let Some(ButtonEvent::Toggled(_id)) = self.ponder_button_0.process_events() {
+            self.players[0].ponder = self.ponder_button_0.selected();
+        }
+        if let Some(ButtonEvent::Toggled(_id)) = self.book_button_1.process_events() {
+            self.players[1].use_book = self.book_button_1.selected();
+        }
+        if let Some(ButtonEvent::Toggled(_id)) = self.ponder_button_1.process_events() {
+            self.players[1].ponder = self.ponder_button_1.selected();
+        }
+
         // Slider 0. Sliders return Option<SliderEvent>.
         if let Some(event) = self.slider_0.process_events() {
             match event {
                 SliderEvent::Hovering(_id) => {},
                 SliderEvent::ValueChanged(_id, val) => {
-                    if self.players[0].kind == AI {
-                        self.players[0].search_depth = val as usize;
+                    let strength = Strength::from_level(val.round() as usize);
+                    match self.players[0].kind {
+                        Human => self.players[0].hint_strength = strength,
+                        AI => self.players[0].strength = strength,
                     }
                 },
             }
@@ -184,8 +313,10 @@ impl ViewSettings {
             match event {
                 SliderEvent::Hovering(_id) => {},
                 SliderEvent::ValueChanged(_id, val) => {
-                    if self.players[1].kind == AI {
-                        self.players[1].search_depth = val as usize;
+                    let strength = Strength::from_level(val.round() as usize);
+                    match self.players[1].kind {
+                        Human => self.players[1].hint_strength = strength,
+                        AI => self.players[1].strength = strength,
                     }
                 },
             }
@@ -199,6 +330,28 @@ impl ViewSettings {
         self.okay_button.transform.set_parent(self.transform);
         self.okay_button.draw();
 
+        self.clock_button.transform.set_parent(self.transform);
+        self.clock_button.draw();
+        self.clock_label.set_text(self.time_control.name());
+        self.clock_label.transform.set_parent(self.transform);
+        self.clock_label.draw();
+
+        self.pieces_label.set_text(self.piece_set_names.get(self.theme.piece_set).cloned().unwrap_or_default());
+        self.colors_label.set_text(self.color_scheme_names.get(self.theme.color_scheme).cloned().unwrap_or_default());
+        for button in [&mut self.pieces_button, &mut self.colors_button] {
+            button.transform.set_parent(self.transform);
+            button.draw();
+        }
+        for label in [&mut self.pieces_label, &mut self.colors_label] {
+            label.transform.set_parent(self.transform);
+            label.draw();
+        }
+
+        for button in [&mut self.last_move_button, &mut self.check_button, &mut self.hanging_button] {
+            button.transform.set_parent(self.transform);
+            button.draw();
+        }
+
         self.button_bar_0.transform.set_parent(self.transform);
         self.button_bar_0.draw();
 
@@ -209,24 +362,44 @@ impl ViewSettings {
         self.slider_0.draw();
 
         // Use live values here so user sees the values change when dragging.
+        let strength_0 = Strength::from_level(self.slider_0.nearest_snap_value().round() as usize);
         let text_0 = match self.players[0].kind {
-            Human => "".to_string(),
-            AI => format!("{} move look-ahead", self.slider_0.nearest_snap_value()),
+            Human => format!("Hints: {}", strength_0.name()),
+            AI => strength_0.name().to_string(),
         };
         self.slider_0_label.set_text(text_0);
         self.slider_0_label.transform.set_parent(self.transform);
         self.slider_0_label.draw();
 
+        if self.book_button_0.texture_drawable.visible {
+            self.book_button_0.transform.set_parent(self.transform);
+            self.book_button_0.draw();
+        }
+        if self.ponder_button_0.texture_drawable.visible {
+            self.ponder_button_0.transform.set_parent(self.transform);
+            self.ponder_button_0.draw();
+        }
+
         self.slider_1.transform.set_parent(self.transform);
         self.slider_1.draw();
 
         // Use live values here so user sees the values change when dragging.
+        let strength_1 = Strength::from_level(self.slider_1.nearest_snap_value().round() as usize);
         let text_1 = match self.players[1].kind {
-            Human => "".to_string(),
-            AI => format!("{} move look-ahead", self.slider_1.nearest_snap_value() as usize),
+            Human => format!("Hints: {}", strength_1.name()),
+            AI => strength_1.name().to_string(),
         };
         self.slider_1_label.set_text(text_1);
         self.slider_1_label.transform.set_parent(self.transform);
         self.slider_1_label.draw();
+
+        if self.book_button_1.texture_drawable.visible {
+            self.book_button_1.transform.set_parent(self.transform);
+            self.book_button_1.draw();
+        }
+        if self.ponder_button_1.texture_drawable.visible {
+            self.ponder_button_1.transform.set_parent(self.transform);
+            self.ponder_button_1.draw();
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,119 @@
+// ViewSpectator
+// Controls for watching the AI play itself: pause, step, the delay between moves and
+// the number of games to play in a row, with the tally so far.
+
+use std::sync::mpsc::Sender;
+
+use crate::view::button::{Button, ButtonEvent, ButtonMode};
+use crate::view::label::Label;
+use crate::view::slider::{Slider, SliderEvent};
+
+// Widget IDs
+const PAUSE_ID: usize = 0;
+const STEP_ID: usize = 1;
+const DELAY_ID: usize = 2;
+const GAMES_ID: usize = 3;
+
+/// The longest delay between moves, in seconds.
+const MAX_DELAY: f32 = 3.0;
+const MAX_GAMES: usize = 20;
+
+pub enum ViewSpectatorMessage {
+    Paused(bool),
+    /// Play one move, pausing first if needed.
+    Step,
+    DelayChanged(f32), // seconds
+    GamesChanged(usize),
+}
+
+pub struct ViewSpectator {
+    /// Sends messages to controller.
+    tx: Sender<ViewSpectatorMessage>,
+
+    pause_button: Button,
+    step_button: Button,
+    delay_label: Label,
+    delay_slider: Slider,
+    games_label: Label,
+    games_slider: Slider,
+    tally_label: Label,
+}
+
+impl ViewSpectator {
+    pub fn new(tx: Sender<ViewSpectatorMessage>, delay: f32, games: usize) -> Self {
+        let mut pause_button = Button::new((165., 33.), 0, "Pause", Some(PAUSE_ID));
+        pause_button.mode = ButtonMode::Toggle;
+
+        let mut delay_slider = Slider::new((410., 45.), 150., delay, 0., MAX_DELAY, DELAY_ID);
+        delay_slider.tick_divisions = (MAX_DELAY * 2.) as usize - 1; // half seconds
+        delay_slider.snap_to_tick = true;
+        delay_slider.value_marker_radius = 7.;
+
+        let mut games_slider = Slider::new((410., 78.), 150., games as f32, 1., MAX_GAMES as f32, GAMES_ID);
+        games_slider.tick_divisions = MAX_GAMES - 2;
+        games_slider.snap_to_tick = true;
+        games_slider.show_ticks = false;
+        games_slider.value_marker_radius = 7.;
+
+        Self {
+            tx,
+            pause_button,
+            step_button: Button::new((229., 33.), 0, "Step", Some(STEP_ID)),
+            delay_label: Label::new((300., 45.), false, "", 14, Some("Menlo")),
+            delay_slider,
+            games_label: Label::new((300., 78.), false, "", 14, Some("Menlo")),
+            games_slider,
+            tally_label: Label::new((400., 100.), true, "", 12, Some("Menlo")),
+        }
+    }
+
+    /// Shows whether play is paused, for changes made elsewhere.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.pause_button.set_selected(paused);
+        self.pause_button.text_drawable.text = if paused { "Resume" } else { "Pause" }.to_string();
+    }
+
+    pub fn set_tally(&mut self, text: String) {
+        self.tally_label.set_text(text);
+    }
+
+    pub fn process_events(&mut self) {
+        if let Some(ButtonEvent::Toggled(_id)) = self.pause_button.process_events() {
+            let paused = self.pause_button.selected();
+            self.set_paused(paused);
+            self.send(ViewSpectatorMessage::Paused(paused));
+        }
+        if self.step_button.process_events().is_some() {
+            self.set_paused(true);
+            self.send(ViewSpectatorMessage::Step);
+        }
+        if let Some(SliderEvent::ValueChanged(_id, value)) = self.delay_slider.process_events() {
+            self.send(ViewSpectatorMessage::DelayChanged(value));
+        }
+        if let Some(SliderEvent::ValueChanged(_id, value)) = self.games_slider.process_events() {
+            self.send(ViewSpectatorMessage::GamesChanged(value.round() as usize));
+        }
+    }
+
+    fn send(&self, message: ViewSpectatorMessage) {
+        self.tx.send(message).expect("Spectator message send error.");
+    }
+
+    pub fn draw(&mut self) {
+        self.pause_button.draw();
+        self.step_button.draw();
+
+        // Use live values here so user sees the values change when dragging.
+        let delay = self.delay_slider.nearest_snap_value();
+        self.delay_label.set_text(format!("Delay: {:.1}s", delay));
+        self.delay_label.draw();
+        self.delay_slider.draw();
+
+        let games = self.games_slider.nearest_snap_value().round() as usize;
+        self.games_label.set_text(format!("Games: {}", games));
+        self.games_label.draw();
+        self.games_slider.draw();
+
+        self.tally_label.draw();
+    }
+}
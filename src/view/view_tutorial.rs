@@ -0,0 +1,121 @@
+// ViewTutorial
+// The tutorial's controls and text: buttons to move between lessons, try one again or
+// leave, the lesson's title above the board, and its instructions below it.
+
+use std::sync::mpsc::Sender;
+
+use crate::view::button::{Button, ButtonEvent};
+use crate::view::label::Label;
+
+// Widget IDs
+const PREVIOUS_ID: usize = 0;
+const NEXT_ID: usize = 1;
+const RETRY_ID: usize = 2;
+const EXIT_ID: usize = 3;
+
+/// The instructions fill the space below the board, between the reserves.
+const TEXT_CORNER: (f32, f32) = (170., 672.);
+const LINE_HEIGHT: f32 = 15.;
+const LINE_CHARS: usize = 64;
+
+pub enum ViewTutorialMessage {
+    Previous,
+    Next,
+    Retry,
+    Exit,
+}
+
+pub struct ViewTutorial {
+    /// Sends messages to controller.
+    tx: Sender<ViewTutorialMessage>,
+
+    buttons: Vec<Button>,
+    title_label: Label,
+    text_labels: Vec<Label>, // one per line, grown as needed
+    line_count: usize, // the labels in use
+}
+
+impl ViewTutorial {
+    pub fn new(tx: Sender<ViewTutorialMessage>) -> Self {
+        let buttons = vec![
+            Button::new((165., 33.), 0, "Prev", Some(PREVIOUS_ID)),
+            Button::new((229., 33.), 0, "Next", Some(NEXT_ID)),
+            Button::new((293., 33.), 0, "Retry", Some(RETRY_ID)),
+            Button::new((357., 33.), 0, "Exit", Some(EXIT_ID)),
+        ];
+
+        Self {
+            tx,
+            buttons,
+            title_label: Label::new((400., 80.), true, "", 14, Some("Menlo")),
+            text_labels: Vec::new(),
+            line_count: 0,
+        }
+    }
+
+    pub fn set_title(&mut self, text: String) {
+        self.title_label.set_text(text);
+    }
+
+    /// Shows the instructions, wrapped to fit. A newline starts a new paragraph.
+    pub fn set_instructions(&mut self, text: &str) {
+        let lines = Self::wrap(text, LINE_CHARS);
+        for (index, line) in lines.iter().enumerate() {
+            if index == self.text_labels.len() {
+                let position = (TEXT_CORNER.0, TEXT_CORNER.1 + LINE_HEIGHT * index as f32);
+                self.text_labels.push(Label::new(position, false, "", 12, Some("Menlo")));
+            }
+            self.text_labels[index].set_text(line.clone());
+        }
+        self.line_count = lines.len();
+    }
+
+    /// Breaks each paragraph into lines of at most width characters, between words.
+    fn wrap(text: &str, width: usize) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                if !line.is_empty() && line.len() + 1 + word.len() > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    pub fn process_events(&mut self) {
+        let mut pushed = None;
+        for button in &mut self.buttons {
+            if let Some(ButtonEvent::Pushed(id)) = button.process_events() {
+                pushed = id;
+            }
+        }
+        match pushed {
+            Some(PREVIOUS_ID) => self.send(ViewTutorialMessage::Previous),
+            Some(NEXT_ID) => self.send(ViewTutorialMessage::Next),
+            Some(RETRY_ID) => self.send(ViewTutorialMessage::Retry),
+            Some(EXIT_ID) => self.send(ViewTutorialMessage::Exit),
+            _ => {},
+        }
+    }
+
+    fn send(&self, message: ViewTutorialMessage) {
+        self.tx.send(message).expect("Tutorial message send error.");
+    }
+
+    pub fn draw(&mut self) {
+        for button in &mut self.buttons {
+            button.draw();
+        }
+        self.title_label.draw();
+        for label in self.text_labels.iter_mut().take(self.line_count) {
+            label.draw();
+        }
+    }
+}